@@ -0,0 +1,384 @@
+// 按 AggregationConfig 树遍历聚合结果，展开为表格和图表序列
+use crate::es_client::MULTI_BUCKET_AGGREGATIONS;
+use crate::types::{AggregationChart, AggregationConfig, AggregationTable, ChartSeries, FlattenedAggregation};
+use serde_json::Value;
+
+// 只有一个桶、不产生键列的聚合，直接下钻到其子聚合
+const SINGLE_BUCKET_AGGREGATIONS: &[&str] = &["filter", "nested", "reverse_nested", "sampler", "missing"];
+
+/// 将聚合结果展开为一行一个叶子桶的表格：每层桶聚合一列键和一列 `<名称>.doc_count`，随后是各度量列。
+/// 同一层有多个桶聚合时只展开第一个，其余的在 warnings 中列出。
+pub fn flatten_aggregations(configs: &[AggregationConfig], aggregations: &Value) -> FlattenedAggregation {
+    let mut builder = TableBuilder::default();
+    let (key_columns, ignored) = bucket_chain(configs);
+
+    walk_level(configs, aggregations, &mut Vec::new(), &mut builder);
+
+    let table = builder.finish();
+    let chart = build_chart(&table, &key_columns);
+    let warnings = match ignored.is_empty() {
+        true => Vec::new(),
+        false => vec![format!("同一层只能展开一个桶聚合，以下聚合未包含在表格中：{}", ignored.join(", "))],
+    };
+    FlattenedAggregation { table, chart, warnings }
+}
+
+// 沿第一个桶聚合往下的名称链，即表格的键列；同时返回各层被跳过的其他桶聚合
+fn bucket_chain(configs: &[AggregationConfig]) -> (Vec<String>, Vec<String>) {
+    let mut chain = Vec::new();
+    let mut ignored = Vec::new();
+    let mut level = configs;
+
+    loop {
+        let mut buckets = level.iter().filter(|c| is_bucket(c) || is_single_bucket(c));
+        match buckets.next() {
+            Some(config) => {
+                ignored.extend(buckets.map(|c| c.name.clone()));
+                if is_bucket(config) {
+                    chain.push(config.name.clone());
+                }
+                level = config.sub_aggregations.as_deref().unwrap_or_default();
+            }
+            None => return (chain, ignored),
+        }
+    }
+}
+
+fn doc_count_column(bucket_name: &str) -> String {
+    format!("{}.doc_count", bucket_name)
+}
+
+fn is_bucket(config: &AggregationConfig) -> bool {
+    MULTI_BUCKET_AGGREGATIONS.contains(&config.r#type.as_str())
+}
+
+fn is_single_bucket(config: &AggregationConfig) -> bool {
+    SINGLE_BUCKET_AGGREGATIONS.contains(&config.r#type.as_str())
+}
+
+#[derive(Default)]
+struct TableBuilder {
+    columns: Vec<String>,
+    rows: Vec<Vec<(usize, Value)>>,
+}
+
+impl TableBuilder {
+    fn column(&mut self, name: &str) -> usize {
+        match self.columns.iter().position(|c| c == name) {
+            Some(index) => index,
+            None => {
+                self.columns.push(name.to_string());
+                self.columns.len() - 1
+            }
+        }
+    }
+
+    fn push_row(&mut self, cells: &[(String, Value)]) {
+        let row = cells.iter().map(|(name, value)| (self.column(name), value.clone())).collect();
+        self.rows.push(row);
+    }
+
+    fn finish(self) -> AggregationTable {
+        let width = self.columns.len();
+        let rows = self
+            .rows
+            .into_iter()
+            .map(|cells| {
+                let mut row = vec![Value::Null; width];
+                for (index, value) in cells {
+                    row[index] = value;
+                }
+                row
+            })
+            .collect();
+
+        AggregationTable { columns: self.columns, rows }
+    }
+}
+
+// cells 为当前路径上已经收集的键和度量
+fn walk_level(configs: &[AggregationConfig], result: &Value, cells: &mut Vec<(String, Value)>, builder: &mut TableBuilder) {
+    let depth = cells.len();
+
+    for config in configs.iter().filter(|c| !is_bucket(c) && !is_single_bucket(c)) {
+        push_metric(&config.name, &result[&config.name], cells);
+    }
+
+    let child = configs.iter().find(|c| is_bucket(c) || is_single_bucket(c));
+    match child {
+        Some(config) if is_single_bucket(config) => {
+            let sub_configs = config.sub_aggregations.as_deref().unwrap_or_default();
+            walk_level(sub_configs, &result[&config.name], cells, builder);
+        }
+        Some(config) => {
+            let sub_configs = config.sub_aggregations.as_deref().unwrap_or_default();
+            let buckets = bucket_entries(&result[&config.name]);
+
+            if buckets.is_empty() {
+                cells.push((config.name.clone(), Value::Null));
+                builder.push_row(cells);
+            }
+
+            for (key, bucket) in buckets {
+                let bucket_depth = cells.len();
+                cells.push((config.name.clone(), key));
+                cells.push((doc_count_column(&config.name), bucket["doc_count"].clone()));
+                walk_level(sub_configs, bucket, cells, builder);
+                cells.truncate(bucket_depth);
+            }
+        }
+        None => builder.push_row(cells),
+    }
+
+    cells.truncate(depth);
+}
+
+// 桶可能是数组，也可能是 keyed 对象（如 filters）
+fn bucket_entries(agg_result: &Value) -> Vec<(Value, &Value)> {
+    match &agg_result["buckets"] {
+        Value::Array(buckets) => buckets.iter().map(|b| (bucket_key(b), b)).collect(),
+        Value::Object(buckets) => buckets.iter().map(|(name, b)| (Value::from(name.as_str()), b)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn bucket_key(bucket: &Value) -> Value {
+    if let Some(key) = bucket.get("key_as_string") {
+        return key.clone();
+    }
+    match bucket.get("key") {
+        // multi_terms 的键是数组
+        Some(Value::Array(parts)) => Value::from(
+            parts.iter().map(|p| p.as_str().map(str::to_string).unwrap_or_else(|| p.to_string())).collect::<Vec<_>>().join("|"),
+        ),
+        Some(key) => key.clone(),
+        None => Value::Null,
+    }
+}
+
+// 单值度量取 value；percentiles 展开 values；stats 类展开每个数值字段
+fn push_metric(name: &str, metric: &Value, cells: &mut Vec<(String, Value)>) {
+    if let Some(value) = metric.get("value") {
+        cells.push((name.to_string(), value.clone()));
+        return;
+    }
+
+    let fields = match metric.get("values") {
+        Some(Value::Object(values)) => values,
+        _ => match metric {
+            Value::Object(fields) => fields,
+            _ => {
+                cells.push((name.to_string(), metric.clone()));
+                return;
+            }
+        },
+    };
+
+    for (field, value) in fields {
+        if value.is_number() || value.is_null() {
+            cells.push((format!("{}.{}", name, field), value.clone()));
+        }
+    }
+}
+
+// 第一列键作为 x 轴，其余键与度量组合成序列名；没有度量时用最内层桶的 doc_count
+fn build_chart(table: &AggregationTable, key_columns: &[String]) -> AggregationChart {
+    let key_indices: Vec<usize> = key_columns
+        .iter()
+        .filter_map(|name| table.columns.iter().position(|c| c == name))
+        .collect();
+    let doc_counts: Vec<String> = key_columns.iter().map(|name| doc_count_column(name)).collect();
+    let metric_columns: Vec<usize> = (0..table.columns.len())
+        .filter(|i| !key_indices.contains(i) && !doc_counts.contains(&table.columns[*i]))
+        .collect();
+    let value_columns = if metric_columns.is_empty() {
+        doc_counts
+            .last()
+            .and_then(|doc_count| table.columns.iter().position(|c| c == doc_count))
+            .into_iter()
+            .collect()
+    } else {
+        metric_columns
+    };
+
+    // 没有桶时度量名作为分类，只有一个序列
+    if key_indices.is_empty() {
+        let row = table.rows.first();
+        return AggregationChart {
+            categories: value_columns.iter().map(|&i| Value::from(table.columns[i].as_str())).collect(),
+            series: vec![ChartSeries {
+                name: "value".to_string(),
+                data: value_columns.iter().map(|&i| row.and_then(|r| r[i].as_f64())).collect(),
+            }],
+        };
+    }
+
+    let mut categories: Vec<Value> = Vec::new();
+    let mut series: Vec<ChartSeries> = Vec::new();
+
+    for row in &table.rows {
+        let category = row[key_indices[0]].clone();
+        let category_index = match categories.iter().position(|c| *c == category) {
+            Some(index) => index,
+            None => {
+                categories.push(category);
+                for s in &mut series {
+                    s.data.push(None);
+                }
+                categories.len() - 1
+            }
+        };
+
+        let group: Vec<String> = key_indices[1..].iter().map(|&i| display_value(&row[i])).collect();
+        for &column in &value_columns {
+            let mut name_parts = group.clone();
+            if value_columns.len() > 1 || group.is_empty() {
+                name_parts.push(table.columns[column].clone());
+            }
+            let name = name_parts.join(" / ");
+
+            let series_index = match series.iter().position(|s| s.name == name) {
+                Some(index) => index,
+                None => {
+                    series.push(ChartSeries { name, data: vec![None; categories.len()] });
+                    series.len() - 1
+                }
+            };
+            series[series_index].data[category_index] = row[column].as_f64();
+        }
+    }
+
+    AggregationChart { categories, series }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+impl AggregationChart {
+    /// 转为 "分类 + 每个序列一列" 的表格，便于导出
+    pub fn to_table(&self) -> AggregationTable {
+        let mut columns = vec!["category".to_string()];
+        columns.extend(self.series.iter().map(|s| s.name.clone()));
+
+        let rows = self
+            .categories
+            .iter()
+            .enumerate()
+            .map(|(i, category)| {
+                let mut row = vec![category.clone()];
+                row.extend(self.series.iter().map(|s| s.data[i].map(Value::from).unwrap_or(Value::Null)));
+                row
+            })
+            .collect();
+
+        AggregationTable { columns, rows }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(name: &str, agg_type: &str, sub_aggregations: Option<Vec<AggregationConfig>>) -> AggregationConfig {
+        AggregationConfig {
+            id: name.to_string(),
+            name: name.to_string(),
+            r#type: agg_type.to_string(),
+            field: Some("f".to_string()),
+            params: None,
+            buckets_path: None,
+            sub_aggregations,
+        }
+    }
+
+    #[test]
+    fn test_flatten_nested_buckets() {
+        let configs = vec![config(
+            "day",
+            "date_histogram",
+            Some(vec![
+                config("host", "terms", Some(vec![config("bytes", "sum", None)])),
+                config("latency", "stats", None),
+            ]),
+        )];
+        let result = json!({
+            "day": { "buckets": [
+                {
+                    "key": 0, "key_as_string": "2024-01-01", "doc_count": 3,
+                    "latency": { "count": 3, "min": 1.0, "max": 5.0, "avg": 3.0, "sum": 9.0 },
+                    "host": { "buckets": [
+                        { "key": "a", "doc_count": 2, "bytes": { "value": 10.0 } },
+                        { "key": "b", "doc_count": 1, "bytes": { "value": 4.0 } }
+                    ] }
+                },
+                {
+                    "key": 86400000, "key_as_string": "2024-01-02", "doc_count": 1,
+                    "latency": { "count": 1, "min": 2.0, "max": 2.0, "avg": 2.0, "sum": 2.0 },
+                    "host": { "buckets": [ { "key": "a", "doc_count": 1, "bytes": { "value": 6.0 } } ] }
+                }
+            ] }
+        });
+
+        let flattened = flatten_aggregations(&configs, &result);
+        let table = &flattened.table;
+        assert_eq!(table.rows.len(), 3);
+        assert_eq!(table.columns[..3], ["day", "day.doc_count", "latency.avg"]);
+        // 每层桶的 doc_count 各占一列，内层不会覆盖外层
+        let host_count = table.columns.iter().position(|c| c == "host.doc_count").unwrap();
+        assert_eq!(table.rows[0][1], 3);
+        assert_eq!(table.rows[0][host_count], 2);
+        assert!(flattened.warnings.is_empty());
+        let host = table.columns.iter().position(|c| c == "host").unwrap();
+        let bytes = table.columns.iter().position(|c| c == "bytes").unwrap();
+        assert_eq!(table.rows[1][host], "b");
+        assert_eq!(table.rows[1][bytes], 4.0);
+
+        let chart = &flattened.chart;
+        assert_eq!(chart.categories, vec![json!("2024-01-01"), json!("2024-01-02")]);
+        let series_a = chart.series.iter().find(|s| s.name == "a / bytes").unwrap();
+        assert_eq!(series_a.data, vec![Some(10.0), Some(6.0)]);
+        let series_b = chart.series.iter().find(|s| s.name == "b / bytes").unwrap();
+        assert_eq!(series_b.data, vec![Some(4.0), None]);
+
+        let chart_table = chart.to_table();
+        assert_eq!(chart_table.columns[0], "category");
+        assert_eq!(chart_table.rows.len(), 2);
+    }
+
+    #[test]
+    fn test_flatten_keyed_filters_and_metrics_only() {
+        let configs = vec![config("status", "filters", Some(vec![config("p", "percentiles", None)]))];
+        let result = json!({
+            "status": { "buckets": {
+                "errors": { "doc_count": 2, "p": { "values": { "95.0": 120.0 } } },
+                "ok": { "doc_count": 8, "p": { "values": { "95.0": 30.0 } } }
+            } }
+        });
+
+        let flattened = flatten_aggregations(&configs, &result);
+        assert_eq!(flattened.table.columns, vec!["status", "status.doc_count", "p.95.0"]);
+        assert_eq!(flattened.table.rows[0], vec![json!("errors"), json!(2), json!(120.0)]);
+        assert_eq!(flattened.chart.series[0].name, "p.95.0");
+
+        let metrics_only = vec![config("avg_price", "avg", None), config("max_price", "max", None)];
+        let result = json!({ "avg_price": { "value": 5.0 }, "max_price": { "value": 9.0 } });
+        let flattened = flatten_aggregations(&metrics_only, &result);
+        assert_eq!(flattened.table.rows, vec![vec![json!(5.0), json!(9.0)]]);
+        assert_eq!(flattened.chart.categories, vec![json!("avg_price"), json!("max_price")]);
+        assert_eq!(flattened.chart.series[0].data, vec![Some(5.0), Some(9.0)]);
+
+        let siblings = vec![config("host", "terms", None), config("day", "date_histogram", None)];
+        let result = json!({ "host": { "buckets": [{ "key": "a", "doc_count": 1 }] }, "day": { "buckets": [] } });
+        let flattened = flatten_aggregations(&siblings, &result);
+        assert_eq!(flattened.table.columns, vec!["host", "host.doc_count"]);
+        assert_eq!(flattened.chart.series[0].name, "host.doc_count");
+        assert_eq!(flattened.warnings.len(), 1);
+        assert!(flattened.warnings[0].contains("day"));
+    }
+}
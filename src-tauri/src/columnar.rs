@@ -0,0 +1,582 @@
+// Parquet / Arrow IPC 导出：根据索引映射生成 Arrow schema，按批写入
+use anyhow::{anyhow, Result};
+use arrow::datatypes::{DataType, Field, Fields, Schema, SchemaRef, TimeUnit};
+use arrow::ipc::writer::FileWriter;
+use arrow::json::ReaderBuilder;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+// Parquet 每个 row group 的最大行数，写入端最多缓存这么多行
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 65_536;
+
+// 每累计这么多行转换成一个 RecordBatch 交给写入器
+const BATCH_SIZE: usize = 1000;
+
+// 时间戳列的时区；不带 chrono-tz 时 arrow 只接受偏移量写法
+const UTC_OFFSET: &str = "+00:00";
+
+// 日期字段的 Arrow 字段元数据，保存映射中的 format，写入时据此解析数值时间戳
+const DATE_FORMAT_METADATA: &str = "es_date_format";
+
+/// 日期字段中数值（以及纯数字字符串）的单位
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EpochUnit {
+    #[default]
+    Millis,
+    Seconds,
+}
+
+impl EpochUnit {
+    /// 映射 format 中第一个 epoch_millis / epoch_second 决定单位，未指定时 ES 按毫秒解析
+    pub fn from_format(format: Option<&str>) -> Self {
+        format
+            .into_iter()
+            .flat_map(|f| f.split("||"))
+            .find_map(|f| match f.trim() {
+                "epoch_millis" => Some(EpochUnit::Millis),
+                "epoch_second" => Some(EpochUnit::Seconds),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnarFormat {
+    Parquet,
+    ArrowIpc,
+}
+
+/// 合并 get_mapping 返回的所有索引的 properties（通配符可能匹配多个索引），同名字段以先出现的为准
+pub fn merged_mapping_properties(mapping: &Value) -> Map<String, Value> {
+    let mut merged = Map::new();
+
+    if let Value::Object(indices) = mapping {
+        for index_obj in indices.values() {
+            if let Some(Value::Object(properties)) = index_obj.get("mappings").and_then(|m| m.get("properties")) {
+                merge_properties(&mut merged, properties);
+            }
+        }
+    }
+
+    merged
+}
+
+fn merge_properties(target: &mut Map<String, Value>, source: &Map<String, Value>) {
+    for (name, def) in source {
+        match target.get_mut(name) {
+            None => {
+                target.insert(name.clone(), def.clone());
+            }
+            Some(existing) => {
+                let sub_source = def.get("properties").and_then(|p| p.as_object());
+                let sub_target = existing.get_mut("properties").and_then(|p| p.as_object_mut());
+                if let (Some(sub_source), Some(sub_target)) = (sub_source, sub_target) {
+                    merge_properties(sub_target, sub_source);
+                }
+            }
+        }
+    }
+}
+
+/// 由映射生成 schema，前两列固定为 _index 和 _id。
+/// `list_fields` 为取样中出现过数组值的字段路径（映射本身不区分单值和数组），这些字段包成 List。
+/// `selected_fields` 不为空时只保留这些字段（支持 "user.name" 这样的子字段）。
+pub fn schema_from_mapping(
+    properties: &Map<String, Value>,
+    list_fields: &HashSet<String>,
+    selected_fields: Option<&[String]>,
+) -> Schema {
+    let mut fields = vec![
+        Field::new("_index", DataType::Utf8, true),
+        Field::new("_id", DataType::Utf8, true),
+    ];
+    fields.extend(struct_fields(properties, "", list_fields, selected_fields));
+    Schema::new(fields)
+}
+
+fn struct_fields(
+    properties: &Map<String, Value>,
+    prefix: &str,
+    list_fields: &HashSet<String>,
+    selected_fields: Option<&[String]>,
+) -> Vec<Field> {
+    let mut fields = Vec::new();
+
+    for (name, def) in properties {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+
+        if let Some(selected) = selected_fields {
+            // 选中的字段本身、它的祖先或后代都要保留
+            let keep = selected.iter().any(|s| {
+                s == &path || s.starts_with(&format!("{}.", path)) || path.starts_with(&format!("{}.", s))
+            });
+            if !keep {
+                continue;
+            }
+        }
+
+        let metadata = date_format_metadata(def);
+        let mut data_type = mapping_data_type(def, &path, list_fields, selected_fields);
+        if list_fields.contains(&path) && !matches!(data_type, DataType::List(_)) {
+            data_type = DataType::List(Arc::new(Field::new("item", data_type, true).with_metadata(metadata.clone())));
+        }
+        fields.push(Field::new(name.as_str(), data_type, true).with_metadata(metadata));
+    }
+
+    fields
+}
+
+fn date_format_metadata(def: &Value) -> HashMap<String, String> {
+    def.get("format")
+        .and_then(|f| f.as_str())
+        .map(|format| HashMap::from([(DATE_FORMAT_METADATA.to_string(), format.to_string())]))
+        .unwrap_or_default()
+}
+
+fn mapping_data_type(
+    def: &Value,
+    path: &str,
+    list_fields: &HashSet<String>,
+    selected_fields: Option<&[String]>,
+) -> DataType {
+    let field_type = def.get("type").and_then(|t| t.as_str());
+
+    // object / nested：子字段为空时无法写成 Parquet group，退化为 JSON 字符串
+    if let Some(Value::Object(properties)) = def.get("properties") {
+        // 选中的是整个对象时不再向下过滤
+        let sub_selection = selected_fields.filter(|s| !s.iter().any(|f| f == path));
+        let children = struct_fields(properties, path, list_fields, sub_selection);
+        if children.is_empty() {
+            return DataType::Utf8;
+        }
+        let struct_type = DataType::Struct(Fields::from(children));
+        return if field_type == Some("nested") { list_of(struct_type) } else { struct_type };
+    }
+
+    match field_type.unwrap_or_default() {
+        "long" => DataType::Int64,
+        "integer" => DataType::Int32,
+        "short" => DataType::Int16,
+        "byte" => DataType::Int8,
+        "unsigned_long" => DataType::UInt64,
+        "double" | "scaled_float" => DataType::Float64,
+        "float" | "half_float" => DataType::Float32,
+        "boolean" => DataType::Boolean,
+        "date" => DataType::Timestamp(TimeUnit::Millisecond, Some(UTC_OFFSET.into())),
+        "date_nanos" => DataType::Timestamp(TimeUnit::Nanosecond, Some(UTC_OFFSET.into())),
+        "dense_vector" => list_of(DataType::Float32),
+        // keyword、text、ip 以及 geo_point 等复合值统一按字符串写出
+        _ => DataType::Utf8,
+    }
+}
+
+fn list_of(data_type: DataType) -> DataType {
+    DataType::List(Arc::new(Field::new("item", data_type, true)))
+}
+
+/// 映射中 date / date_nanos 字段的路径（子字段以 "." 连接）及其数值单位，Excel 导出据此把这些列写成日期
+pub fn date_fields(properties: &Map<String, Value>) -> HashMap<String, EpochUnit> {
+    let mut fields = HashMap::new();
+    collect_date_fields(properties, "", &mut fields);
+    fields
+}
+
+fn collect_date_fields(properties: &Map<String, Value>, prefix: &str, fields: &mut HashMap<String, EpochUnit>) {
+    for (name, def) in properties {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        if let Some(Value::Object(children)) = def.get("properties") {
+            collect_date_fields(children, &path, fields);
+        } else if matches!(def.get("type").and_then(|t| t.as_str()), Some("date") | Some("date_nanos")) {
+            fields.insert(path, EpochUnit::from_format(def.get("format").and_then(|f| f.as_str())));
+        }
+    }
+}
+
+/// 在取样的命中中找出值为数组的字段路径
+pub fn detect_list_fields(hits: &[Value]) -> HashSet<String> {
+    let mut list_fields = HashSet::new();
+    for hit in hits {
+        if let Some(Value::Object(source)) = hit.get("_source") {
+            collect_list_fields(source, "", &mut list_fields);
+        }
+    }
+    list_fields
+}
+
+fn collect_list_fields(object: &Map<String, Value>, prefix: &str, list_fields: &mut HashSet<String>) {
+    for (name, value) in object {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        match value {
+            Value::Array(items) => {
+                list_fields.insert(path.clone());
+                for item in items {
+                    if let Value::Object(child) = item {
+                        collect_list_fields(child, &path, list_fields);
+                    }
+                }
+            }
+            Value::Object(child) => collect_list_fields(child, &path, list_fields),
+            _ => {}
+        }
+    }
+}
+
+/// 把一个命中转换成与 schema 一致的 JSON 行。
+/// 无法转换的值写为 null；schema 中不是列表的字段如果后来出现多个元素的数组，报错并指出字段。
+pub fn conform_hit(hit: &Value, schema: &Schema) -> Result<Value> {
+    let source = hit.get("_source").cloned().unwrap_or(Value::Null);
+    let mut row = Map::new();
+
+    for field in schema.fields() {
+        let value = match field.name().as_str() {
+            "_index" | "_id" => hit.get(field.name()).cloned().unwrap_or(Value::Null),
+            name => conform_value(source.get(name).unwrap_or(&Value::Null), field, name)?,
+        };
+        row.insert(field.name().clone(), value);
+    }
+
+    Ok(Value::Object(row))
+}
+
+fn conform_value(value: &Value, field: &Field, path: &str) -> Result<Value> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+
+    let data_type = field.data_type();
+    let conformed = match data_type {
+        DataType::List(item) => match value {
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|v| conform_value(v, item, path))
+                    .collect::<Result<_>>()?,
+            ),
+            single => Value::Array(vec![conform_value(single, item, path)?]),
+        },
+        // 列表列由第一页推断，单元素数组可以按单值写入，多个元素时不能静默丢弃
+        _ if value.is_array() => match value.as_array().map(Vec::as_slice).unwrap_or_default() {
+            [] => Value::Null,
+            [only] => conform_value(only, field, path)?,
+            items => {
+                return Err(anyhow!(
+                    "字段 {} 在第一页中不是数组，之后的文档中出现了 {} 个元素的数组，无法写入列式文件；请改用 JSON 或 NDJSON 导出",
+                    path,
+                    items.len()
+                ))
+            }
+        },
+        DataType::Struct(fields) => match value {
+            Value::Object(object) => Value::Object(
+                fields
+                    .iter()
+                    .map(|f| {
+                        let child_path = format!("{}.{}", path, f.name());
+                        Ok((f.name().clone(), conform_value(object.get(f.name()).unwrap_or(&Value::Null), f, &child_path)?))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            _ => Value::Null,
+        },
+        DataType::Utf8 => match value {
+            Value::String(_) => value.clone(),
+            other => Value::String(other.to_string()),
+        },
+        DataType::Boolean => match value {
+            Value::Bool(_) => value.clone(),
+            Value::String(s) => s.parse::<bool>().map(Value::Bool).unwrap_or(Value::Null),
+            _ => Value::Null,
+        },
+        DataType::Float32 | DataType::Float64 => match value {
+            Value::Number(_) => value.clone(),
+            Value::String(s) => s.parse::<f64>().ok().map(Value::from).unwrap_or(Value::Null),
+            _ => Value::Null,
+        },
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 | DataType::UInt64 => match value {
+            Value::Number(n) if n.is_i64() || n.is_u64() => value.clone(),
+            Value::Number(n) => n.as_f64().map(|f| Value::from(f.trunc() as i64)).unwrap_or(Value::Null),
+            Value::String(s) => s.parse::<i64>().ok().map(Value::from).unwrap_or(Value::Null),
+            _ => Value::Null,
+        },
+        DataType::Timestamp(unit, _) => {
+            let epoch_unit = EpochUnit::from_format(field.metadata().get(DATE_FORMAT_METADATA).map(String::as_str));
+            match parse_es_date(value, epoch_unit) {
+                Some(date) if *unit == TimeUnit::Nanosecond => date.timestamp_nanos_opt().map(Value::from).unwrap_or(Value::Null),
+                Some(date) => Value::from(date.timestamp_millis()),
+                None => Value::Null,
+            }
+        }
+        _ => value.clone(),
+    };
+    Ok(conformed)
+}
+
+// ES 日期可能是 epoch 数值，也可能是 ISO 8601 字符串；保留到纳秒，供 date_nanos 使用
+pub(crate) fn parse_es_date(value: &Value, unit: EpochUnit) -> Option<DateTime<Utc>> {
+    match value {
+        Value::Number(n) => match n.as_i64() {
+            Some(epoch) => from_epoch(epoch, unit),
+            None => n.as_f64().and_then(|epoch| from_fractional_epoch(epoch, unit)),
+        },
+        Value::String(s) => {
+            if let Ok(epoch) = s.parse::<i64>() {
+                return from_epoch(epoch, unit);
+            }
+            if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+                return Some(dt.with_timezone(&Utc));
+            }
+            if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+                return Some(dt.and_utc());
+            }
+            if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f") {
+                return Some(dt.and_utc());
+            }
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|dt| dt.and_utc())
+        }
+        _ => None,
+    }
+}
+
+fn from_epoch(epoch: i64, unit: EpochUnit) -> Option<DateTime<Utc>> {
+    match unit {
+        EpochUnit::Millis => DateTime::from_timestamp_millis(epoch),
+        EpochUnit::Seconds => DateTime::from_timestamp(epoch, 0),
+    }
+}
+
+// 带小数的 epoch（如 1704067200.5），小数部分换算成纳秒
+fn from_fractional_epoch(epoch: f64, unit: EpochUnit) -> Option<DateTime<Utc>> {
+    let seconds = match unit {
+        EpochUnit::Millis => epoch / 1000.0,
+        EpochUnit::Seconds => epoch,
+    };
+    let whole = seconds.floor();
+    DateTime::from_timestamp(whole as i64, ((seconds - whole) * 1e9) as u32)
+}
+
+enum ColumnarSink {
+    Parquet(Box<ArrowWriter<File>>),
+    Ipc(Box<FileWriter<File>>),
+}
+
+/// 逐行写入 Parquet 或 Arrow IPC 文件，只在内存中保留一个批次（Parquet 另有当前 row group）
+pub struct ColumnarWriter {
+    sink: ColumnarSink,
+    schema: SchemaRef,
+    buffer: Vec<Value>,
+}
+
+impl ColumnarWriter {
+    pub fn create(file_path: &Path, format: ColumnarFormat, schema: Schema, row_group_size: usize) -> Result<Self> {
+        let schema = Arc::new(schema);
+        let file = File::create(file_path)?;
+
+        let sink = match format {
+            ColumnarFormat::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_max_row_group_size(row_group_size)
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                ColumnarSink::Parquet(Box::new(ArrowWriter::try_new(file, schema.clone(), Some(props))?))
+            }
+            ColumnarFormat::ArrowIpc => ColumnarSink::Ipc(Box::new(FileWriter::try_new(file, &schema)?)),
+        };
+
+        Ok(Self { sink, schema, buffer: Vec::with_capacity(BATCH_SIZE) })
+    }
+
+    pub fn write_hit(&mut self, hit: &Value) -> Result<()> {
+        self.buffer.push(conform_hit(hit, &self.schema)?);
+
+        if self.buffer.len() >= BATCH_SIZE {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush_batch(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let mut decoder = ReaderBuilder::new(self.schema.clone())
+            .with_batch_size(self.buffer.len())
+            .build_decoder()?;
+        decoder.serialize(&self.buffer)?;
+        let batch = decoder.flush()?.ok_or_else(|| anyhow!("无法构建 Arrow 批次"))?;
+        self.buffer.clear();
+
+        match &mut self.sink {
+            ColumnarSink::Parquet(writer) => writer.write(&batch)?,
+            ColumnarSink::Ipc(writer) => writer.write(&batch)?,
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.flush_batch()?;
+        match self.sink {
+            ColumnarSink::Parquet(writer) => {
+                writer.close()?;
+            }
+            ColumnarSink::Ipc(mut writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn mapping() -> Value {
+        json!({
+            "logs-1": { "mappings": { "properties": {
+                "message": { "type": "text" },
+                "bytes": { "type": "long" },
+                "@timestamp": { "type": "date" },
+                "tags": { "type": "keyword" },
+                "user": { "properties": { "name": { "type": "keyword" }, "age": { "type": "integer" } } },
+                "events": { "type": "nested", "properties": { "code": { "type": "keyword" } } }
+            } } }
+        })
+    }
+
+    #[test]
+    fn test_schema_from_mapping() {
+        let properties = merged_mapping_properties(&mapping());
+        let hits = vec![json!({ "_source": { "tags": ["a", "b"] } })];
+        let schema = schema_from_mapping(&properties, &detect_list_fields(&hits), None);
+
+        assert_eq!(schema.field_with_name("_id").unwrap().data_type(), &DataType::Utf8);
+        assert_eq!(schema.field_with_name("message").unwrap().data_type(), &DataType::Utf8);
+        assert_eq!(schema.field_with_name("bytes").unwrap().data_type(), &DataType::Int64);
+        assert!(matches!(
+            schema.field_with_name("@timestamp").unwrap().data_type(),
+            DataType::Timestamp(TimeUnit::Millisecond, _)
+        ));
+        assert_eq!(schema.field_with_name("tags").unwrap().data_type(), &list_of(DataType::Utf8));
+        assert!(matches!(schema.field_with_name("user").unwrap().data_type(), DataType::Struct(f) if f.len() == 2));
+        assert!(matches!(schema.field_with_name("events").unwrap().data_type(), DataType::List(_)));
+        assert_eq!(date_fields(&properties), HashMap::from([("@timestamp".to_string(), EpochUnit::Millis)]));
+
+        let selected = vec!["user.name".to_string()];
+        let pruned = schema_from_mapping(&properties, &HashSet::new(), Some(&selected));
+        assert_eq!(pruned.fields().len(), 3);
+        assert!(matches!(pruned.field_with_name("user").unwrap().data_type(), DataType::Struct(f) if f.len() == 1));
+    }
+
+    fn conform(value: Value, data_type: DataType) -> Result<Value> {
+        conform_value(&value, &Field::new("f", data_type, true), "f")
+    }
+
+    #[test]
+    fn test_conform_values() {
+        let timestamp = DataType::Timestamp(TimeUnit::Millisecond, Some(UTC_OFFSET.into()));
+        assert_eq!(conform(json!("2024-01-01T00:00:00Z"), timestamp.clone()).unwrap(), json!(1704067200000i64));
+        assert_eq!(conform(json!("2024-01-01"), timestamp.clone()).unwrap(), json!(1704067200000i64));
+        assert_eq!(conform(json!("not a date"), timestamp).unwrap(), Value::Null);
+        assert_eq!(conform(json!("42"), DataType::Int64).unwrap(), json!(42));
+        assert_eq!(conform(json!(7), DataType::Utf8).unwrap(), json!("7"));
+        assert_eq!(conform(json!("x"), list_of(DataType::Utf8)).unwrap(), json!(["x"]));
+        assert_eq!(conform(json!([1]), DataType::Int64).unwrap(), json!(1));
+
+        // 第一页推断为单值的字段后来出现多个值时报错并指出字段
+        let user = DataType::Struct(Fields::from(vec![Field::new("name", DataType::Utf8, true)]));
+        let error = conform_value(&json!({ "name": ["a", "b"] }), &Field::new("user", user, true), "user").unwrap_err();
+        assert!(error.to_string().contains("user.name"));
+    }
+
+    #[test]
+    fn test_conform_dates_with_mapping_format() {
+        let properties = json!({
+            "nanos": { "type": "date_nanos" },
+            "seconds": { "type": "date", "format": "epoch_second" },
+            "either": { "type": "date", "format": "strict_date_optional_time||epoch_millis" }
+        });
+        let properties = properties.as_object().unwrap();
+        let schema = schema_from_mapping(properties, &HashSet::new(), None);
+        let row = conform_hit(
+            &json!({ "_source": {
+                "nanos": "2024-01-01T00:00:00.123456789Z",
+                "seconds": 1704067200,
+                "either": "1704067200000"
+            } }),
+            &schema,
+        )
+        .unwrap();
+
+        // date_nanos 保留亚毫秒部分，epoch_second 按秒解析
+        assert_eq!(row["nanos"], json!(1704067200123456789i64));
+        assert_eq!(row["seconds"], json!(1704067200000i64));
+        assert_eq!(row["either"], json!(1704067200000i64));
+
+        assert_eq!(
+            date_fields(properties),
+            HashMap::from([
+                ("nanos".to_string(), EpochUnit::Millis),
+                ("seconds".to_string(), EpochUnit::Seconds),
+                ("either".to_string(), EpochUnit::Millis),
+            ])
+        );
+        assert_eq!(
+            parse_es_date(&json!("1704067200"), EpochUnit::Seconds).map(|d| d.timestamp_millis()),
+            Some(1704067200000)
+        );
+    }
+
+    #[test]
+    fn test_columnar_writer_row_groups() {
+        let properties = merged_mapping_properties(&mapping());
+        let schema = schema_from_mapping(&properties, &HashSet::from(["tags".to_string()]), None);
+        let path = std::env::temp_dir().join(format!("es_client_{}_export.parquet", std::process::id()));
+
+        let mut writer = ColumnarWriter::create(&path, ColumnarFormat::Parquet, schema.clone(), 1000).unwrap();
+        for i in 0..2500 {
+            writer
+                .write_hit(&json!({
+                    "_index": "logs-1",
+                    "_id": i.to_string(),
+                    "_source": {
+                        "message": "hello",
+                        "bytes": i,
+                        "@timestamp": "2024-01-01T00:00:00Z",
+                        "tags": "single",
+                        "user": { "name": "alice", "age": 30 },
+                        "events": [{ "code": "E1" }]
+                    }
+                }))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = parquet::file::reader::SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = parquet::file::reader::FileReader::metadata(&reader);
+        assert_eq!(metadata.file_metadata().num_rows(), 2500);
+        assert_eq!(metadata.num_row_groups(), 3);
+
+        let ipc_path = path.with_extension("arrow");
+        let mut writer = ColumnarWriter::create(&ipc_path, ColumnarFormat::ArrowIpc, schema, DEFAULT_ROW_GROUP_SIZE).unwrap();
+        writer.write_hit(&json!({ "_id": "1", "_source": { "bytes": "12" } })).unwrap();
+        writer.finish().unwrap();
+
+        let reader = arrow::ipc::reader::FileReader::try_new(File::open(&ipc_path).unwrap(), None).unwrap();
+        let batches: Vec<_> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(batches[0].num_rows(), 1);
+
+        std::fs::remove_file(path).ok();
+        std::fs::remove_file(ipc_path).ok();
+    }
+}
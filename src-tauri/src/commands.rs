@@ -1,19 +1,61 @@
-use crate::es_client::EsClient;
+use crate::es_client::{build_geo_query, load_search_template_file, EsClient};
 use crate::export::ExportService;
+use crate::output::{discard_partial_output, prepare_directory, read_checkpoint};
+use crate::redaction::Redactor;
+use crate::schedule::{render_filename_template, CronSchedule};
 use crate::import::ImportService;
+use crate::query_parser::{self, QuerySyntax};
+use crate::aggregation_table::flatten_aggregations;
 use crate::types::*;
 use crate::crypto::{CryptoManager, SecureConnectionData};
 use crate::error::ErrorDetails;
+use chrono::{Local, NaiveDateTime, Timelike};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tauri::api::path::app_data_dir;
-use tauri::State;
+use tauri::{AppHandle, Manager, Runtime, State, Window};
 use uuid::Uuid;
 use anyhow::Result;
 
+// 正在运行的导出任务，值为取消标记
+#[derive(Default)]
+pub struct JobManager {
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&self, job_id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.jobs.lock().unwrap().insert(job_id.to_string(), flag.clone());
+        flag
+    }
+
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.jobs.lock().unwrap().get(job_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self, job_id: &str) {
+        self.jobs.lock().unwrap().remove(job_id);
+    }
+}
+
 pub struct ConnectionManager {
     connections: Mutex<HashMap<String, EsConnection>>,
     clients: Mutex<HashMap<String, EsClient>>,
@@ -199,6 +241,333 @@ impl ConnectionManager {
     }
 }
 
+// 应用设置，保存在应用数据目录的 settings.json 中
+pub struct SettingsManager {
+    settings: Mutex<AppSettings>,
+    config: tauri::Config,
+}
+
+impl SettingsManager {
+    pub fn new(config: tauri::Config) -> Self {
+        let manager = Self {
+            settings: Mutex::new(AppSettings::default()),
+            config,
+        };
+
+        match manager.load_settings() {
+            Ok(settings) => *manager.settings.lock().unwrap() = settings,
+            Err(e) => eprintln!("Failed to load settings: {}", e),
+        }
+
+        manager
+    }
+
+    fn get_settings_file_path(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let app_data_dir = app_data_dir(&self.config)
+            .ok_or("Failed to get app data directory")?;
+
+        fs::create_dir_all(&app_data_dir)?;
+
+        Ok(app_data_dir.join("settings.json"))
+    }
+
+    fn load_settings(&self) -> Result<AppSettings, Box<dyn std::error::Error>> {
+        let file_path = self.get_settings_file_path()?;
+
+        if !file_path.exists() {
+            return Ok(AppSettings::default());
+        }
+
+        let json_data = fs::read_to_string(&file_path)?;
+        Ok(serde_json::from_str(&json_data)?)
+    }
+
+    pub fn get(&self) -> AppSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn update(&self, settings: AppSettings) -> Result<(), Box<dyn std::error::Error>> {
+        let file_path = self.get_settings_file_path()?;
+        fs::write(&file_path, serde_json::to_string_pretty(&settings)?)?;
+
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    // 按设置中的默认导出目录创建导出服务
+    pub fn export_service(&self) -> ExportService {
+        ExportService::with_default_directory(self.get().default_export_directory)
+    }
+
+    // 把请求引用的已保存脱敏配置的规则合并到请求自身的规则之前
+    pub fn resolve_redaction(&self, redaction: Option<RedactionConfig>) -> Result<Option<RedactionConfig>, ErrorDetails> {
+        let Some(mut redaction) = redaction else {
+            return Ok(None);
+        };
+
+        if let Some(name) = redaction.profile.take() {
+            let profile = self
+                .get()
+                .redaction_profiles
+                .into_iter()
+                .find(|p| p.name == name)
+                .ok_or_else(|| ErrorDetails::validation_error("redaction.profile", &format!("脱敏配置不存在：{}", name)))?;
+            redaction.rules = profile.rules.into_iter().chain(redaction.rules).collect();
+        }
+
+        Ok(Some(redaction))
+    }
+}
+
+// 运行历史最多保留的条数，超出后丢弃最早的记录
+const MAX_RUN_HISTORY: usize = 200;
+
+// 导出预设与运行历史，分别保存在应用数据目录的 export_presets.json 和 export_history.json
+pub struct PresetManager {
+    presets: Mutex<Vec<ExportPreset>>,
+    history: Mutex<Vec<ExportRunRecord>>,
+    running: Mutex<HashSet<String>>,
+    config: tauri::Config,
+}
+
+impl PresetManager {
+    pub fn new(config: tauri::Config) -> Self {
+        let manager = Self {
+            presets: Mutex::new(Vec::new()),
+            history: Mutex::new(Vec::new()),
+            running: Mutex::new(HashSet::new()),
+            config,
+        };
+
+        match manager.load_file("export_presets.json") {
+            Ok(presets) => *manager.presets.lock().unwrap() = presets,
+            Err(e) => eprintln!("Failed to load export presets: {}", e),
+        }
+        match manager.load_file("export_history.json") {
+            Ok(history) => *manager.history.lock().unwrap() = history,
+            Err(e) => eprintln!("Failed to load export history: {}", e),
+        }
+
+        manager
+    }
+
+    fn get_data_file_path(&self, name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let app_data_dir = app_data_dir(&self.config)
+            .ok_or("Failed to get app data directory")?;
+
+        fs::create_dir_all(&app_data_dir)?;
+
+        Ok(app_data_dir.join(name))
+    }
+
+    fn load_file<T: DeserializeOwned + Default>(&self, name: &str) -> Result<T, Box<dyn std::error::Error>> {
+        let file_path = self.get_data_file_path(name)?;
+
+        if !file_path.exists() {
+            return Ok(T::default());
+        }
+
+        let json_data = fs::read_to_string(&file_path)?;
+        Ok(serde_json::from_str(&json_data)?)
+    }
+
+    fn save_file<T: Serialize>(&self, name: &str, value: &T) -> Result<(), Box<dyn std::error::Error>> {
+        let file_path = self.get_data_file_path(name)?;
+        fs::write(&file_path, serde_json::to_string_pretty(value)?)?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<ExportPreset> {
+        self.presets.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<ExportPreset> {
+        self.presets.lock().unwrap().iter().find(|p| p.id == id).cloned()
+    }
+
+    // id 为空时新建，否则替换同 id 的预设
+    pub fn save(&self, mut preset: ExportPreset) -> Result<ExportPreset, Box<dyn std::error::Error>> {
+        let mut presets = self.presets.lock().unwrap();
+        if preset.id.is_empty() {
+            preset.id = Uuid::new_v4().to_string();
+        }
+
+        match presets.iter_mut().find(|p| p.id == preset.id) {
+            Some(existing) => *existing = preset.clone(),
+            None => presets.push(preset.clone()),
+        }
+        self.save_file("export_presets.json", &*presets)?;
+        Ok(preset)
+    }
+
+    pub fn remove(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut presets = self.presets.lock().unwrap();
+        let before = presets.len();
+        presets.retain(|p| p.id != id);
+        if presets.len() == before {
+            return Ok(false);
+        }
+        self.save_file("export_presets.json", &*presets)?;
+        Ok(true)
+    }
+
+    // 最新的记录在前
+    pub fn history(&self, preset_id: Option<&str>) -> Vec<ExportRunRecord> {
+        self.history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| preset_id.is_none_or(|id| r.preset_id == id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn record_run(&self, record: ExportRunRecord) -> Result<(), Box<dyn std::error::Error>> {
+        let mut history = self.history.lock().unwrap();
+        history.insert(0, record);
+        history.truncate(MAX_RUN_HISTORY);
+        self.save_file("export_history.json", &*history)
+    }
+
+    // 这一分钟到点且未暂停的预设
+    pub fn due_presets(&self, minute: &NaiveDateTime) -> Vec<ExportPreset> {
+        self.list()
+            .into_iter()
+            .filter(|p| !p.paused)
+            .filter(|p| {
+                p.schedule
+                    .as_deref()
+                    .and_then(|expr| CronSchedule::parse(expr).ok())
+                    .is_some_and(|schedule| schedule.matches(minute))
+            })
+            .collect()
+    }
+
+    // 同一个预设不会同时运行两次
+    fn try_start(&self, id: &str) -> bool {
+        self.running.lock().unwrap().insert(id.to_string())
+    }
+
+    fn finish_run(&self, id: &str) {
+        self.running.lock().unwrap().remove(id);
+    }
+}
+
+// 保存前检查名称、定时表达式和文件名模板
+fn validate_preset(preset: &ExportPreset) -> Result<(), ErrorDetails> {
+    if preset.name.trim().is_empty() {
+        return Err(ErrorDetails::validation_error("name", "预设名称不能为空"));
+    }
+    if let Some(schedule) = &preset.schedule {
+        CronSchedule::parse(schedule).map_err(|e| ErrorDetails::validation_error("schedule", &e.to_string()))?;
+    }
+    render_filename_template(&preset.request.filename, &preset.request.query.index, &preset.name, &Local::now().naive_local())
+        .map_err(|e| ErrorDetails::validation_error("request.filename", &e.to_string()))?;
+    Ok(())
+}
+
+// 运行一个预设并写入运行历史；进度和运行结果广播给所有窗口
+pub async fn run_preset(app: &AppHandle, preset: ExportPreset, trigger: ExportRunTrigger) -> ExportRunRecord {
+    let presets = app.state::<PresetManager>();
+    let started_at = Local::now();
+
+    let outcome = if presets.try_start(&preset.id) {
+        let outcome = execute_preset(app, &preset, started_at.naive_local()).await;
+        presets.finish_run(&preset.id);
+        outcome
+    } else {
+        Err("上一次运行尚未结束".to_string())
+    };
+
+    let record = ExportRunRecord {
+        id: Uuid::new_v4().to_string(),
+        preset_id: preset.id.clone(),
+        preset_name: preset.name.clone(),
+        trigger,
+        started_at: started_at.to_rfc3339(),
+        finished_at: Local::now().to_rfc3339(),
+        success: outcome.is_ok(),
+        total_records: outcome.as_ref().map_or(0, |r| r.total_records),
+        file_path: outcome.as_ref().ok().map(|r| r.file_path.clone()),
+        error: outcome.err(),
+    };
+
+    if let Err(e) = presets.record_run(record.clone()) {
+        eprintln!("Failed to save export history: {}", e);
+    }
+    if let Err(e) = app.emit_all("export-preset-run", &record) {
+        eprintln!("Failed to emit export preset run: {}", e);
+    }
+
+    record
+}
+
+// 导出进度回调：把进度作为 export-progress 事件推送给前端，推送失败只打印日志，不中断导出
+fn progress_emitter<R: Runtime, M: Manager<R> + Sync>(target: &M) -> impl FnMut(&ExportProgress) + Send + '_ {
+    move |progress: &ExportProgress| {
+        if let Err(e) = target.emit_all("export-progress", progress) {
+            eprintln!("Failed to emit export progress: {}", e);
+        }
+    }
+}
+
+async fn execute_preset(app: &AppHandle, preset: &ExportPreset, now: NaiveDateTime) -> Result<ExportResult, String> {
+    let manager = app.state::<ConnectionManager>();
+    let settings = app.state::<SettingsManager>();
+    let jobs = app.state::<JobManager>();
+
+    let client = manager
+        .get_client(&preset.request.connection_id)
+        .ok_or("Connection not found")?;
+
+    let filename = render_filename_template(&preset.request.filename, &preset.request.query.index, &preset.name, &now)
+        .map_err(|e| e.to_string())?;
+    let job_id = Uuid::new_v4().to_string();
+    let request = ExportRequest {
+        filename,
+        job_id: Some(job_id.clone()),
+        redaction: settings.resolve_redaction(preset.request.redaction.clone()).map_err(|e| e.to_string())?,
+        ..preset.request.clone()
+    };
+    let cancelled = jobs.register(&job_id);
+
+    let mut emit_progress = progress_emitter(app);
+
+    let export_service = settings.export_service();
+    let result = export_service
+        .export_search_results(&client, &request, &job_id, &cancelled, &mut emit_progress)
+        .await;
+
+    jobs.finish(&job_id);
+    result.map_err(|e| e.to_string())
+}
+
+// 应用运行期间在每分钟开始时检查定时表达式，到点的预设各自在后台运行
+pub async fn run_preset_scheduler(app: AppHandle) {
+    let mut last_minute: Option<NaiveDateTime> = None;
+
+    loop {
+        let now = Local::now();
+        let elapsed_ms = now.second() as u64 * 1000 + now.timestamp_subsec_millis().min(999) as u64;
+        tokio::time::sleep(Duration::from_millis(60_000 - elapsed_ms)).await;
+
+        let Some(minute) = Local::now().naive_local().with_second(0).and_then(|t| t.with_nanosecond(0)) else {
+            continue;
+        };
+        if last_minute == Some(minute) {
+            continue;
+        }
+        last_minute = Some(minute);
+
+        for preset in app.state::<PresetManager>().due_presets(&minute) {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                run_preset(&app, preset, ExportRunTrigger::Schedule).await;
+            });
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn add_connection(
     manager: State<'_, ConnectionManager>,
@@ -296,74 +665,75 @@ pub async fn search_documents(
 }
 
 #[tauri::command]
-pub async fn search_documents_stream(
+pub async fn multi_search(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
-    query: SearchQuery,
-    batch_size: Option<usize>,
-    max_results: Option<usize>,
-) -> Result<Vec<serde_json::Value>, String> {
+    queries: Vec<SearchQuery>,
+) -> Result<Vec<MultiSearchItem>, ErrorDetails> {
     let client = manager
         .get_client(&connection_id)
-        .ok_or("Connection not found")?;
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
 
     client
-        .search_stream(query, batch_size.unwrap_or(1000), max_results)
+        .multi_search(&queries)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(ErrorDetails::from)
 }
 
 #[tauri::command]
-pub async fn get_index_mapping(
+pub async fn count_documents(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
     index: String,
-) -> Result<Value, String> {
+    query: Option<Value>,
+) -> Result<CountResult, ErrorDetails> {
     let client = manager
         .get_client(&connection_id)
-        .ok_or("Connection not found")?;
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
 
     client
-        .get_mapping(&index)
+        .count(&index, query.as_ref())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(ErrorDetails::from)
 }
 
 #[tauri::command]
-pub async fn get_field_names(
+pub async fn estimate_query_cost(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
     index: String,
-) -> Result<Vec<String>, String> {
+    query: Option<Value>,
+) -> Result<QueryCostEstimate, ErrorDetails> {
     let client = manager
         .get_client(&connection_id)
-        .ok_or("Connection not found")?;
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
 
     client
-        .get_field_names(&index)
+        .estimate_query_cost(&index, query.as_ref())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(ErrorDetails::from)
 }
 
 #[tauri::command]
-pub async fn create_index(
+pub async fn search_documents_stream(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
-    index: String,
-    mapping: Option<Value>,
-) -> Result<Value, String> {
+    query: SearchQuery,
+    batch_size: Option<usize>,
+    max_results: Option<usize>,
+) -> Result<Vec<serde_json::Value>, String> {
     let client = manager
         .get_client(&connection_id)
         .ok_or("Connection not found")?;
 
     client
-        .create_index(&index, mapping)
+        .search_stream(query, batch_size.unwrap_or(1000), max_results)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn delete_index(
+pub async fn get_index_mapping(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
     index: String,
@@ -373,155 +743,120 @@ pub async fn delete_index(
         .ok_or("Connection not found")?;
 
     client
-        .delete_index(&index)
+        .get_mapping(&index)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn export_search_results(
+pub async fn get_field_names(
     manager: State<'_, ConnectionManager>,
-    request: ExportRequest,
-) -> Result<ExportResult, String> {
+    connection_id: String,
+    index: String,
+) -> Result<Vec<FieldInfo>, String> {
     let client = manager
-        .get_client(&request.connection_id)
+        .get_client(&connection_id)
         .ok_or("Connection not found")?;
 
-    // 执行搜索获取所有数据
-    let mut all_data = Vec::new();
-    let batch_size = 1000;
-    let max_records = request.max_records.unwrap_or(10000);
-    let mut current_from = 0;
-
-    while current_from < max_records {
-        let remaining = max_records - current_from;
-        let current_size = std::cmp::min(batch_size, remaining);
-
-        let mut search_query = request.query.clone();
-        search_query.from = Some(current_from as i32);
-        search_query.size = Some(current_size as i32);
-
-        match client.search(search_query).await {
-            Ok(result) => {
-                if result.hits.is_empty() {
-                    break;
-                }
-                
-                // 在移动前保存长度
-                let hits_count = result.hits.len();
-                all_data.extend(result.hits);
-                current_from += current_size;
-                
-                // 如果这批返回的数据少于请求的数量，说明已经没有更多数据了
-                if hits_count < current_size as usize {
-                    break;
-                }
-            }
-            Err(e) => return Err(format!("搜索失败: {}", e)),
-        }
-    }
-
-    // 使用导出服务导出数据
-    let export_service = ExportService::new();
-    export_service
-        .export_data(request, all_data)
+    client
+        .get_field_names(&index)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_export_directory() -> Result<String, String> {
-    let export_service = ExportService::new();
-    match export_service.get_export_directory() {
-        Ok(path) => Ok(path.to_string_lossy().to_string()),
-        Err(e) => Err(e.to_string()),
-    }
-}
-
-#[tauri::command]
-pub async fn create_document(
+pub async fn get_field_details(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
-    request: DocumentRequest,
-) -> Result<DocumentResponse, String> {
+    index: String,
+) -> Result<Vec<FieldInfo>, ErrorDetails> {
     let client = manager
         .get_client(&connection_id)
-        .ok_or("Connection not found")?;
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
 
     client
-        .create_document(&request)
+        .get_field_details(&index)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(ErrorDetails::from)
 }
 
 #[tauri::command]
-pub async fn update_document(
+pub async fn get_geo_fields(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
-    request: DocumentRequest,
-) -> Result<DocumentResponse, String> {
+    index: String,
+) -> Result<Vec<FieldInfo>, ErrorDetails> {
     let client = manager
         .get_client(&connection_id)
-        .ok_or("Connection not found")?;
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
 
     client
-        .update_document(&request)
+        .get_geo_fields(&index)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(ErrorDetails::from)
 }
 
 #[tauri::command]
-pub async fn get_document(
+pub async fn build_geo_query_dsl(query: GeoQuery) -> Result<Value, ErrorDetails> {
+    Ok(build_geo_query(&query))
+}
+
+#[tauri::command]
+pub async fn geo_grid_search(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
-    index: String,
-    id: String,
-) -> Result<GetDocumentResponse, String> {
+    request: GeoGridRequest,
+) -> Result<GeoGridResult, ErrorDetails> {
     let client = manager
         .get_client(&connection_id)
-        .ok_or("Connection not found")?;
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
 
     client
-        .get_document(&index, &id)
+        .geo_grid(&request)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(ErrorDetails::from)
 }
 
 #[tauri::command]
-pub async fn delete_document(
+pub async fn translate_query_string(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
     index: String,
-    id: String,
-) -> Result<DocumentResponse, String> {
+    query: String,
+    syntax: QuerySyntax,
+) -> Result<Value, ErrorDetails> {
     let client = manager
         .get_client(&connection_id)
-        .ok_or("Connection not found")?;
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
 
-    client
-        .delete_document(&index, &id)
+    let field_types = client
+        .get_field_types(&index)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(ErrorDetails::from)?;
+
+    query_parser::query_string_to_dsl(&query, syntax, &field_types)
+        .map_err(|e| ErrorDetails::query_syntax_error(e.to_string()))
 }
 
 #[tauri::command]
-pub async fn bulk_operations(
+pub async fn create_index(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
-    request: BulkRequest,
-) -> Result<BulkResponse, String> {
+    index: String,
+    mapping: Option<Value>,
+) -> Result<Value, String> {
     let client = manager
         .get_client(&connection_id)
         .ok_or("Connection not found")?;
 
     client
-        .bulk_operations(&request)
+        .create_index(&index, mapping)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_index_settings(
+pub async fn delete_index(
     manager: State<'_, ConnectionManager>,
     connection_id: String,
     index: String,
@@ -531,17 +866,269 @@ pub async fn get_index_settings(
         .ok_or("Connection not found")?;
 
     client
-        .get_index_settings(&index)
+        .delete_index(&index)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn update_index_settings(
+pub async fn export_search_results(
+    window: Window,
     manager: State<'_, ConnectionManager>,
-    connection_id: String,
-    index: String,
-    settings: IndexSettings,
+    settings: State<'_, SettingsManager>,
+    jobs: State<'_, JobManager>,
+    request: ExportRequest,
+) -> Result<ExportResult, String> {
+    let client = manager
+        .get_client(&request.connection_id)
+        .ok_or("Connection not found")?;
+
+    let request = ExportRequest {
+        redaction: settings.resolve_redaction(request.redaction).map_err(|e| e.to_string())?,
+        ..request
+    };
+
+    let job_id = request.job_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancelled = jobs.register(&job_id);
+
+    let mut emit_progress = progress_emitter(&window);
+
+    // 逐页拉取并写入，不在内存中累积全部命中
+    let export_service = settings.export_service();
+    let result = export_service
+        .export_search_results(&client, &request, &job_id, &cancelled, &mut emit_progress)
+        .await;
+
+    jobs.finish(&job_id);
+    result.map_err(|e| e.to_string())
+}
+
+// 从断点文件继续之前被取消或中断的搜索导出
+#[tauri::command]
+pub async fn resume_search_export(
+    window: Window,
+    manager: State<'_, ConnectionManager>,
+    settings: State<'_, SettingsManager>,
+    jobs: State<'_, JobManager>,
+    checkpoint_path: String,
+    job_id: Option<String>,
+) -> Result<ExportResult, String> {
+    let checkpoint = read_checkpoint(Path::new(&checkpoint_path)).map_err(|e| e.to_string())?;
+    let client = manager
+        .get_client(&checkpoint.request.connection_id)
+        .ok_or("Connection not found")?;
+
+    let job_id = job_id
+        .or_else(|| checkpoint.request.job_id.clone())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancelled = jobs.register(&job_id);
+
+    let mut emit_progress = progress_emitter(&window);
+
+    let export_service = settings.export_service();
+    let result = export_service
+        .resume_search_export(&client, &checkpoint, &job_id, &cancelled, &mut emit_progress)
+        .await;
+
+    jobs.finish(&job_id);
+    result.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_export_directory(settings: State<'_, SettingsManager>) -> Result<String, String> {
+    let export_service = settings.export_service();
+    match export_service.get_export_directory() {
+        Ok(path) => Ok(path.to_string_lossy().to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_app_settings(settings: State<'_, SettingsManager>) -> Result<AppSettings, ErrorDetails> {
+    Ok(settings.get())
+}
+
+#[tauri::command]
+pub async fn update_app_settings(
+    settings: State<'_, SettingsManager>,
+    new_settings: AppSettings,
+) -> Result<AppSettings, ErrorDetails> {
+    // 空字符串视为恢复默认目录
+    let new_settings = AppSettings {
+        default_export_directory: new_settings
+            .default_export_directory
+            .filter(|directory| !directory.trim().is_empty()),
+        ..new_settings
+    };
+
+    if let Some(directory) = &new_settings.default_export_directory {
+        prepare_directory(Path::new(directory))
+            .map_err(|e| ErrorDetails::validation_error("default_export_directory", &e.to_string()))?;
+    }
+
+    let mut profile_names = HashSet::new();
+    for profile in &new_settings.redaction_profiles {
+        if profile.name.trim().is_empty() || !profile_names.insert(profile.name.as_str()) {
+            return Err(ErrorDetails::validation_error("redaction_profiles", &format!("脱敏配置名称为空或重复：{}", profile.name)));
+        }
+        let config = RedactionConfig { rules: profile.rules.clone(), ..Default::default() };
+        Redactor::new(Some(&config)).map_err(|e| ErrorDetails::validation_error("redaction_profiles", &e.to_string()))?;
+    }
+
+    settings
+        .update(new_settings.clone())
+        .map_err(|e| ErrorDetails::from(anyhow::anyhow!("保存设置失败: {}", e)))?;
+    Ok(new_settings)
+}
+
+#[tauri::command]
+pub async fn list_export_presets(presets: State<'_, PresetManager>) -> Result<Vec<ExportPreset>, ErrorDetails> {
+    Ok(presets.list())
+}
+
+#[tauri::command]
+pub async fn save_export_preset(
+    presets: State<'_, PresetManager>,
+    preset: ExportPreset,
+) -> Result<ExportPreset, ErrorDetails> {
+    validate_preset(&preset)?;
+    presets
+        .save(preset)
+        .map_err(|e| ErrorDetails::from(anyhow::anyhow!("保存导出预设失败: {}", e)))
+}
+
+#[tauri::command]
+pub async fn delete_export_preset(presets: State<'_, PresetManager>, id: String) -> Result<bool, ErrorDetails> {
+    presets
+        .remove(&id)
+        .map_err(|e| ErrorDetails::from(anyhow::anyhow!("删除导出预设失败: {}", e)))
+}
+
+// 立即运行一次预设，失败同样记入运行历史并在返回的记录中给出错误
+#[tauri::command]
+pub async fn run_export_preset(
+    app: AppHandle,
+    presets: State<'_, PresetManager>,
+    id: String,
+) -> Result<ExportRunRecord, ErrorDetails> {
+    let preset = presets
+        .get(&id)
+        .ok_or_else(|| ErrorDetails::validation_error("id", "导出预设不存在"))?;
+    Ok(run_preset(&app, preset, ExportRunTrigger::Manual).await)
+}
+
+#[tauri::command]
+pub async fn get_export_run_history(
+    presets: State<'_, PresetManager>,
+    preset_id: Option<String>,
+) -> Result<Vec<ExportRunRecord>, ErrorDetails> {
+    Ok(presets.history(preset_id.as_deref()))
+}
+
+#[tauri::command]
+pub async fn create_document(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    request: DocumentRequest,
+) -> Result<DocumentResponse, String> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or("Connection not found")?;
+
+    client
+        .create_document(&request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_document(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    request: DocumentRequest,
+) -> Result<DocumentResponse, String> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or("Connection not found")?;
+
+    client
+        .update_document(&request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_document(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    index: String,
+    id: String,
+) -> Result<GetDocumentResponse, String> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or("Connection not found")?;
+
+    client
+        .get_document(&index, &id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_document(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    index: String,
+    id: String,
+) -> Result<DocumentResponse, String> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or("Connection not found")?;
+
+    client
+        .delete_document(&index, &id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn bulk_operations(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    request: BulkRequest,
+) -> Result<BulkResponse, String> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or("Connection not found")?;
+
+    client
+        .bulk_operations(&request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_index_settings(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    index: String,
+) -> Result<Value, String> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or("Connection not found")?;
+
+    client
+        .get_index_settings(&index)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_index_settings(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    index: String,
+    settings: IndexSettings,
 ) -> Result<Value, String> {
     let client = manager
         .get_client(&connection_id)
@@ -699,6 +1286,121 @@ pub async fn delete_template(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn list_search_templates(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+) -> Result<Vec<SearchTemplate>, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .list_search_templates()
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn get_search_template(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    id: String,
+) -> Result<SearchTemplate, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .get_search_template(&id)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn put_search_template(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    template: SearchTemplate,
+) -> Result<Value, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .put_search_template(&template)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn delete_search_template(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    id: String,
+) -> Result<Value, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .delete_search_template(&id)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn render_search_template(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    query: SearchTemplateQuery,
+) -> Result<Value, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .render_search_template(&query)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn render_search_template_file(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    file_path: String,
+    params: Option<HashMap<String, Value>>,
+) -> Result<Value, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    let query = load_search_template_file(&file_path, params)
+        .map_err(|e| ErrorDetails::validation_error("file_path", &e.to_string()))?;
+
+    client
+        .render_search_template(&query)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn search_template(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    query: SearchTemplateQuery,
+) -> Result<SearchResult, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .search_template(&query)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
 #[tauri::command]
 pub async fn execute_aggregation(
     manager: State<'_, ConnectionManager>,
@@ -763,6 +1465,400 @@ pub async fn close_sql_cursor(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn translate_sql(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    query: SqlQuery,
+) -> Result<Value, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .translate_sql(&query)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn download_sql_results(
+    manager: State<'_, ConnectionManager>,
+    settings: State<'_, SettingsManager>,
+    connection_id: String,
+    query: SqlQuery,
+    format: SqlFormat,
+    filename: String,
+    target: Option<ExportTarget>,
+    redaction: Option<RedactionConfig>,
+) -> Result<SqlDownloadResult, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    // 原样下载 ES 返回的内容，无法逐字段脱敏
+    if settings.resolve_redaction(redaction)?.is_some_and(|r| !r.rules.is_empty()) {
+        return Err(ErrorDetails::validation_error("redaction", "原样下载不支持脱敏，请改用 SQL 结果导出"));
+    }
+
+    let file_path = settings
+        .export_service()
+        .output_path(&target.unwrap_or_default(), &filename, format.as_param())
+        .map_err(ErrorDetails::from)?;
+    let file = fs::File::create(&file_path).map_err(|e| ErrorDetails::from(anyhow::Error::from(e)))?;
+    let mut writer = std::io::BufWriter::new(file);
+
+    let outcome = client.download_sql(&query, format, &mut writer).await;
+    drop(writer);
+    if outcome.is_err() {
+        discard_partial_output(&file_path);
+    }
+    let (bytes_written, pages) = outcome.map_err(ErrorDetails::from)?;
+
+    Ok(SqlDownloadResult {
+        file_path: file_path.to_string_lossy().to_string(),
+        bytes_written,
+        pages,
+    })
+}
+
+#[tauri::command]
+pub async fn export_sql_results(
+    window: Window,
+    manager: State<'_, ConnectionManager>,
+    settings: State<'_, SettingsManager>,
+    jobs: State<'_, JobManager>,
+    request: SqlExportRequest,
+) -> Result<ExportResult, ErrorDetails> {
+    let client = manager
+        .get_client(&request.connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    let request = SqlExportRequest {
+        redaction: settings.resolve_redaction(request.redaction)?,
+        ..request
+    };
+
+    let job_id = request.job_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancelled = jobs.register(&job_id);
+
+    let mut emit_progress = progress_emitter(&window);
+
+    let export_service = settings.export_service();
+    let result = export_service
+        .export_sql_results(&client, &request, &job_id, &cancelled, &mut emit_progress)
+        .await;
+
+    jobs.finish(&job_id);
+    result.map_err(ErrorDetails::from)
+}
+
+// 执行聚合并展开为表格和图表序列
+#[tauri::command]
+pub async fn execute_aggregation_table(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    request: AggregationRequest,
+) -> Result<FlattenedAggregation, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    let result = client
+        .execute_aggregation(&request)
+        .await
+        .map_err(ErrorDetails::from)?;
+
+    Ok(flatten_aggregations(
+        &request.aggregations,
+        result.aggregations.as_ref().unwrap_or(&Value::Null),
+    ))
+}
+
+#[tauri::command]
+pub async fn export_aggregation_result(
+    manager: State<'_, ConnectionManager>,
+    settings: State<'_, SettingsManager>,
+    request: AggregationExportRequest,
+) -> Result<ExportResult, ErrorDetails> {
+    let client = manager
+        .get_client(&request.connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+    let redaction = settings.resolve_redaction(request.redaction.clone())?;
+
+    let result = client
+        .execute_aggregation(&request.request)
+        .await
+        .map_err(ErrorDetails::from)?;
+
+    let flattened = flatten_aggregations(
+        &request.request.aggregations,
+        result.aggregations.as_ref().unwrap_or(&Value::Null),
+    );
+    let table = match request.view {
+        AggregationView::Table => flattened.table,
+        AggregationView::Chart => flattened.chart.to_table(),
+    };
+
+    let result = settings
+        .export_service()
+        .export_table(&request.target, &request.filename, &request.format, table.columns, &table.rows, redaction.as_ref())
+        .map_err(ErrorDetails::from)?;
+    Ok(ExportResult { warnings: flattened.warnings, ..result })
+}
+
+#[tauri::command]
+pub async fn composite_aggregation_page(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    request: CompositeAggregationRequest,
+) -> Result<CompositePage, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .composite_page(&request, request.after.as_ref())
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+// 遍历全部桶，通过 "composite-batch" 事件逐页推送给前端，返回桶总数
+#[tauri::command]
+pub async fn stream_composite_aggregation(
+    window: Window,
+    manager: State<'_, ConnectionManager>,
+    jobs: State<'_, JobManager>,
+    connection_id: String,
+    job_id: Option<String>,
+    request: CompositeAggregationRequest,
+) -> Result<u64, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    let job_id = job_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancelled = jobs.register(&job_id);
+
+    let mut pages = client.composite_pages(&request);
+    let mut total_buckets = 0u64;
+    let outcome: Result<(), ErrorDetails> = async {
+        while let Some(page) = pages.next_page().await.map_err(ErrorDetails::from)? {
+            total_buckets += page.buckets.len() as u64;
+            let batch = CompositeBatch {
+                job_id: job_id.clone(),
+                buckets: page.buckets,
+                total_buckets,
+                finished: false,
+            };
+            if let Err(e) = window.emit("composite-batch", &batch) {
+                eprintln!("Failed to emit composite batch: {}", e);
+            }
+
+            // 取消时不发送 finished 事件，前端据此区分取消和正常结束
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(ErrorDetails::from(anyhow::anyhow!("已取消")));
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    jobs.finish(&job_id);
+    outcome?;
+
+    let done = CompositeBatch {
+        job_id,
+        buckets: Vec::new(),
+        total_buckets,
+        finished: true,
+    };
+    if let Err(e) = window.emit("composite-batch", &done) {
+        eprintln!("Failed to emit composite batch: {}", e);
+    }
+
+    Ok(total_buckets)
+}
+
+#[tauri::command]
+pub async fn export_composite_aggregation(
+    window: Window,
+    manager: State<'_, ConnectionManager>,
+    settings: State<'_, SettingsManager>,
+    jobs: State<'_, JobManager>,
+    request: CompositeExportRequest,
+) -> Result<ExportResult, ErrorDetails> {
+    let client = manager
+        .get_client(&request.connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    let request = CompositeExportRequest {
+        redaction: settings.resolve_redaction(request.redaction)?,
+        ..request
+    };
+
+    let job_id = request.job_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let cancelled = jobs.register(&job_id);
+
+    let mut emit_progress = progress_emitter(&window);
+
+    let result = settings
+        .export_service()
+        .export_composite_aggregation(&client, &request, &job_id, &cancelled, &mut emit_progress)
+        .await;
+
+    jobs.finish(&job_id);
+    result.map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn cancel_export_job(
+    jobs: State<'_, JobManager>,
+    job_id: String,
+) -> Result<bool, String> {
+    Ok(jobs.cancel(&job_id))
+}
+
+#[tauri::command]
+pub async fn execute_eql(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    query: EqlQuery,
+) -> Result<EqlResult, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .execute_eql(&query)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn get_eql_result(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    id: String,
+) -> Result<EqlResult, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .get_eql_result(&id)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn get_eql_status(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    id: String,
+) -> Result<EqlStatus, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .get_eql_status(&id)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn delete_eql_search(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    id: String,
+) -> Result<Value, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .delete_eql_search(&id)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn execute_esql(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    query: EsqlQuery,
+) -> Result<EsqlResult, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .execute_esql(&query)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn get_esql_async_result(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    id: String,
+) -> Result<EsqlResult, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .get_esql_async_result(&id)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn delete_esql_async_query(
+    manager: State<'_, ConnectionManager>,
+    connection_id: String,
+    id: String,
+) -> Result<Value, ErrorDetails> {
+    let client = manager
+        .get_client(&connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+
+    client
+        .delete_esql_async_query(&id)
+        .await
+        .map_err(ErrorDetails::from)
+}
+
+#[tauri::command]
+pub async fn export_esql_results(
+    manager: State<'_, ConnectionManager>,
+    settings: State<'_, SettingsManager>,
+    request: EsqlExportRequest,
+) -> Result<ExportResult, ErrorDetails> {
+    let client = manager
+        .get_client(&request.connection_id)
+        .ok_or_else(|| ErrorDetails::validation_error("connection_id", "连接不存在"))?;
+    let redaction = settings.resolve_redaction(request.redaction.clone())?;
+
+    // 导出需要同步拿到完整的行数据
+    let query = EsqlQuery {
+        columnar: None,
+        wait_for_completion_timeout: None,
+        keep_alive: None,
+        keep_on_completion: None,
+        ..request.query.clone()
+    };
+    let result = client.execute_esql(&query).await.map_err(ErrorDetails::from)?;
+    let columns = result.columns.iter().map(|c| c.name.clone()).collect();
+
+    settings
+        .export_service()
+        .export_table(&request.target, &request.filename, &request.format, columns, &result.rows, redaction.as_ref())
+        .map_err(ErrorDetails::from)
+}
+
 #[tauri::command]
 pub async fn get_nodes_info(
     manager: State<'_, ConnectionManager>,
@@ -25,6 +25,7 @@ pub enum ErrorType {
     RateLimited,
     Conflict,
     Forbidden,
+    Unsupported,
     UnknownError,
 }
 
@@ -170,7 +171,23 @@ impl ErrorDetails {
         }
     }
 
+    pub fn unsupported_feature(feature: &str, details: Option<String>) -> Self {
+        Self {
+            error_type: ErrorType::Unsupported,
+            code: "UNSUPPORTED_FEATURE".to_string(),
+            message: format!("当前集群不支持 {}", feature),
+            details,
+            suggestion: Some("请确认 Elasticsearch 版本及许可证支持该功能".to_string()),
+            recoverable: false,
+        }
+    }
+
     pub fn from_anyhow_error(error: AnyhowError) -> Self {
+        // 已经是结构化错误时直接返回
+        if let Some(details) = error.downcast_ref::<ErrorDetails>() {
+            return details.clone();
+        }
+
         let error_str = error.to_string();
         
         // 尝试从错误信息中识别错误类型
@@ -245,6 +262,25 @@ pub fn parse_http_error(status: u16, body: &str) -> ErrorDetails {
     ErrorDetails::server_error(status, body.to_string())
 }
 
+// 判断失败响应是否意味着集群没有该接口（版本过旧、OSS 发行版或许可证不支持），
+// 而不是请求本身的错误
+pub fn is_unsupported_endpoint(status: u16, body: &str) -> bool {
+    if body.contains("no handler found for uri")
+        || body.contains("Incorrect HTTP method")
+        || body.contains("invalid_type_name_exception")
+        || body.contains("non-compliant for")
+    {
+        return true;
+    }
+
+    match status {
+        405 => true,
+        // 索引或异步查询 id 不存在时，响应体中会有具体的异常类型
+        404 => !body.contains("index_not_found_exception") && !body.contains("resource_not_found_exception"),
+        _ => false,
+    }
+}
+
 fn extract_index_from_error(error_msg: &str) -> Option<String> {
     // 从错误消息中提取索引名称
     if let Some(start) = error_msg.find("index [") {
@@ -276,6 +312,14 @@ mod tests {
         assert!(!error.recoverable);
     }
 
+    #[test]
+    fn test_is_unsupported_endpoint() {
+        assert!(is_unsupported_endpoint(400, r#"{"error":"no handler found for uri [/logs/_eql/search] and method [POST]","status":400}"#));
+        assert!(is_unsupported_endpoint(404, ""));
+        assert!(!is_unsupported_endpoint(404, r#"{"error":{"type":"index_not_found_exception","reason":"no such index [logs]"}}"#));
+        assert!(!is_unsupported_endpoint(400, r#"{"error":{"type":"verification_exception","reason":"Unknown column [foo]"}}"#));
+    }
+
     #[test]
     fn test_parse_http_error() {
         let es_error_json = r#"{
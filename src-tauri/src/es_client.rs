@@ -1,8 +1,11 @@
 use crate::types::*;
-use crate::error::{ErrorDetails, parse_http_error};
+use crate::error::{ErrorDetails, ErrorType, is_unsupported_endpoint, parse_http_error};
 use anyhow::{Context, Result};
 use reqwest::Client;
 use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::{debug, error};
 
@@ -10,6 +13,8 @@ use tracing::{debug, error};
 pub struct EsClient {
     client: Client,
     connection: EsConnection,
+    // 异步 ES|QL 查询 id 到提交时 columnar 设置的映射，取结果时按相同布局解析
+    esql_layouts: Arc<Mutex<HashMap<String, bool>>>,
 }
 
 impl EsClient {
@@ -20,7 +25,7 @@ impl EsClient {
 
         let client = client_builder.build().unwrap();
 
-        Self { client, connection }
+        Self { client, connection, esql_layouts: Arc::default() }
     }
 
     pub async fn test_connection(&self) -> Result<Value> {
@@ -94,58 +99,99 @@ impl EsClient {
     }
 
     pub async fn search(&self, query: SearchQuery) -> Result<SearchResult> {
+        validate_search_query(&query)?;
         let url = format!("{}/{}/_search", self.connection.url, query.index);
+        let search_body = build_search_body(&query);
         
-        let mut search_body = serde_json::json!({
-            "query": query.query
-        });
+        let response = self.make_post_request(&url, &search_body).await?;
         
-        if let Some(from) = query.from {
-            search_body["from"] = Value::from(from);
+        Ok(parse_search_response(&response))
+    }
+
+    // 批量搜索 (_msearch)，单个查询失败不会影响其他查询
+    pub async fn multi_search(&self, queries: &[SearchQuery]) -> Result<Vec<MultiSearchItem>> {
+        if queries.is_empty() {
+            return Ok(Vec::new());
         }
         
-        if let Some(size) = query.size {
-            search_body["size"] = Value::from(size);
+        for query in queries {
+            validate_search_query(query)?;
         }
         
-        if let Some(sort) = query.sort {
-            search_body["sort"] = Value::Array(sort);
-        }
+        let url = format!("{}/_msearch", self.connection.url);
+        let body = build_msearch_body(queries)?;
         
-        let response = self.make_post_request(&url, &search_body).await?;
+        let response = self.make_bulk_request(&url, &body).await?;
         
-        let total = response.get("hits")
-            .and_then(|h| h.get("total"))
-            .and_then(|t| {
-                if t.is_object() {
-                    t.get("value").and_then(|v| v.as_u64())
-                } else {
-                    t.as_u64()
-                }
-            })
-            .unwrap_or(0);
+        let responses = response.get("responses")
+            .and_then(|r| r.as_array())
+            .ok_or_else(|| anyhow::anyhow!("Invalid msearch response: missing responses"))?;
+        
+        if responses.len() != queries.len() {
+            return Err(anyhow::anyhow!(
+                "Invalid msearch response: expected {} responses, got {}",
+                queries.len(),
+                responses.len()
+            ));
+        }
         
-        let hits = response.get("hits")
-            .and_then(|h| h.get("hits"))
-            .and_then(|h| h.as_array())
-            .unwrap_or(&vec![])
-            .clone();
+        Ok(responses.iter().map(parse_msearch_item).collect())
+    }
+
+    // 统计匹配文档数，index 支持逗号分隔和通配符
+    pub async fn count(&self, index: &str, query: Option<&Value>) -> Result<CountResult> {
+        let url = format!("{}/{}/_count", self.connection.url, index);
         
-        let took = response.get("took").and_then(|t| t.as_u64()).unwrap_or(0);
-        let timed_out = response.get("timed_out").and_then(|t| t.as_bool()).unwrap_or(false);
+        let mut body = serde_json::json!({});
+        if let Some(query) = query {
+            body["query"] = query.clone();
+        }
         
-        // 获取聚合结果
-        let aggregations = response.get("aggregations").cloned();
+        let response = self.make_post_request(&url, &body).await?;
         
-        Ok(SearchResult {
-            total,
-            hits,
-            took,
-            timed_out,
-            aggregations,
+        Ok(CountResult {
+            count: response.get("count").and_then(|c| c.as_u64()).unwrap_or(0),
+            shards: parse_shard_stats(&response),
         })
     }
 
+    // 结合 _count、分片文档数和存储大小估算查询开销
+    pub async fn estimate_query_cost(&self, index: &str, query: Option<&Value>) -> Result<QueryCostEstimate> {
+        let count = self.count(index, query).await?;
+        
+        let indices_url = format!("{}/_cat/indices/{}?format=json&bytes=b", self.connection.url, index);
+        let indices: Vec<Value> = serde_json::from_value(self.make_request(&indices_url).await?)
+            .context("Failed to parse indices response")?;
+        
+        let shards_url = format!("{}/_cat/shards/{}?format=json&bytes=b", self.connection.url, index);
+        let shards_raw: Vec<Value> = serde_json::from_value(self.make_request(&shards_url).await?)
+            .context("Failed to parse shards response")?;
+        
+        let total_documents = indices.iter().map(|i| cat_u64(i, "docs.count")).sum();
+        let primary_store_bytes = indices.iter().map(|i| cat_u64(i, "pri.store.size")).sum();
+        
+        let shards = shards_raw
+            .iter()
+            .map(|shard| ShardDocCount {
+                index: shard.get("index").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                shard: cat_u64(shard, "shard") as u32,
+                primary: shard.get("prirep").and_then(|v| v.as_str()) == Some("p"),
+                state: shard.get("state").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                docs: cat_u64(shard, "docs"),
+                store_bytes: cat_u64(shard, "store"),
+            })
+            .collect();
+        
+        Ok(assess_query_cost(
+            index,
+            count.count,
+            total_documents,
+            indices.len() as u32,
+            primary_store_bytes,
+            shards,
+        ))
+    }
+
     // 流式搜索方法，支持大数据集的内存优化
     pub async fn search_stream(&self, query: SearchQuery, batch_size: usize, max_results: Option<usize>) -> Result<Vec<Value>> {
         let mut all_hits = Vec::new();
@@ -194,8 +240,8 @@ impl EsClient {
         self.make_request(&url).await
     }
 
-    // 提取索引字段名列表（用于自动补全）
-    pub async fn get_field_names(&self, index: &str) -> Result<Vec<String>> {
+    // 提取索引字段列表（用于自动补全），dense_vector 字段附带维度与相似度，供前端判断能否做向量检索
+    pub async fn get_field_names(&self, index: &str) -> Result<Vec<FieldInfo>> {
         let mapping = self.get_mapping(index).await?;
         let mut field_names = Vec::new();
         
@@ -210,22 +256,115 @@ impl EsClient {
         }
         
         // 添加常用的元字段
-        field_names.extend([
-            "_id".to_string(),
-            "_index".to_string(),
-            "_type".to_string(),
-            "_score".to_string(),
-            "_source".to_string(),
-            "@timestamp".to_string(),
-        ]);
-        
-        // 去重并排序
-        field_names.sort();
-        field_names.dedup();
+        for name in ["_id", "_index", "_type", "_score", "_source"] {
+            field_names.push(field_info(name.to_string(), &serde_json::json!({ "type": "metadata" })));
+        }
+        field_names.push(field_info("@timestamp".to_string(), &serde_json::json!({ "type": "date" })));
+        
+        // 去重并排序，同名时保留映射中的字段
+        field_names.sort_by(|a, b| a.name.cmp(&b.name));
+        field_names.dedup_by(|a, b| a.name == b.name);
         
         Ok(field_names)
     }
 
+    // 提取字段路径到映射类型的表，索引模式匹配多个索引时合并（先出现的优先）
+    pub async fn get_field_types(&self, index: &str) -> Result<HashMap<String, String>> {
+        let mapping = self.get_mapping(index).await?;
+        let mut field_types = HashMap::new();
+        
+        if let Value::Object(indices) = &mapping {
+            for index_obj in indices.values() {
+                if let Some(properties) = index_obj.get("mappings").and_then(|m| m.get("properties")) {
+                    extract_field_types(properties, "", &mut field_types);
+                }
+            }
+        }
+        
+        Ok(field_types)
+    }
+
+    // 带类型的字段列表，供前端判断哪些字段可以做向量或地理查询
+    pub async fn get_field_details(&self, index: &str) -> Result<Vec<FieldInfo>> {
+        let mapping = self.get_mapping(index).await?;
+        let mut fields: Vec<FieldInfo> = Vec::new();
+        
+        if let Value::Object(indices) = &mapping {
+            for index_obj in indices.values() {
+                if let Some(properties) = index_obj.get("mappings").and_then(|m| m.get("properties")) {
+                    extract_field_infos(properties, "", &mut fields);
+                }
+            }
+        }
+        
+        // 多个索引中的同名字段只保留第一个
+        let mut seen = std::collections::HashSet::new();
+        fields.retain(|f| seen.insert(f.name.clone()));
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+        
+        Ok(fields)
+    }
+
+    pub async fn get_geo_fields(&self, index: &str) -> Result<Vec<FieldInfo>> {
+        let fields = self.get_field_details(index).await?;
+        
+        Ok(fields
+            .into_iter()
+            .filter(|f| f.field_type == "geo_point" || f.field_type == "geo_shape")
+            .collect())
+    }
+
+    // 网格聚合 + 边界 + 中心点，结果解码为地图可用的单元格
+    pub async fn geo_grid(&self, request: &GeoGridRequest) -> Result<GeoGridResult> {
+        if request.grid_type != "geohash_grid" && request.grid_type != "geotile_grid" {
+            return Err(ErrorDetails::validation_error("grid_type", "仅支持 geohash_grid 或 geotile_grid").into());
+        }
+        
+        let mut grid_params = serde_json::Map::new();
+        if let Some(precision) = request.precision {
+            grid_params.insert("precision".to_string(), Value::from(precision));
+        }
+        if let Some(size) = request.size {
+            grid_params.insert("size".to_string(), Value::from(size));
+        }
+        
+        let geo_agg = |name: &str, agg_type: &str, params: Option<Value>| AggregationConfig {
+            id: name.to_string(),
+            name: name.to_string(),
+            r#type: agg_type.to_string(),
+            field: Some(request.field.clone()),
+            params,
+            buckets_path: None,
+            sub_aggregations: None,
+        };
+        
+        let aggregations = vec![
+            geo_agg("grid", &request.grid_type, Some(Value::Object(grid_params))),
+            geo_agg("bounds", "geo_bounds", None),
+            geo_agg("centroid", "geo_centroid", None),
+        ];
+        
+        let result = self.execute_aggregation(&AggregationRequest {
+            index: request.index.clone(),
+            query: request.query.clone(),
+            aggregations,
+            size: Some(0),
+        }).await?;
+        
+        let aggs = result.aggregations.unwrap_or(Value::Null);
+        let total = result.hits["total"]["value"].as_u64()
+            .or_else(|| result.hits["total"].as_u64())
+            .unwrap_or(0);
+        
+        Ok(GeoGridResult {
+            took: result.took,
+            total,
+            cells: parse_geo_cells(&aggs["grid"], &request.grid_type),
+            bounds: parse_geo_bounds(&aggs["bounds"]),
+            centroid: parse_geo_point(&aggs["centroid"]["location"]),
+        })
+    }
+
     pub async fn create_index(&self, index: &str, mapping: Option<Value>) -> Result<Value> {
         let url = format!("{}/{}", self.connection.url, index);
         let body = mapping.unwrap_or_else(|| serde_json::json!({}));
@@ -333,6 +472,11 @@ impl EsClient {
                 _ => return Err(anyhow::anyhow!("Unsupported bulk operation: {}", operation.action)),
             };
             
+            let mut action_line = action_line;
+            if let Some(routing) = &operation.routing {
+                action_line[operation.action.as_str()]["routing"] = Value::from(routing.as_str());
+            }
+            
             bulk_body.push_str(&serde_json::to_string(&action_line)?);
             bulk_body.push('\n');
             
@@ -527,6 +671,92 @@ impl EsClient {
         self.make_delete_request(&url).await
     }
 
+    // 获取所有搜索模板（存储脚本中 lang 为 mustache 的部分）
+    pub async fn list_search_templates(&self) -> Result<Vec<SearchTemplate>> {
+        let url = format!("{}/_cluster/state/metadata?filter_path=metadata.stored_scripts", self.connection.url);
+        let response = self.make_request(&url).await?;
+        
+        let empty_map = serde_json::Map::new();
+        let scripts = response
+            .get("metadata")
+            .and_then(|m| m.get("stored_scripts"))
+            .and_then(|s| s.as_object())
+            .unwrap_or(&empty_map);
+        
+        let mut templates: Vec<SearchTemplate> = scripts
+            .iter()
+            .filter(|(_, script)| script.get("lang").and_then(|l| l.as_str()) == Some("mustache"))
+            .map(|(id, script)| SearchTemplate {
+                id: id.clone(),
+                source: parse_template_source(script.get("source").unwrap_or(&Value::Null)),
+            })
+            .collect();
+        templates.sort_by(|a, b| a.id.cmp(&b.id));
+        
+        Ok(templates)
+    }
+
+    // 获取特定搜索模板
+    pub async fn get_search_template(&self, id: &str) -> Result<SearchTemplate> {
+        let url = format!("{}/_scripts/{}", self.connection.url, id);
+        let response = self.make_request(&url).await?;
+        
+        let script = response
+            .get("script")
+            .ok_or_else(|| anyhow::anyhow!("Search template {} not found", id))?;
+        
+        Ok(SearchTemplate {
+            id: id.to_string(),
+            source: parse_template_source(script.get("source").unwrap_or(&Value::Null)),
+        })
+    }
+
+    // 创建或更新搜索模板
+    pub async fn put_search_template(&self, template: &SearchTemplate) -> Result<Value> {
+        if template.id.trim().is_empty() {
+            return Err(anyhow::anyhow!("Search template id is required"));
+        }
+        
+        let url = format!("{}/_scripts/{}", self.connection.url, template.id);
+        let body = serde_json::json!({
+            "script": {
+                "lang": "mustache",
+                "source": template.source
+            }
+        });
+        
+        self.make_put_request(&url, &body).await
+    }
+
+    // 删除搜索模板
+    pub async fn delete_search_template(&self, id: &str) -> Result<Value> {
+        let url = format!("{}/_scripts/{}", self.connection.url, id);
+        self.make_delete_request(&url).await
+    }
+
+    // 渲染搜索模板，返回生成的 DSL
+    pub async fn render_search_template(&self, query: &SearchTemplateQuery) -> Result<Value> {
+        let url = format!("{}/_render/template", self.connection.url);
+        let body = build_search_template_body(query)?;
+        
+        let response = self.make_post_request(&url, &body).await?;
+        
+        response
+            .get("template_output")
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Invalid render response: missing template_output"))
+    }
+
+    // 使用搜索模板执行搜索
+    pub async fn search_template(&self, query: &SearchTemplateQuery) -> Result<SearchResult> {
+        let url = format!("{}/{}/_search/template", self.connection.url, query.index);
+        let body = build_search_template_body(query)?;
+        
+        let response = self.make_post_request(&url, &body).await?;
+        
+        Ok(parse_search_response(&response))
+    }
+
     // 聚合查询
     pub async fn execute_aggregation(&self, request: &AggregationRequest) -> Result<AggregationResult> {
         let url = format!("{}{}{}/_search", self.connection.url, 
@@ -543,7 +773,8 @@ impl EsClient {
             search_body["query"] = query.clone();
         }
         
-        // 构建聚合
+        // 构建聚合，管道聚合的 buckets_path 先在配置树内校验
+        validate_pipeline_aggregations(&request.aggregations, None)?;
         let aggs = build_aggregations(&request.aggregations)?;
         search_body["aggs"] = aggs;
         
@@ -559,61 +790,105 @@ impl EsClient {
         })
     }
 
-    // SQL 查询
-    pub async fn execute_sql(&self, query: &SqlQuery) -> Result<SqlResult> {
-        let url = format!("{}/_sql", self.connection.url);
+    // 打开 PIT（point in time），返回 PIT id
+    pub async fn open_point_in_time(&self, index: &str, keep_alive: &str) -> Result<String> {
+        let url = format!("{}/{}/_pit?keep_alive={}", self.connection.url, index, keep_alive);
+        let response = self.make_feature_request(reqwest::Method::POST, &url, None, "Point in time").await?;
+        
+        response["id"].as_str()
+            .map(|id| id.to_string())
+            .ok_or_else(|| anyhow::anyhow!("PIT response missing id"))
+    }
+    
+    pub async fn close_point_in_time(&self, pit_id: &str) -> Result<()> {
+        let url = format!("{}/_pit", self.connection.url);
+        let body = serde_json::json!({ "id": pit_id });
+        self.make_feature_request(reqwest::Method::DELETE, &url, Some(&body), "Point in time").await?;
+        Ok(())
+    }
+    
+    pub async fn clear_scroll(&self, scroll_id: &str) -> Result<()> {
+        let url = format!("{}/_search/scroll", self.connection.url);
+        let body = serde_json::json!({ "scroll_id": scroll_id });
+        self.make_feature_request(reqwest::Method::DELETE, &url, Some(&body), "Scroll").await?;
+        Ok(())
+    }
+    
+    // 逐页遍历全部命中，优先使用 PIT + search_after，集群不支持 PIT 时退回 scroll。
+    // 用完后必须调用 HitPages::close 释放服务端上下文。
+    pub async fn hit_pages(&self, query: &SearchQuery, page_size: u32) -> Result<HitPages<'_>> {
+        self.open_hit_pages(query, page_size, None).await
+    }
+    
+    // 从断点记录的排序值之后继续遍历，只能使用新开的 PIT；
+    // 需要显式排序且最后一项唯一，_shard_doc 的值在新 PIT 中没有意义
+    pub async fn hit_pages_after(&self, query: &SearchQuery, page_size: u32, search_after: Value) -> Result<HitPages<'_>> {
+        self.open_hit_pages(query, page_size, Some(search_after)).await
+    }
+    
+    async fn open_hit_pages(&self, query: &SearchQuery, page_size: u32, search_after: Option<Value>) -> Result<HitPages<'_>> {
+        validate_search_query(query)?;
         
-        let mut request_body = serde_json::json!({
-            "query": query.query
-        });
+        let cursor = match self.open_point_in_time(&query.index, HIT_PAGES_KEEP_ALIVE).await {
+            Ok(pit_id) => HitCursor::Pit { pit_id, search_after },
+            Err(e) if search_after.is_some() => {
+                return Err(anyhow::anyhow!("续传导出需要集群支持 point in time: {}", e));
+            }
+            Err(e) if e.downcast_ref::<ErrorDetails>().is_some_and(|d| matches!(d.error_type, ErrorType::Unsupported)) => {
+                debug!("Point in time unsupported, falling back to scroll: {}", e);
+                HitCursor::Scroll { scroll_id: None }
+            }
+            Err(e) => return Err(e),
+        };
         
-        if let Some(fetch_size) = query.fetch_size {
-            request_body["fetch_size"] = serde_json::Value::from(fetch_size);
-        }
+        Ok(HitPages {
+            client: self,
+            index: query.index.clone(),
+            base_body: build_export_base_body(query),
+            page_size,
+            cursor,
+            exhausted: false,
+            total_hits: None,
+            first_request: true,
+        })
+    }
+    
+    async fn search_hits_page(&self, url: &str, body: &Value) -> Result<Value> {
+        debug!("Hit page request: {}", serde_json::to_string(body).unwrap_or_default());
+        self.make_post_request(url, body).await
+    }
+
+    // 取 composite 聚合的一页，after 为上一页的 after_key
+    pub async fn composite_page(&self, request: &CompositeAggregationRequest, after: Option<&Value>) -> Result<CompositePage> {
+        let url = format!("{}/{}/_search", self.connection.url, request.index);
+        let body = build_composite_body(request, after)?;
         
-        if let Some(request_timeout) = &query.request_timeout {
-            request_body["request_timeout"] = serde_json::Value::from(request_timeout.as_str());
-        }
+        debug!("Composite aggregation query: {}", serde_json::to_string_pretty(&body).unwrap_or_default());
         
-        if let Some(page_timeout) = &query.page_timeout {
-            request_body["page_timeout"] = serde_json::Value::from(page_timeout.as_str());
+        let response = self.make_post_request(&url, &body).await?;
+        Ok(parse_composite_page(&response))
+    }
+    
+    // 按 after_key 逐页遍历 composite 聚合，直到桶取完
+    pub fn composite_pages<'a>(&'a self, request: &'a CompositeAggregationRequest) -> CompositePages<'a> {
+        CompositePages {
+            client: self,
+            request,
+            after_key: request.after.clone(),
+            exhausted: false,
         }
+    }
+
+    // SQL 查询
+    pub async fn execute_sql(&self, query: &SqlQuery) -> Result<SqlResult> {
+        let url = format!("{}/_sql", self.connection.url);
+        let request_body = build_sql_body(query, true);
         
         debug!("SQL query request: {}", serde_json::to_string_pretty(&request_body).unwrap_or_default());
         
         let response = self.make_post_request(&url, &request_body).await?;
         
-        let columns = response.get("columns")
-            .and_then(|c| c.as_array())
-            .map(|cols| {
-                cols.iter()
-                    .filter_map(|col| {
-                        let name = col.get("name")?.as_str()?.to_string();
-                        let type_str = col.get("type")?.as_str()?.to_string();
-                        Some(SqlColumn { name, r#type: type_str })
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-            
-        let rows = response.get("rows")
-            .and_then(|r| r.as_array())
-            .map(|rows_array| {
-                rows_array.iter()
-                    .filter_map(|row| row.as_array().cloned())
-                    .collect()
-            })
-            .unwrap_or_default();
-            
-        let cursor = response.get("cursor")
-            .and_then(|c| c.as_str())
-            .map(|s| s.to_string());
-        
-        Ok(SqlResult {
-            columns,
-            rows,
-            cursor,
-        })
+        Ok(parse_sql_response(&response))
     }
     
     // SQL 游标查询 (用于分页)
@@ -626,37 +901,72 @@ impl EsClient {
         
         let response = self.make_post_request(&url, &request_body).await?;
         
-        let columns = response.get("columns")
-            .and_then(|c| c.as_array())
-            .map(|cols| {
-                cols.iter()
-                    .filter_map(|col| {
-                        let name = col.get("name")?.as_str()?.to_string();
-                        let type_str = col.get("type")?.as_str()?.to_string();
-                        Some(SqlColumn { name, r#type: type_str })
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
+        Ok(parse_sql_response(&response))
+    }
+    
+    // 查看 SQL 语句生成的 DSL
+    pub async fn translate_sql(&self, query: &SqlQuery) -> Result<Value> {
+        let url = format!("{}/_sql/translate", self.connection.url);
+        let request_body = build_sql_body(query, false);
+        
+        self.make_post_request(&url, &request_body).await
+    }
+    
+    // 以 csv/tsv/txt 格式下载 SQL 结果，逐块写入 writer 并自动跟随游标翻页，
+    // 不会在内存中构造 SqlResult.rows
+    pub async fn download_sql<W: Write>(&self, query: &SqlQuery, format: SqlFormat, writer: &mut W) -> Result<(u64, u32)> {
+        let mut cursor = None;
+        let outcome = self.download_sql_pages(query, format, writer, &mut cursor).await;
+        
+        // 中途读取或写入失败时游标仍占用服务端资源，需要主动关闭
+        if outcome.is_err() {
+            if let Some(open_cursor) = &cursor {
+                if let Err(e) = self.close_sql_cursor(open_cursor).await {
+                    tracing::warn!("Failed to close SQL cursor after download failure: {}", e);
+                }
+            }
+        }
+        outcome
+    }
+    
+    // cursor 记录尚未读完的游标，供出错时关闭
+    async fn download_sql_pages<W: Write>(
+        &self,
+        query: &SqlQuery,
+        format: SqlFormat,
+        writer: &mut W,
+        cursor: &mut Option<String>,
+    ) -> Result<(u64, u32)> {
+        let url = format!("{}/_sql?format={}", self.connection.url, format.as_param());
+        let mut request_body = build_sql_body(query, false);
+        let mut bytes_written = 0u64;
+        let mut pages = 0u32;
+        
+        loop {
+            let mut response = self.make_raw_post_request(&url, &request_body).await?;
+            pages += 1;
             
-        let rows = response.get("rows")
-            .and_then(|r| r.as_array())
-            .map(|rows_array| {
-                rows_array.iter()
-                    .filter_map(|row| row.as_array().cloned())
-                    .collect()
-            })
-            .unwrap_or_default();
+            // 文本格式的游标通过响应头返回
+            *cursor = response
+                .headers()
+                .get("Cursor")
+                .and_then(|v| v.to_str().ok())
+                .filter(|c| !c.is_empty())
+                .map(|c| c.to_string());
+            
+            while let Some(chunk) = response.chunk().await.context("Failed to read SQL response")? {
+                writer.write_all(&chunk)?;
+                bytes_written += chunk.len() as u64;
+            }
             
-        let cursor = response.get("cursor")
-            .and_then(|c| c.as_str())
-            .map(|s| s.to_string());
+            match cursor {
+                Some(next) => request_body = serde_json::json!({ "cursor": next }),
+                None => break,
+            }
+        }
         
-        Ok(SqlResult {
-            columns,
-            rows,
-            cursor,
-        })
+        writer.flush()?;
+        Ok((bytes_written, pages))
     }
     
     // 关闭 SQL 游标
@@ -671,6 +981,78 @@ impl EsClient {
         Ok(())
     }
 
+    // EQL 查询
+    pub async fn execute_eql(&self, query: &EqlQuery) -> Result<EqlResult> {
+        let url = format!("{}/{}/_eql/search", self.connection.url, query.index);
+        let body = build_eql_body(query);
+        
+        debug!("EQL query request: {}", serde_json::to_string_pretty(&body).unwrap_or_default());
+        
+        let response = self.make_feature_request(reqwest::Method::POST, &url, Some(&body), "EQL").await?;
+        Ok(parse_eql_response(&response))
+    }
+
+    // 获取异步 EQL 查询的结果
+    pub async fn get_eql_result(&self, id: &str) -> Result<EqlResult> {
+        let url = format!("{}/_eql/search/{}", self.connection.url, id);
+        let response = self.make_feature_request(reqwest::Method::GET, &url, None, "EQL").await?;
+        Ok(parse_eql_response(&response))
+    }
+
+    // 获取异步 EQL 查询的状态
+    pub async fn get_eql_status(&self, id: &str) -> Result<EqlStatus> {
+        let url = format!("{}/_eql/search/status/{}", self.connection.url, id);
+        let response = self.make_feature_request(reqwest::Method::GET, &url, None, "EQL").await?;
+        
+        Ok(EqlStatus {
+            id: response.get("id").and_then(|v| v.as_str()).unwrap_or(id).to_string(),
+            is_running: response.get("is_running").and_then(|v| v.as_bool()).unwrap_or(false),
+            is_partial: response.get("is_partial").and_then(|v| v.as_bool()).unwrap_or(false),
+            start_time_in_millis: response.get("start_time_in_millis").and_then(|v| v.as_u64()),
+            expiration_time_in_millis: response.get("expiration_time_in_millis").and_then(|v| v.as_u64()),
+            completion_status: response.get("completion_status").and_then(|v| v.as_u64()).map(|v| v as u16),
+        })
+    }
+
+    // 删除异步 EQL 查询（运行中的查询会被取消）
+    pub async fn delete_eql_search(&self, id: &str) -> Result<Value> {
+        let url = format!("{}/_eql/search/{}", self.connection.url, id);
+        self.make_feature_request(reqwest::Method::DELETE, &url, None, "EQL").await
+    }
+
+    // ES|QL 查询，设置 wait_for_completion_timeout 时走异步接口
+    pub async fn execute_esql(&self, query: &EsqlQuery) -> Result<EsqlResult> {
+        let endpoint = if query.wait_for_completion_timeout.is_some() { "_query/async" } else { "_query" };
+        let url = format!("{}/{}", self.connection.url, endpoint);
+        let body = build_esql_body(query);
+        
+        debug!("ES|QL query request: {}", serde_json::to_string_pretty(&body).unwrap_or_default());
+        
+        let response = self.make_feature_request(reqwest::Method::POST, &url, Some(&body), "ES|QL").await?;
+        let columnar = query.columnar.unwrap_or(false);
+        if let Some(id) = response.get("id").and_then(|v| v.as_str()) {
+            self.esql_layouts.lock().unwrap().insert(id.to_string(), columnar);
+        }
+        Ok(parse_esql_response(&response, columnar))
+    }
+
+    // 获取异步 ES|QL 查询的结果，布局沿用提交查询时的 columnar 设置
+    pub async fn get_esql_async_result(&self, id: &str) -> Result<EsqlResult> {
+        let url = format!("{}/_query/async/{}", self.connection.url, id);
+        let response = self.make_feature_request(reqwest::Method::GET, &url, None, "ES|QL").await?;
+        let known = self.esql_layouts.lock().unwrap().get(id).copied();
+        let columnar = known.unwrap_or_else(|| esql_values_are_columnar(&response));
+        Ok(parse_esql_response(&response, columnar))
+    }
+
+    // 删除异步 ES|QL 查询
+    pub async fn delete_esql_async_query(&self, id: &str) -> Result<Value> {
+        let url = format!("{}/_query/async/{}", self.connection.url, id);
+        let response = self.make_feature_request(reqwest::Method::DELETE, &url, None, "ES|QL").await?;
+        self.esql_layouts.lock().unwrap().remove(id);
+        Ok(response)
+    }
+
     // 获取节点信息
     pub async fn get_nodes_info(&self) -> Result<Vec<NodeInfo>> {
         let url = format!("{}/_nodes", self.connection.url);
@@ -827,6 +1209,81 @@ impl EsClient {
         Ok(json)
     }
 
+    // 返回原始响应，供需要流式读取响应体的调用方使用
+    async fn make_raw_post_request(&self, url: &str, body: &Value) -> Result<reqwest::Response> {
+        debug!("Making raw POST request to: {}", url);
+        
+        let mut request = self.client.post(url).json(body);
+        
+        if let Some(username) = &self.connection.username {
+            if let Some(password) = &self.connection.password {
+                request = request.basic_auth(username, Some(password));
+            }
+        }
+        
+        for (key, value) in &self.connection.headers {
+            request = request.header(key, value);
+        }
+        
+        let response = request.send().await
+            .context("Failed to send POST request")?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            error!("POST request failed with status {}: {}", status, text);
+            return Err(parse_http_error(status.as_u16(), &text).into());
+        }
+        
+        Ok(response)
+    }
+
+    // 请求可选功能（EQL、ES|QL 等）的接口，集群不支持时返回 UNSUPPORTED_FEATURE 而不是原始 HTTP 错误
+    async fn make_feature_request(&self, method: reqwest::Method, url: &str, body: Option<&Value>, feature: &str) -> Result<Value> {
+        debug!("Making {} request to: {}", method, url);
+        
+        let mut request = self.client.request(method.clone(), url);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        
+        if let Some(username) = &self.connection.username {
+            if let Some(password) = &self.connection.password {
+                request = request.basic_auth(username, Some(password));
+            }
+        }
+        
+        for (key, value) in &self.connection.headers {
+            request = request.header(key, value);
+        }
+        
+        let response = request.send().await
+            .with_context(|| format!("Failed to send {} request", method))?;
+        
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            error!("{} request failed with status {}: {}", method, status, text);
+            if is_unsupported_endpoint(status.as_u16(), &text) {
+                return Err(ErrorDetails::unsupported_feature(feature, Some(text)).into());
+            }
+            return Err(parse_http_error(status.as_u16(), &text).into());
+        }
+        
+        let text = response.text().await
+            .with_context(|| format!("Failed to read {} response", feature))?;
+        
+        // 只有 DELETE 可能返回空响应体，视为已确认；其他方法的响应必须能解析
+        if method == reqwest::Method::DELETE && text.trim().is_empty() {
+            return Ok(serde_json::json!({"acknowledged": true}));
+        }
+        
+        let json: Value = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse {} response", feature))?;
+        
+        Ok(json)
+    }
+
     async fn make_put_request(&self, url: &str, body: &Value) -> Result<Value> {
         debug!("Making PUT request to: {}", url);
         
@@ -923,25 +1380,907 @@ impl EsClient {
     }
 }
 
-// 构建聚合查询的辅助函数
-fn build_aggregations(aggregations: &[AggregationConfig]) -> Result<Value> {
-    let mut aggs_object = serde_json::Map::new();
+// 构建搜索请求体的辅助函数
+fn build_search_body(query: &SearchQuery) -> Value {
+    let mut search_body = serde_json::json!({});
     
-    for agg in aggregations {
-        let agg_definition = build_single_aggregation(agg)?;
-        aggs_object.insert(agg.name.clone(), agg_definition);
+    // 先写入透传参数，显式建模的字段优先级更高
+    for (key, value) in &query.extra {
+        search_body[key] = value.clone();
     }
     
-    Ok(Value::Object(aggs_object))
-}
-
-fn build_single_aggregation(agg: &AggregationConfig) -> Result<Value> {
-    let mut agg_def = serde_json::Map::new();
+    // 纯 kNN 搜索可以不带 query
+    if !query.query.is_null() {
+        search_body["query"] = query.query.clone();
+    }
     
-    match agg.r#type.as_str() {
+    if let Some(from) = query.from {
+        search_body["from"] = Value::from(from);
+    }
+    
+    if let Some(size) = query.size {
+        search_body["size"] = Value::from(size);
+    }
+    
+    if let Some(sort) = &query.sort {
+        search_body["sort"] = Value::Array(sort.clone());
+    }
+    
+    if let Some(source) = &query.source {
+        search_body["_source"] = source.clone();
+    }
+    
+    if let Some(highlight) = &query.highlight {
+        search_body["highlight"] = highlight.clone();
+    }
+    
+    if let Some(track_total_hits) = &query.track_total_hits {
+        search_body["track_total_hits"] = track_total_hits.clone();
+    }
+    
+    if let Some(aggs) = &query.aggs {
+        search_body["aggs"] = aggs.clone();
+    }
+    
+    if let Some(collapse) = &query.collapse {
+        search_body["collapse"] = collapse.clone();
+    }
+    
+    if let Some(post_filter) = &query.post_filter {
+        search_body["post_filter"] = post_filter.clone();
+    }
+    
+    if let Some(docvalue_fields) = &query.docvalue_fields {
+        search_body["docvalue_fields"] = Value::Array(docvalue_fields.clone());
+    }
+    
+    if let Some(stored_fields) = &query.stored_fields {
+        search_body["stored_fields"] = Value::from(stored_fields.clone());
+    }
+    
+    if let Some(runtime_mappings) = &query.runtime_mappings {
+        search_body["runtime_mappings"] = runtime_mappings.clone();
+    }
+    
+    if let Some(timeout) = &query.timeout {
+        search_body["timeout"] = Value::from(timeout.as_str());
+    }
+    
+    let knn = query.knn.iter().flatten();
+    
+    if let Some(rrf) = &query.rrf {
+        // 混合检索：query 作为 standard retriever，每个 knn 子句作为 knn retriever
+        let mut retrievers = Vec::new();
+        if let Some(standard_query) = search_body.as_object_mut().and_then(|b| b.remove("query")) {
+            retrievers.push(serde_json::json!({ "standard": { "query": standard_query } }));
+        }
+        retrievers.extend(knn.map(|clause| serde_json::json!({ "knn": build_knn_clause(clause, false) })));
+        
+        let mut rrf_body = serde_json::json!({ "retrievers": retrievers });
+        if let Some(rank_constant) = rrf.rank_constant {
+            rrf_body["rank_constant"] = Value::from(rank_constant);
+        }
+        if let Some(rank_window_size) = rrf.rank_window_size {
+            rrf_body["rank_window_size"] = Value::from(rank_window_size);
+        }
+        search_body["retriever"] = serde_json::json!({ "rrf": rrf_body });
+    } else if query.knn.as_ref().is_some_and(|k| !k.is_empty()) {
+        search_body["knn"] = Value::Array(knn.map(|clause| build_knn_clause(clause, true)).collect());
+    }
+    
+    search_body
+}
+
+const HIT_PAGES_KEEP_ALIVE: &str = "2m";
+
+enum HitCursor {
+    Pit { pit_id: String, search_after: Option<Value> },
+    Scroll { scroll_id: Option<String> },
+}
+
+pub struct HitPages<'a> {
+    client: &'a EsClient,
+    index: String,
+    base_body: Value,
+    page_size: u32,
+    cursor: HitCursor,
+    exhausted: bool,
+    total_hits: Option<u64>,
+    first_request: bool,
+}
+
+impl HitPages<'_> {
+    // 返回 None 表示命中已取完；page_size 可在两页之间调整（如只剩少量配额时）
+    pub async fn next_page(&mut self) -> Result<Option<Vec<Value>>> {
+        if self.exhausted {
+            return Ok(None);
+        }
+        
+        let base_url = &self.client.connection.url;
+        // 只在第一次请求时统计命中总数，用于估算剩余时间
+        let track_total_hits = std::mem::replace(&mut self.first_request, false);
+        let response = match &self.cursor {
+            HitCursor::Pit { pit_id, search_after } => {
+                let mut body = build_pit_page_body(&self.base_body, pit_id, HIT_PAGES_KEEP_ALIVE, self.page_size, search_after.as_ref());
+                body["track_total_hits"] = Value::Bool(track_total_hits);
+                self.client.search_hits_page(&format!("{}/_search", base_url), &body).await?
+            }
+            HitCursor::Scroll { scroll_id: None } => {
+                let mut body = self.base_body.clone();
+                body["size"] = Value::from(self.page_size);
+                body["track_total_hits"] = Value::Bool(track_total_hits);
+                let url = format!("{}/{}/_search?scroll={}", base_url, self.index, HIT_PAGES_KEEP_ALIVE);
+                self.client.search_hits_page(&url, &body).await?
+            }
+            HitCursor::Scroll { scroll_id: Some(scroll_id) } => {
+                let body = serde_json::json!({ "scroll": HIT_PAGES_KEEP_ALIVE, "scroll_id": scroll_id });
+                self.client.search_hits_page(&format!("{}/_search/scroll", base_url), &body).await?
+            }
+        };
+        
+        let hits = response["hits"]["hits"].as_array().cloned().unwrap_or_default();
+        if track_total_hits {
+            // 7.x 之后为 {"value": n}，更早的版本直接是数字
+            let total = &response["hits"]["total"];
+            self.total_hits = total["value"].as_u64().or_else(|| total.as_u64());
+        }
+        
+        match &mut self.cursor {
+            HitCursor::Pit { pit_id, search_after } => {
+                // PIT id 可能在每次响应中更新
+                if let Some(new_id) = response["pit_id"].as_str() {
+                    *pit_id = new_id.to_string();
+                }
+                *search_after = hits.last().and_then(|hit| hit.get("sort")).cloned();
+            }
+            HitCursor::Scroll { scroll_id } => {
+                *scroll_id = response["_scroll_id"].as_str().map(|id| id.to_string());
+            }
+        }
+        
+        if hits.len() < self.page_size as usize {
+            self.exhausted = true;
+        }
+        
+        if hits.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(hits))
+        }
+    }
+    
+    pub fn set_page_size(&mut self, page_size: u32) {
+        self.page_size = page_size;
+    }
+    
+    // 查询匹配的命中总数，取到第一页之后才有值
+    pub fn total_hits(&self) -> Option<u64> {
+        self.total_hits
+    }
+    
+    // 最后一条命中的排序值，PIT 模式下可用于断点续传
+    pub fn search_after(&self) -> Option<&Value> {
+        match &self.cursor {
+            HitCursor::Pit { search_after, .. } => search_after.as_ref(),
+            HitCursor::Scroll { .. } => None,
+        }
+    }
+    
+    // 释放 PIT 或 scroll 上下文，失败只记录日志
+    pub async fn close(self) {
+        let result = match &self.cursor {
+            HitCursor::Pit { pit_id, .. } => self.client.close_point_in_time(pit_id).await,
+            HitCursor::Scroll { scroll_id: Some(scroll_id) } => self.client.clear_scroll(scroll_id).await,
+            HitCursor::Scroll { scroll_id: None } => Ok(()),
+        };
+        
+        if let Err(e) = result {
+            tracing::warn!("Failed to release search context: {}", e);
+        }
+    }
+}
+
+// 导出用的基础请求体：去掉分页和聚合，未指定排序时使用最廉价的排序
+fn build_export_base_body(query: &SearchQuery) -> Value {
+    let mut body = build_search_body(query);
+    
+    if let Some(map) = body.as_object_mut() {
+        map.remove("from");
+        map.remove("size");
+        map.remove("aggs");
+        map.remove("aggregations");
+    }
+    
+    body
+}
+
+fn build_pit_page_body(base: &Value, pit_id: &str, keep_alive: &str, page_size: u32, search_after: Option<&Value>) -> Value {
+    let mut body = base.clone();
+    body["size"] = Value::from(page_size);
+    body["pit"] = serde_json::json!({ "id": pit_id, "keep_alive": keep_alive });
+    
+    // PIT 搜索会自动追加 _shard_doc 作为决胜排序
+    if body.get("sort").is_none() {
+        body["sort"] = serde_json::json!(["_shard_doc"]);
+    }
+    
+    if let Some(after) = search_after {
+        body["search_after"] = after.clone();
+    }
+    
+    body
+}
+
+pub struct CompositePages<'a> {
+    client: &'a EsClient,
+    request: &'a CompositeAggregationRequest,
+    after_key: Option<Value>,
+    exhausted: bool,
+}
+
+impl CompositePages<'_> {
+    // 返回 None 表示已没有更多桶
+    pub async fn next_page(&mut self) -> Result<Option<CompositePage>> {
+        if self.exhausted {
+            return Ok(None);
+        }
+        
+        let page = self.client.composite_page(self.request, self.after_key.as_ref()).await?;
+        if page.buckets.is_empty() || page.after_key.is_none() {
+            self.exhausted = true;
+        }
+        self.after_key = page.after_key.clone();
+        
+        if page.buckets.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(page))
+        }
+    }
+}
+
+const COMPOSITE_AGG_NAME: &str = "composite_buckets";
+const DEFAULT_COMPOSITE_PAGE_SIZE: u32 = 1000;
+
+fn build_composite_body(request: &CompositeAggregationRequest, after: Option<&Value>) -> Result<Value> {
+    if request.sources.is_empty() {
+        return Err(ErrorDetails::validation_error("sources", "composite 聚合至少需要一个来源").into());
+    }
+    
+    let mut sources = Vec::with_capacity(request.sources.len());
+    for source in &request.sources {
+        if !matches!(source.r#type.as_str(), "terms" | "histogram" | "date_histogram") {
+            return Err(ErrorDetails::validation_error(
+                "sources",
+                &format!("来源 '{}' 的类型 {} 不支持，仅支持 terms/histogram/date_histogram", source.name, source.r#type),
+            ).into());
+        }
+        
+        let mut source_def = merge_agg_params(serde_json::json!({ "field": source.field }), source.params.as_ref());
+        if let Some(missing_bucket) = source.missing_bucket {
+            source_def["missing_bucket"] = Value::Bool(missing_bucket);
+        }
+        
+        sources.push(serde_json::json!({ source.name.as_str(): { source.r#type.as_str(): source_def } }));
+    }
+    
+    let mut composite = serde_json::json!({
+        "size": request.page_size.unwrap_or(DEFAULT_COMPOSITE_PAGE_SIZE),
+        "sources": sources
+    });
+    if let Some(after) = after {
+        composite["after"] = after.clone();
+    }
+    
+    let mut composite_agg = serde_json::json!({ "composite": composite });
+    if let Some(sub_aggs) = request.sub_aggregations.as_ref().filter(|s| !s.is_empty()) {
+        validate_pipeline_aggregations(sub_aggs, Some("composite"))?;
+        composite_agg["aggs"] = build_aggregations(sub_aggs)?;
+    }
+    
+    let mut body = serde_json::json!({
+        "size": 0,
+        "aggs": { COMPOSITE_AGG_NAME: composite_agg }
+    });
+    if let Some(query) = &request.query {
+        body["query"] = query.clone();
+    }
+    
+    Ok(body)
+}
+
+fn parse_composite_page(response: &Value) -> CompositePage {
+    let agg = &response["aggregations"][COMPOSITE_AGG_NAME];
+    
+    let buckets = agg["buckets"]
+        .as_array()
+        .map(|buckets| {
+            buckets.iter().filter_map(|bucket| {
+                let mut metrics = bucket.as_object()?.clone();
+                let key = metrics.remove("key")?.as_object()?.clone();
+                let doc_count = metrics.remove("doc_count").and_then(|c| c.as_u64()).unwrap_or(0);
+                Some(CompositeBucket { key, doc_count, metrics })
+            }).collect()
+        })
+        .unwrap_or_default();
+    
+    CompositePage {
+        buckets,
+        after_key: agg.get("after_key").filter(|k| !k.is_null()).cloned(),
+    }
+}
+
+// 把 composite 桶展开为一行：来源键、doc_count，然后是各子聚合的值
+pub fn composite_bucket_row(bucket: &CompositeBucket, request: &CompositeAggregationRequest) -> Vec<Value> {
+    let mut row: Vec<Value> = request.sources.iter()
+        .map(|s| bucket.key.get(&s.name).cloned().unwrap_or(Value::Null))
+        .collect();
+    row.push(Value::from(bucket.doc_count));
+    
+    for sub_agg in request.sub_aggregations.iter().flatten() {
+        let metric = bucket.metrics.get(&sub_agg.name).cloned().unwrap_or(Value::Null);
+        // 单值度量取 value，多值度量（如 stats）保留整个对象
+        row.push(metric.get("value").cloned().unwrap_or(metric));
+    }
+    
+    row
+}
+
+pub fn composite_columns(request: &CompositeAggregationRequest) -> Vec<String> {
+    request.sources.iter().map(|s| s.name.clone())
+        .chain(std::iter::once("doc_count".to_string()))
+        .chain(request.sub_aggregations.iter().flatten().map(|a| a.name.clone()))
+        .collect()
+}
+
+// knn retriever 不接受 boost，只有顶层 knn 子句才写入
+fn build_knn_clause(knn: &KnnQuery, top_level: bool) -> Value {
+    let mut clause = serde_json::json!({
+        "field": knn.field,
+        "query_vector": knn.query_vector,
+        "k": knn.k,
+        "num_candidates": knn.num_candidates
+    });
+    
+    if let Some(filter) = &knn.filter {
+        clause["filter"] = filter.clone();
+    }
+    
+    if let Some(similarity) = knn.similarity {
+        clause["similarity"] = Value::from(similarity);
+    }
+    
+    if let Some(boost) = knn.boost.filter(|_| top_level) {
+        clause["boost"] = Value::from(boost);
+    }
+    
+    clause
+}
+
+// 发送前校验 ES 会拒绝的参数组合，给出更明确的错误
+fn validate_search_query(query: &SearchQuery) -> Result<()> {
+    for knn in query.knn.iter().flatten() {
+        if knn.query_vector.is_empty() {
+            return Err(ErrorDetails::validation_error("knn.query_vector", "查询向量不能为空").into());
+        }
+        if knn.k == 0 {
+            return Err(ErrorDetails::validation_error("knn.k", "k 必须大于 0").into());
+        }
+        if knn.num_candidates < knn.k {
+            return Err(ErrorDetails::validation_error("knn.num_candidates", "num_candidates 不能小于 k").into());
+        }
+    }
+    
+    if query.rrf.is_some() {
+        let knn = query.knn.as_deref().unwrap_or_default();
+        if knn.is_empty() {
+            return Err(ErrorDetails::validation_error("rrf", "混合检索至少需要一个 knn 子句").into());
+        }
+        // rrf 融合至少两个 retriever：非空的 query 加 knn，或多个 knn
+        if knn.len() + usize::from(!query.query.is_null()) < 2 {
+            return Err(ErrorDetails::validation_error("rrf", "混合检索至少需要两个检索子句：请同时提供 query，或提供多个 knn 子句").into());
+        }
+        if knn.iter().any(|k| k.boost.is_some()) {
+            return Err(ErrorDetails::validation_error("knn.boost", "混合检索中的 knn 不支持 boost，排序由 rrf 融合决定").into());
+        }
+    }
+    
+    Ok(())
+}
+
+// 解析搜索响应（_search 与 _msearch 的单项响应格式相同）
+fn parse_search_response(response: &Value) -> SearchResult {
+    let total_value = response.get("hits").and_then(|h| h.get("total"));
+    
+    let total = total_value
+        .and_then(|t| {
+            if t.is_object() {
+                t.get("value").and_then(|v| v.as_u64())
+            } else {
+                t.as_u64()
+            }
+        })
+        .unwrap_or(0);
+    
+    // 旧版本 ES 的 total 是数字，总是精确值
+    let total_relation = total_value.map(|t| {
+        t.get("relation")
+            .and_then(|r| r.as_str())
+            .unwrap_or("eq")
+            .to_string()
+    });
+    
+    let hits = response.get("hits")
+        .and_then(|h| h.get("hits"))
+        .and_then(|h| h.as_array())
+        .cloned()
+        .unwrap_or_default();
+    
+    let max_score = response.get("hits")
+        .and_then(|h| h.get("max_score"))
+        .and_then(|s| s.as_f64());
+    
+    let shards = parse_shard_stats(response);
+    
+    let highlights = if hits.iter().any(|hit| hit.get("highlight").is_some()) {
+        hits.iter().map(parse_hit_highlight).collect()
+    } else {
+        Vec::new()
+    };
+    
+    let took = response.get("took").and_then(|t| t.as_u64()).unwrap_or(0);
+    let timed_out = response.get("timed_out").and_then(|t| t.as_bool()).unwrap_or(false);
+    
+    // 获取聚合结果
+    let aggregations = response.get("aggregations").cloned();
+    
+    SearchResult {
+        total,
+        hits,
+        took,
+        timed_out,
+        aggregations,
+        total_relation,
+        max_score,
+        shards,
+        highlights,
+    }
+}
+
+fn parse_shard_stats(response: &Value) -> Option<ShardStats> {
+    response.get("_shards").map(|s| {
+        let count = |key: &str| s.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        ShardStats {
+            total: count("total"),
+            successful: count("successful"),
+            skipped: count("skipped"),
+            failed: count("failed"),
+        }
+    })
+}
+
+fn parse_hit_highlight(hit: &Value) -> HashMap<String, Vec<String>> {
+    let mut fragments = HashMap::new();
+    
+    if let Some(fields) = hit.get("highlight").and_then(|h| h.as_object()) {
+        for (field, values) in fields {
+            let texts = values
+                .as_array()
+                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default();
+            fragments.insert(field.clone(), texts);
+        }
+    }
+    
+    fragments
+}
+
+// 命中文档数超过该值时提示中等风险
+const COST_MEDIUM_DOCS: u64 = 100_000;
+// 命中文档数超过该值时提示高风险
+const COST_HIGH_DOCS: u64 = 1_000_000;
+// 预估数据量超过该值（1GB）时提示高风险
+const COST_HIGH_BYTES: u64 = 1024 * 1024 * 1024;
+
+// _cat 接口的数值以字符串返回，未分配的分片可能为 null
+fn cat_u64(row: &Value, key: &str) -> u64 {
+    match row.get(key) {
+        Some(Value::String(s)) => s.parse().unwrap_or(0),
+        Some(Value::Number(n)) => n.as_u64().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn assess_query_cost(
+    index: &str,
+    matching_documents: u64,
+    total_documents: u64,
+    index_count: u32,
+    primary_store_bytes: u64,
+    shards: Vec<ShardDocCount>,
+) -> QueryCostEstimate {
+    let estimated_bytes = if total_documents == 0 {
+        0
+    } else {
+        (primary_store_bytes as f64 * matching_documents as f64 / total_documents as f64) as u64
+    };
+    
+    let mut warnings = Vec::new();
+    let risk_level = if matching_documents >= COST_HIGH_DOCS || estimated_bytes >= COST_HIGH_BYTES {
+        warnings.push(format!(
+            "查询将涉及约 {} 条文档（约 {:.1} MB），建议缩小查询范围或分批处理",
+            matching_documents,
+            estimated_bytes as f64 / 1024.0 / 1024.0
+        ));
+        "high"
+    } else if matching_documents >= COST_MEDIUM_DOCS {
+        warnings.push(format!("查询将涉及约 {} 条文档，执行可能需要较长时间", matching_documents));
+        "medium"
+    } else {
+        "low"
+    };
+    
+    let unassigned = shards.iter().filter(|s| s.state != "STARTED").count();
+    if unassigned > 0 {
+        warnings.push(format!("有 {} 个分片未处于 STARTED 状态，结果可能不完整", unassigned));
+    }
+    
+    if index_count > 1 {
+        warnings.push(format!("索引模式匹配了 {} 个索引", index_count));
+    }
+    
+    QueryCostEstimate {
+        index: index.to_string(),
+        matching_documents,
+        total_documents,
+        index_count,
+        primary_store_bytes,
+        estimated_bytes,
+        shards,
+        risk_level: risk_level.to_string(),
+        warnings,
+    }
+}
+
+// 构建 SQL 请求体；columnar 只影响 JSON 响应，translate 与文本格式下载不发送
+fn build_sql_body(query: &SqlQuery, include_columnar: bool) -> Value {
+    let mut request_body = serde_json::json!({
+        "query": query.query
+    });
+    
+    if let Some(fetch_size) = query.fetch_size {
+        request_body["fetch_size"] = Value::from(fetch_size);
+    }
+    
+    if let Some(request_timeout) = &query.request_timeout {
+        request_body["request_timeout"] = Value::from(request_timeout.as_str());
+    }
+    
+    if let Some(page_timeout) = &query.page_timeout {
+        request_body["page_timeout"] = Value::from(page_timeout.as_str());
+    }
+    
+    if let Some(params) = &query.params {
+        request_body["params"] = Value::Array(params.clone());
+    }
+    
+    if let Some(filter) = &query.filter {
+        request_body["filter"] = filter.clone();
+    }
+    
+    if let Some(time_zone) = &query.time_zone {
+        request_body["time_zone"] = Value::from(time_zone.as_str());
+    }
+    
+    if let Some(leniency) = query.field_multi_value_leniency {
+        request_body["field_multi_value_leniency"] = Value::from(leniency);
+    }
+    
+    if include_columnar {
+        if let Some(columnar) = query.columnar {
+            request_body["columnar"] = Value::from(columnar);
+        }
+    }
+    
+    request_body
+}
+
+fn parse_sql_response(response: &Value) -> SqlResult {
+    let columns = response.get("columns")
+        .and_then(|c| c.as_array())
+        .map(|cols| {
+            cols.iter()
+                .filter_map(|col| {
+                    let name = col.get("name")?.as_str()?.to_string();
+                    let type_str = col.get("type")?.as_str()?.to_string();
+                    Some(SqlColumn { name, r#type: type_str })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    let parse_arrays = |key: &str| -> Option<Vec<Vec<Value>>> {
+        response.get(key)
+            .and_then(|r| r.as_array())
+            .map(|outer| {
+                outer.iter()
+                    .filter_map(|inner| inner.as_array().cloned())
+                    .collect()
+            })
+    };
+    
+    let rows = parse_arrays("rows").unwrap_or_default();
+    let values = parse_arrays("values");
+    
+    let cursor = response.get("cursor")
+        .and_then(|c| c.as_str())
+        .map(|s| s.to_string());
+    
+    SqlResult {
+        columns,
+        rows,
+        cursor,
+        values,
+    }
+}
+
+fn build_eql_body(query: &EqlQuery) -> Value {
+    let mut body = serde_json::json!({
+        "query": query.query
+    });
+    
+    if let Some(field) = &query.event_category_field {
+        body["event_category_field"] = Value::from(field.as_str());
+    }
+    
+    if let Some(field) = &query.timestamp_field {
+        body["timestamp_field"] = Value::from(field.as_str());
+    }
+    
+    if let Some(field) = &query.tiebreaker_field {
+        body["tiebreaker_field"] = Value::from(field.as_str());
+    }
+    
+    if let Some(size) = query.size {
+        body["size"] = Value::from(size);
+    }
+    
+    if let Some(filter) = &query.filter {
+        body["filter"] = filter.clone();
+    }
+    
+    if let Some(timeout) = &query.wait_for_completion_timeout {
+        body["wait_for_completion_timeout"] = Value::from(timeout.as_str());
+    }
+    
+    if let Some(keep_alive) = &query.keep_alive {
+        body["keep_alive"] = Value::from(keep_alive.as_str());
+    }
+    
+    if let Some(keep_on_completion) = query.keep_on_completion {
+        body["keep_on_completion"] = Value::from(keep_on_completion);
+    }
+    
+    body
+}
+
+fn parse_eql_events(events: Option<&Value>) -> Vec<EqlEvent> {
+    events
+        .and_then(|e| e.as_array())
+        .map(|events| {
+            events.iter()
+                .map(|event| EqlEvent {
+                    index: event.get("_index").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                    id: event.get("_id").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                    source: event.get("_source").cloned().unwrap_or(Value::Null),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_eql_response(response: &Value) -> EqlResult {
+    let hits = response.get("hits");
+    
+    let sequences = hits
+        .and_then(|h| h.get("sequences"))
+        .and_then(|s| s.as_array())
+        .map(|sequences| {
+            sequences.iter()
+                .map(|sequence| EqlSequence {
+                    join_keys: sequence.get("join_keys")
+                        .and_then(|k| k.as_array())
+                        .cloned()
+                        .unwrap_or_default(),
+                    events: parse_eql_events(sequence.get("events")),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    EqlResult {
+        id: response.get("id").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        is_running: response.get("is_running").and_then(|v| v.as_bool()).unwrap_or(false),
+        is_partial: response.get("is_partial").and_then(|v| v.as_bool()).unwrap_or(false),
+        took: response.get("took").and_then(|v| v.as_u64()).unwrap_or(0),
+        timed_out: response.get("timed_out").and_then(|v| v.as_bool()).unwrap_or(false),
+        total: hits
+            .and_then(|h| h.get("total"))
+            .and_then(|t| t.get("value"))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        events: parse_eql_events(hits.and_then(|h| h.get("events"))),
+        sequences,
+    }
+}
+
+fn build_esql_body(query: &EsqlQuery) -> Value {
+    let mut body = serde_json::json!({
+        "query": query.query
+    });
+    
+    if let Some(params) = &query.params {
+        body["params"] = Value::Array(params.clone());
+    }
+    
+    if let Some(locale) = &query.locale {
+        body["locale"] = Value::from(locale.as_str());
+    }
+    
+    if let Some(columnar) = query.columnar {
+        body["columnar"] = Value::from(columnar);
+    }
+    
+    if let Some(filter) = &query.filter {
+        body["filter"] = filter.clone();
+    }
+    
+    if let Some(timeout) = &query.wait_for_completion_timeout {
+        body["wait_for_completion_timeout"] = Value::from(timeout.as_str());
+    }
+    
+    if let Some(keep_alive) = &query.keep_alive {
+        body["keep_alive"] = Value::from(keep_alive.as_str());
+    }
+    
+    if let Some(keep_on_completion) = query.keep_on_completion {
+        body["keep_on_completion"] = Value::from(keep_on_completion);
+    }
+    
+    body
+}
+
+// 不是本客户端提交的异步查询（如应用重启后）只能按响应形状推断布局：
+// 列模式下外层数组与 columns 一一对应，内层长度为行数。行列数相同时无法区分，按行模式解析
+fn esql_values_are_columnar(response: &Value) -> bool {
+    let column_count = response["columns"].as_array().map_or(0, |c| c.len());
+    let Some(values) = response["values"].as_array() else {
+        return false;
+    };
+    
+    values.len() == column_count
+        && values.iter().any(|v| v.as_array().is_some_and(|inner| inner.len() != column_count))
+}
+
+// ES|QL 的 values 在行模式下是行数组，columnar 模式下是列数组
+fn parse_esql_response(response: &Value, columnar: bool) -> EsqlResult {
+    let sql_shape = parse_sql_response(response);
+    let values = sql_shape.values.unwrap_or_default();
+    let (rows, values) = if columnar { (Vec::new(), Some(values)) } else { (values, None) };
+    
+    EsqlResult {
+        columns: sql_shape.columns,
+        rows,
+        values,
+        id: response.get("id").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        is_running: response.get("is_running").and_then(|v| v.as_bool()).unwrap_or(false),
+    }
+}
+
+// 构建 _msearch 的 NDJSON 请求体：每个查询一行 header 加一行 body
+fn build_msearch_body(queries: &[SearchQuery]) -> Result<String> {
+    let mut body = String::new();
+    
+    for query in queries {
+        let header = serde_json::json!({ "index": query.index });
+        body.push_str(&serde_json::to_string(&header)?);
+        body.push('\n');
+        body.push_str(&serde_json::to_string(&build_search_body(query))?);
+        body.push('\n');
+    }
+    
+    Ok(body)
+}
+
+fn parse_msearch_item(item: &Value) -> MultiSearchItem {
+    if item.get("error").is_some() {
+        let status = item.get("status")
+            .and_then(|s| s.as_u64())
+            .unwrap_or(500) as u16;
+        
+        return MultiSearchItem {
+            result: None,
+            error: Some(parse_http_error(status, &item.to_string())),
+        };
+    }
+    
+    MultiSearchItem {
+        result: Some(parse_search_response(item)),
+        error: None,
+    }
+}
+
+// 构建 _render/template 与 _search/template 的请求体
+fn build_search_template_body(query: &SearchTemplateQuery) -> Result<Value> {
+    let mut body = match (&query.id, &query.source) {
+        (Some(id), None) => serde_json::json!({ "id": id }),
+        (None, Some(source)) => serde_json::json!({ "source": source }),
+        _ => return Err(anyhow::anyhow!("Exactly one of template id or source must be provided")),
+    };
+    
+    body["params"] = serde_json::to_value(query.params.clone().unwrap_or_default())?;
+    
+    Ok(body)
+}
+
+// 读取本地模板文件：文件可以直接是模板内容，也可以是 {"source": ..., "params": {...}}，
+// 后者的 params 作为默认值，会被调用方传入的同名参数覆盖
+pub fn load_search_template_file(file_path: &str, params: Option<HashMap<String, Value>>) -> Result<SearchTemplateQuery> {
+    let content = std::fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read template file: {}", file_path))?;
+    
+    let (source, mut merged_params) = match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Object(mut obj)) if obj.contains_key("source") => {
+            let defaults = obj
+                .remove("params")
+                .map(serde_json::from_value::<HashMap<String, Value>>)
+                .transpose()
+                .context("Template file params must be an object")?
+                .unwrap_or_default();
+            (obj.remove("source").unwrap_or(Value::Null), defaults)
+        }
+        Ok(value) => (value, HashMap::new()),
+        // mustache 片段（如 {{#toJson}}）不一定是合法 JSON，按原文发送
+        Err(_) => (Value::String(content), HashMap::new()),
+    };
+    
+    merged_params.extend(params.unwrap_or_default());
+    
+    Ok(SearchTemplateQuery {
+        index: String::new(),
+        id: None,
+        source: Some(source),
+        params: Some(merged_params),
+    })
+}
+
+// 存储脚本的 source 总是字符串；能解析为 JSON 时返回对象，便于前端编辑
+fn parse_template_source(source: &Value) -> Value {
+    match source {
+        Value::String(text) => serde_json::from_str(text).unwrap_or_else(|_| source.clone()),
+        other => other.clone(),
+    }
+}
+
+// 构建聚合查询的辅助函数
+fn build_aggregations(aggregations: &[AggregationConfig]) -> Result<Value> {
+    let mut aggs_object = serde_json::Map::new();
+    
+    for agg in aggregations {
+        let agg_definition = build_single_aggregation(agg)?;
+        aggs_object.insert(agg.name.clone(), agg_definition);
+    }
+    
+    Ok(Value::Object(aggs_object))
+}
+
+fn build_single_aggregation(agg: &AggregationConfig) -> Result<Value> {
+    let mut agg_def = serde_json::Map::new();
+    
+    match agg.r#type.as_str() {
         "terms" => {
             let mut terms_agg = serde_json::json!({
-                "field": agg.field
+                "field": agg_field(agg)?
             });
             
             // 添加可选参数
@@ -962,7 +2301,7 @@ fn build_single_aggregation(agg: &AggregationConfig) -> Result<Value> {
         
         "date_histogram" => {
             let mut date_hist_agg = serde_json::json!({
-                "field": agg.field
+                "field": agg_field(agg)?
             });
             
             if let Some(params) = &agg.params {
@@ -990,95 +2329,540 @@ fn build_single_aggregation(agg: &AggregationConfig) -> Result<Value> {
         
         "histogram" => {
             let mut hist_agg = serde_json::json!({
-                "field": agg.field
+                "field": agg_field(agg)?
+            });
+            
+            if let Some(params) = &agg.params {
+                if let Some(interval) = params.get("interval") {
+                    hist_agg["interval"] = interval.clone();
+                } else {
+                    hist_agg["interval"] = Value::Number(serde_json::Number::from(1));
+                }
+                
+                if let Some(min_doc_count) = params.get("min_doc_count") {
+                    hist_agg["min_doc_count"] = min_doc_count.clone();
+                }
+            } else {
+                hist_agg["interval"] = Value::Number(serde_json::Number::from(1));
+            }
+            
+            agg_def.insert("histogram".to_string(), hist_agg);
+        },
+        
+        "range" => {
+            let mut range_agg = serde_json::json!({
+                "field": agg_field(agg)?
+            });
+            
+            if let Some(params) = &agg.params {
+                if let Some(ranges) = params.get("ranges") {
+                    range_agg["ranges"] = ranges.clone();
+                }
+            }
+            
+            agg_def.insert("range".to_string(), range_agg);
+        },
+        
+        // 度量聚合
+        "avg" => {
+            agg_def.insert("avg".to_string(), serde_json::json!({
+                "field": agg_field(agg)?
+            }));
+        },
+        
+        "sum" => {
+            agg_def.insert("sum".to_string(), serde_json::json!({
+                "field": agg_field(agg)?
+            }));
+        },
+        
+        "max" => {
+            agg_def.insert("max".to_string(), serde_json::json!({
+                "field": agg_field(agg)?
+            }));
+        },
+        
+        "min" => {
+            agg_def.insert("min".to_string(), serde_json::json!({
+                "field": agg_field(agg)?
+            }));
+        },
+        
+        "count" => {
+            agg_def.insert("value_count".to_string(), serde_json::json!({
+                "field": agg_field(agg)?
+            }));
+        },
+        
+        "cardinality" => {
+            agg_def.insert("cardinality".to_string(), serde_json::json!({
+                "field": agg_field(agg)?
+            }));
+        },
+        
+        // 地理聚合
+        "geohash_grid" | "geotile_grid" => {
+            let mut grid_agg = serde_json::json!({
+                "field": agg_field(agg)?
             });
             
-            if let Some(params) = &agg.params {
-                if let Some(interval) = params.get("interval") {
-                    hist_agg["interval"] = interval.clone();
-                } else {
-                    hist_agg["interval"] = Value::Number(serde_json::Number::from(1));
+            if let Some(params) = &agg.params {
+                if let Some(precision) = params.get("precision") {
+                    grid_agg["precision"] = precision.clone();
+                }
+                if let Some(size) = params.get("size") {
+                    grid_agg["size"] = size.clone();
+                }
+                if let Some(bounds) = params.get("bounds") {
+                    grid_agg["bounds"] = bounds.clone();
+                }
+            }
+            
+            agg_def.insert(agg.r#type.clone(), grid_agg);
+        },
+        
+        "geo_bounds" => {
+            let mut bounds_agg = serde_json::json!({
+                "field": agg_field(agg)?
+            });
+            
+            if let Some(wrap_longitude) = agg.params.as_ref().and_then(|p| p.get("wrap_longitude")) {
+                bounds_agg["wrap_longitude"] = wrap_longitude.clone();
+            }
+            
+            agg_def.insert("geo_bounds".to_string(), bounds_agg);
+        },
+        
+        "geo_centroid" => {
+            agg_def.insert("geo_centroid".to_string(), serde_json::json!({
+                "field": agg_field(agg)?
+            }));
+        },
+        
+        // 基于字段的聚合，params 原样合并进聚合体
+        "percentiles" | "stats" | "extended_stats" | "date_range" | "significant_terms"
+        | "rare_terms" | "missing" | "auto_date_histogram" => {
+            let field_agg = merge_agg_params(serde_json::json!({ "field": agg_field(agg)? }), agg.params.as_ref());
+            agg_def.insert(agg.r#type.clone(), field_agg);
+        },
+        
+        "percentile_ranks" => {
+            required_agg_param(agg, "values")?;
+            let ranks_agg = merge_agg_params(serde_json::json!({ "field": agg_field(agg)? }), agg.params.as_ref());
+            agg_def.insert("percentile_ranks".to_string(), ranks_agg);
+        },
+        
+        "multi_terms" => {
+            required_agg_param(agg, "terms")?;
+            agg_def.insert("multi_terms".to_string(), merge_agg_params(serde_json::json!({}), agg.params.as_ref()));
+        },
+        
+        // 不依赖字段的聚合
+        "top_hits" | "sampler" | "reverse_nested" => {
+            agg_def.insert(agg.r#type.clone(), merge_agg_params(serde_json::json!({}), agg.params.as_ref()));
+        },
+        
+        "filters" => {
+            required_agg_param(agg, "filters")?;
+            agg_def.insert("filters".to_string(), merge_agg_params(serde_json::json!({}), agg.params.as_ref()));
+        },
+        
+        "filter" => {
+            // filter 聚合体本身就是一个查询
+            let filter = required_agg_param(agg, "filter")?;
+            agg_def.insert("filter".to_string(), filter.clone());
+        },
+        
+        "nested" => {
+            // path 优先取 params.path，否则使用 field
+            let path = agg.params.as_ref()
+                .and_then(|p| p.get("path"))
+                .and_then(|p| p.as_str())
+                .or(agg.field.as_deref())
+                .unwrap_or_default();
+            if path.is_empty() {
+                return Err(anyhow::anyhow!("Aggregation '{}' (nested) requires a path", agg.name));
+            }
+            agg_def.insert("nested".to_string(), serde_json::json!({ "path": path }));
+        },
+        
+        // 管道聚合
+        "derivative" | "cumulative_sum" | "moving_fn" | "bucket_script" | "bucket_selector"
+        | "avg_bucket" | "max_bucket" => {
+            let buckets_path = agg.buckets_path.as_ref()
+                .ok_or_else(|| ErrorDetails::validation_error(
+                    "buckets_path",
+                    &format!("管道聚合 '{}' ({}) 缺少 buckets_path", agg.name, agg.r#type),
+                ))?;
+            if matches!(agg.r#type.as_str(), "moving_fn" | "bucket_script" | "bucket_selector") {
+                required_agg_param(agg, "script")?;
+            }
+            if agg.r#type == "moving_fn" {
+                required_agg_param(agg, "window")?;
+            }
+            
+            let pipeline_agg = merge_agg_params(serde_json::json!({ "buckets_path": buckets_path }), agg.params.as_ref());
+            agg_def.insert(agg.r#type.clone(), pipeline_agg);
+        },
+        
+        "bucket_sort" => {
+            agg_def.insert("bucket_sort".to_string(), merge_agg_params(serde_json::json!({}), agg.params.as_ref()));
+        },
+        
+        _ => {
+            return Err(anyhow::anyhow!("Unsupported aggregation type: {}", agg.r#type));
+        }
+    }
+    
+    // 添加子聚合
+    if let Some(sub_aggs) = &agg.sub_aggregations {
+        if !sub_aggs.is_empty() {
+            let sub_aggs_value = build_aggregations(sub_aggs)?;
+            agg_def.insert("aggs".to_string(), sub_aggs_value);
+        }
+    }
+    
+    Ok(Value::Object(agg_def))
+}
+
+fn agg_field(agg: &AggregationConfig) -> Result<&str> {
+    agg.field.as_deref()
+        .filter(|f| !f.is_empty())
+        .ok_or_else(|| ErrorDetails::validation_error(
+            "field",
+            &format!("聚合 '{}' ({}) 需要指定字段", agg.name, agg.r#type),
+        ).into())
+}
+
+// 引用父聚合桶的管道聚合，必须嵌套在多桶聚合之下
+const PARENT_PIPELINE_AGGREGATIONS: &[&str] = &[
+    "derivative", "cumulative_sum", "moving_fn", "bucket_script", "bucket_selector", "bucket_sort",
+];
+
+// 引用同级多桶聚合的管道聚合
+const SIBLING_PIPELINE_AGGREGATIONS: &[&str] = &["avg_bucket", "max_bucket"];
+
+pub(crate) const MULTI_BUCKET_AGGREGATIONS: &[&str] = &[
+    "terms", "date_histogram", "histogram", "range", "date_range", "filters", "significant_terms",
+    "rare_terms", "multi_terms", "auto_date_histogram", "geohash_grid", "geotile_grid", "composite",
+];
+
+// 按桶序的管道聚合只能放在 histogram 类聚合下
+const HISTOGRAM_AGGREGATIONS: &[&str] = &["date_histogram", "histogram", "auto_date_histogram"];
+
+fn is_pipeline_aggregation(agg_type: &str) -> bool {
+    PARENT_PIPELINE_AGGREGATIONS.contains(&agg_type) || SIBLING_PIPELINE_AGGREGATIONS.contains(&agg_type)
+}
+
+// 校验配置树中所有管道聚合的 buckets_path 能解析到已存在的同级聚合，parent_type 为外层聚合的类型
+fn validate_pipeline_aggregations(aggs: &[AggregationConfig], parent_type: Option<&str>) -> Result<()> {
+    for agg in aggs {
+        let agg_type = agg.r#type.as_str();
+        
+        if is_pipeline_aggregation(agg_type) {
+            let pipeline_error = |message: String| -> anyhow::Error {
+                ErrorDetails::validation_error("buckets_path", &format!("管道聚合 '{}' ({}): {}", agg.name, agg_type, message)).into()
+            };
+            
+            if agg.sub_aggregations.as_ref().is_some_and(|s| !s.is_empty()) {
+                return Err(pipeline_error("管道聚合不能包含子聚合".to_string()));
+            }
+            
+            if PARENT_PIPELINE_AGGREGATIONS.contains(&agg_type) {
+                let parent_type = parent_type.unwrap_or_default();
+                let allowed = if matches!(agg_type, "derivative" | "cumulative_sum" | "moving_fn") {
+                    HISTOGRAM_AGGREGATIONS
+                } else {
+                    MULTI_BUCKET_AGGREGATIONS
+                };
+                if !allowed.contains(&parent_type) {
+                    return Err(pipeline_error(format!("必须作为 {} 聚合的子聚合", allowed.join("/"))));
+                }
+            }
+            
+            let paths: Vec<&str> = match &agg.buckets_path {
+                None if agg_type == "bucket_sort" => Vec::new(),
+                None => return Err(pipeline_error("缺少 buckets_path".to_string())),
+                Some(Value::String(path)) => vec![path.as_str()],
+                Some(Value::Object(map)) => {
+                    let mut paths = Vec::new();
+                    for (name, path) in map {
+                        paths.push(path.as_str().ok_or_else(|| pipeline_error(format!("变量 '{}' 的路径必须是字符串", name)))?);
+                    }
+                    paths
+                }
+                Some(_) => return Err(pipeline_error("buckets_path 必须是字符串或对象".to_string())),
+            };
+            
+            for path in paths {
+                let target = resolve_buckets_path(path, aggs).map_err(|e| pipeline_error(format!("buckets_path '{}' 无效，{}", path, e)))?;
+                
+                if SIBLING_PIPELINE_AGGREGATIONS.contains(&agg_type)
+                    && !target.is_some_and(|t| MULTI_BUCKET_AGGREGATIONS.contains(&t.r#type.as_str()))
+                {
+                    return Err(pipeline_error(format!("buckets_path '{}' 必须以同级的多桶聚合开头", path)));
+                }
+            }
+        }
+        
+        if let Some(sub_aggs) = &agg.sub_aggregations {
+            validate_pipeline_aggregations(sub_aggs, Some(agg_type))?;
+        }
+    }
+    
+    Ok(())
+}
+
+// 解析 "agg1>agg2.metric" 形式的路径，返回路径第一段引用的聚合（_count 等特殊路径返回 None）
+fn resolve_buckets_path<'a>(path: &str, siblings: &'a [AggregationConfig]) -> std::result::Result<Option<&'a AggregationConfig>, String> {
+    let segments: Vec<&str> = path.split('>').collect();
+    let mut level = siblings;
+    let mut first = None;
+    
+    for (i, segment) in segments.iter().enumerate() {
+        let name = segment.split(['.', '[']).next().unwrap_or_default();
+        let is_last = i == segments.len() - 1;
+        
+        if matches!(name, "_count" | "_key" | "_bucket_count") {
+            if !is_last {
+                return Err(format!("'{}' 只能出现在路径末尾", name));
+            }
+            return Ok(first);
+        }
+        
+        let target = level.iter().find(|a| a.name == name).ok_or_else(|| {
+            let available: Vec<&str> = level.iter().map(|a| a.name.as_str()).collect();
+            format!("找不到聚合 '{}'，可用的聚合: [{}]", name, available.join(", "))
+        })?;
+        
+        if i == 0 {
+            first = Some(target);
+        }
+        
+        if !is_last {
+            level = target.sub_aggregations.as_deref().unwrap_or_default();
+        }
+    }
+    
+    Ok(first)
+}
+
+fn merge_agg_params(mut body: Value, params: Option<&Value>) -> Value {
+    if let (Some(Value::Object(params)), Value::Object(body_map)) = (params, &mut body) {
+        for (key, value) in params {
+            body_map.insert(key.clone(), value.clone());
+        }
+    }
+    body
+}
+
+fn required_agg_param<'a>(agg: &'a AggregationConfig, key: &str) -> Result<&'a Value> {
+    agg.params.as_ref()
+        .and_then(|p| p.get(key))
+        .ok_or_else(|| anyhow::anyhow!("Aggregation '{}' ({}) requires parameter '{}'", agg.name, agg.r#type, key))
+}
+
+// 递归提取字段类型的辅助函数，object/nested 字段本身不记录
+fn extract_field_types(properties: &Value, prefix: &str, field_types: &mut HashMap<String, String>) {
+    if let Value::Object(fields) = properties {
+        for (field_name, field_def) in fields {
+            let full_name = if prefix.is_empty() {
+                field_name.clone()
+            } else {
+                format!("{}.{}", prefix, field_name)
+            };
+            
+            if let Some(field_type) = field_def.get("type").and_then(|t| t.as_str()) {
+                if field_type != "object" && field_type != "nested" {
+                    field_types.entry(full_name.clone()).or_insert_with(|| field_type.to_string());
                 }
-                
-                if let Some(min_doc_count) = params.get("min_doc_count") {
-                    hist_agg["min_doc_count"] = min_doc_count.clone();
-                }
-            } else {
-                hist_agg["interval"] = Value::Number(serde_json::Number::from(1));
             }
             
-            agg_def.insert("histogram".to_string(), hist_agg);
-        },
-        
-        "range" => {
-            let mut range_agg = serde_json::json!({
-                "field": agg.field
-            });
+            if let Some(nested_properties) = field_def.get("properties") {
+                extract_field_types(nested_properties, &full_name, field_types);
+            }
             
-            if let Some(params) = &agg.params {
-                if let Some(ranges) = params.get("ranges") {
-                    range_agg["ranges"] = ranges.clone();
-                }
+            if let Some(sub_fields) = field_def.get("fields") {
+                extract_field_types(sub_fields, &full_name, field_types);
             }
+        }
+    }
+}
+
+fn field_info(name: String, field_def: &Value) -> FieldInfo {
+    // 只有 properties 没有 type 的是 object 字段
+    let field_type = field_def.get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or(if field_def.get("properties").is_some() { "object" } else { "unknown" });
+    
+    let (dims, similarity) = if field_type == "dense_vector" {
+        (
+            field_def.get("dims").and_then(|d| d.as_u64()).map(|d| d as u32),
+            // 8.x 中已索引的向量默认使用 cosine
+            Some(field_def.get("similarity").and_then(|s| s.as_str()).unwrap_or("cosine").to_string()),
+        )
+    } else {
+        (None, None)
+    };
+    
+    FieldInfo {
+        name,
+        field_type: field_type.to_string(),
+        dims,
+        similarity,
+    }
+}
+
+fn extract_field_infos(properties: &Value, prefix: &str, fields: &mut Vec<FieldInfo>) {
+    if let Value::Object(props) = properties {
+        for (field_name, field_def) in props {
+            let full_name = if prefix.is_empty() {
+                field_name.clone()
+            } else {
+                format!("{}.{}", prefix, field_name)
+            };
             
-            agg_def.insert("range".to_string(), range_agg);
-        },
-        
-        // 度量聚合
-        "avg" => {
-            agg_def.insert("avg".to_string(), serde_json::json!({
-                "field": agg.field
-            }));
-        },
-        
-        "sum" => {
-            agg_def.insert("sum".to_string(), serde_json::json!({
-                "field": agg.field
-            }));
-        },
-        
-        "max" => {
-            agg_def.insert("max".to_string(), serde_json::json!({
-                "field": agg.field
-            }));
-        },
-        
-        "min" => {
-            agg_def.insert("min".to_string(), serde_json::json!({
-                "field": agg.field
-            }));
-        },
-        
-        "count" => {
-            agg_def.insert("value_count".to_string(), serde_json::json!({
-                "field": agg.field
-            }));
-        },
-        
-        "cardinality" => {
-            agg_def.insert("cardinality".to_string(), serde_json::json!({
-                "field": agg.field
-            }));
-        },
-        
-        _ => {
-            return Err(anyhow::anyhow!("Unsupported aggregation type: {}", agg.r#type));
+            fields.push(field_info(full_name.clone(), field_def));
+            
+            if let Some(nested_properties) = field_def.get("properties") {
+                extract_field_infos(nested_properties, &full_name, fields);
+            }
+            
+            if let Some(sub_fields) = field_def.get("fields") {
+                extract_field_infos(sub_fields, &full_name, fields);
+            }
+        }
+    }
+}
+
+pub fn build_geo_query(query: &GeoQuery) -> Value {
+    match query {
+        GeoQuery::Distance { field, center, distance, distance_type } => {
+            let mut clause = serde_json::json!({
+                "distance": distance,
+                field.as_str(): { "lat": center.lat, "lon": center.lon }
+            });
+            if let Some(distance_type) = distance_type {
+                clause["distance_type"] = Value::from(distance_type.as_str());
+            }
+            serde_json::json!({ "geo_distance": clause })
         }
+        GeoQuery::BoundingBox { field, bounds } => serde_json::json!({
+            "geo_bounding_box": {
+                field.as_str(): {
+                    "top_left": { "lat": bounds.top_left.lat, "lon": bounds.top_left.lon },
+                    "bottom_right": { "lat": bounds.bottom_right.lat, "lon": bounds.bottom_right.lon }
+                }
+            }
+        }),
+        GeoQuery::Shape { field, shape, relation } => serde_json::json!({
+            "geo_shape": {
+                field.as_str(): {
+                    "shape": shape,
+                    "relation": relation.as_deref().unwrap_or("intersects")
+                }
+            }
+        }),
+    }
+}
+
+fn parse_geo_cells(grid: &Value, grid_type: &str) -> Vec<GeoCell> {
+    grid["buckets"]
+        .as_array()
+        .map(|buckets| {
+            buckets.iter().filter_map(|bucket| {
+                let key = bucket["key"].as_str()?;
+                let bounds = if grid_type == "geotile_grid" {
+                    decode_geotile(key)?
+                } else {
+                    decode_geohash(key)?
+                };
+                
+                Some(GeoCell {
+                    key: key.to_string(),
+                    doc_count: bucket["doc_count"].as_u64().unwrap_or(0),
+                    center: GeoPoint {
+                        lat: (bounds.top_left.lat + bounds.bottom_right.lat) / 2.0,
+                        lon: (bounds.top_left.lon + bounds.bottom_right.lon) / 2.0,
+                    },
+                    bounds,
+                })
+            }).collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_geo_point(value: &Value) -> Option<GeoPoint> {
+    Some(GeoPoint {
+        lat: value.get("lat")?.as_f64()?,
+        lon: value.get("lon")?.as_f64()?,
+    })
+}
+
+fn parse_geo_bounds(value: &Value) -> Option<GeoBoundingBox> {
+    let bounds = value.get("bounds")?;
+    Some(GeoBoundingBox {
+        top_left: parse_geo_point(&bounds["top_left"])?,
+        bottom_right: parse_geo_point(&bounds["bottom_right"])?,
+    })
+}
+
+// geohash 交替编码经度/纬度位，从经度开始
+fn decode_geohash(hash: &str) -> Option<GeoBoundingBox> {
+    const BASE32: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+    
+    let (mut lat_min, mut lat_max) = (-90.0_f64, 90.0_f64);
+    let (mut lon_min, mut lon_max) = (-180.0_f64, 180.0_f64);
+    let mut is_lon = true;
+    
+    if hash.is_empty() {
+        return None;
     }
     
-    // 添加子聚合
-    if let Some(sub_aggs) = &agg.sub_aggregations {
-        if !sub_aggs.is_empty() {
-            let sub_aggs_value = build_aggregations(sub_aggs)?;
-            agg_def.insert("aggs".to_string(), sub_aggs_value);
+    for c in hash.bytes() {
+        let value = BASE32.iter().position(|&b| b == c.to_ascii_lowercase())?;
+        for bit in (0..5).rev() {
+            let on = (value >> bit) & 1 == 1;
+            if is_lon {
+                let mid = (lon_min + lon_max) / 2.0;
+                if on { lon_min = mid } else { lon_max = mid }
+            } else {
+                let mid = (lat_min + lat_max) / 2.0;
+                if on { lat_min = mid } else { lat_max = mid }
+            }
+            is_lon = !is_lon;
         }
     }
     
-    Ok(Value::Object(agg_def))
+    Some(GeoBoundingBox {
+        top_left: GeoPoint { lat: lat_max, lon: lon_min },
+        bottom_right: GeoPoint { lat: lat_min, lon: lon_max },
+    })
+}
+
+// geotile key 格式为 "zoom/x/y"（Web Mercator 瓦片）
+fn decode_geotile(key: &str) -> Option<GeoBoundingBox> {
+    let mut parts = key.split('/').map(|p| p.parse::<u32>().ok());
+    let zoom = parts.next()??;
+    let x = parts.next()?? as f64;
+    let y = parts.next()?? as f64;
+    
+    if zoom > 29 {
+        return None;
+    }
+    
+    let tiles = f64::from(1u32 << zoom);
+    let tile_lon = |x: f64| x / tiles * 360.0 - 180.0;
+    let tile_lat = |y: f64| (std::f64::consts::PI * (1.0 - 2.0 * y / tiles)).sinh().atan().to_degrees();
+    
+    Some(GeoBoundingBox {
+        top_left: GeoPoint { lat: tile_lat(y), lon: tile_lon(x) },
+        bottom_right: GeoPoint { lat: tile_lat(y + 1.0), lon: tile_lon(x + 1.0) },
+    })
 }
 
 // 递归提取字段名的辅助函数
-fn extract_field_names(properties: &Value, prefix: &str, field_names: &mut Vec<String>) {
+fn extract_field_names(properties: &Value, prefix: &str, field_names: &mut Vec<FieldInfo>) {
     if let Value::Object(fields) = properties {
         for (field_name, field_def) in fields {
             let full_name = if prefix.is_empty() {
@@ -1087,14 +2871,14 @@ fn extract_field_names(properties: &Value, prefix: &str, field_names: &mut Vec<S
                 format!("{}.{}", prefix, field_name)
             };
             
-            field_names.push(full_name.clone());
+            field_names.push(field_info(full_name.clone(), field_def));
             
             // 处理keyword类型的子字段
             if let Value::Object(field_obj) = field_def {
                 if let Some(field_type) = field_obj.get("type") {
                     if field_type == "text" {
                         // text字段通常有keyword子字段
-                        field_names.push(format!("{}.keyword", full_name));
+                        field_names.push(field_info(format!("{}.keyword", full_name), &serde_json::json!({ "type": "keyword" })));
                     }
                 }
                 
@@ -1110,4 +2894,639 @@ fn extract_field_names(properties: &Value, prefix: &str, field_names: &mut Vec<S
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(index: &str) -> SearchQuery {
+        SearchQuery {
+            index: index.to_string(),
+            query: serde_json::json!({ "match_all": {} }),
+            size: Some(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_build_search_body_with_extended_options() {
+        let mut search_query = query("logs");
+        search_query.source = Some(serde_json::json!({ "includes": ["message"] }));
+        search_query.track_total_hits = Some(Value::Bool(true));
+        search_query.timeout = Some("5s".to_string());
+        search_query.stored_fields = Some(vec!["_none_".to_string()]);
+        search_query.extra.insert("min_score".to_string(), serde_json::json!(0.5));
+        search_query.extra.insert("size".to_string(), serde_json::json!(100));
+
+        let body = build_search_body(&search_query);
+        assert_eq!(body["_source"]["includes"][0], "message");
+        assert_eq!(body["track_total_hits"], true);
+        assert_eq!(body["timeout"], "5s");
+        assert_eq!(body["stored_fields"][0], "_none_");
+        assert_eq!(body["min_score"], 0.5);
+        // 显式字段优先于透传参数
+        assert_eq!(body["size"], 5);
+        assert!(body.get("highlight").is_none());
+    }
+
+    #[test]
+    fn test_parse_search_response_metadata_and_highlights() {
+        let response = serde_json::json!({
+            "took": 7,
+            "timed_out": false,
+            "_shards": { "total": 3, "successful": 2, "skipped": 0, "failed": 1 },
+            "hits": {
+                "total": { "value": 10000, "relation": "gte" },
+                "max_score": 1.5,
+                "hits": [
+                    { "_id": "1", "highlight": { "message": ["<em>error</em> at boot"] } },
+                    { "_id": "2" }
+                ]
+            }
+        });
+
+        let result = parse_search_response(&response);
+        assert_eq!(result.total, 10000);
+        assert_eq!(result.total_relation.as_deref(), Some("gte"));
+        assert_eq!(result.max_score, Some(1.5));
+        assert_eq!(result.shards.unwrap().failed, 1);
+        assert_eq!(result.highlights.len(), 2);
+        assert_eq!(result.highlights[0]["message"][0], "<em>error</em> at boot");
+        assert!(result.highlights[1].is_empty());
+    }
+
+    #[test]
+    fn test_build_msearch_body() {
+        let body = build_msearch_body(&[query("logs-a"), query("logs-b")]).unwrap();
+        let lines: Vec<&str> = body.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(body.ends_with('\n'));
+        assert_eq!(serde_json::from_str::<Value>(lines[0]).unwrap()["index"], "logs-a");
+        assert_eq!(serde_json::from_str::<Value>(lines[1]).unwrap()["size"], 5);
+        assert_eq!(serde_json::from_str::<Value>(lines[2]).unwrap()["index"], "logs-b");
+    }
+
+    #[test]
+    fn test_build_search_template_body() {
+        let mut params = HashMap::new();
+        params.insert("status".to_string(), serde_json::json!(500));
+        let template_query = SearchTemplateQuery {
+            index: "logs".to_string(),
+            id: Some("errors-by-status".to_string()),
+            source: None,
+            params: Some(params),
+        };
+
+        let body = build_search_template_body(&template_query).unwrap();
+        assert_eq!(body["id"], "errors-by-status");
+        assert_eq!(body["params"]["status"], 500);
+
+        let ambiguous = SearchTemplateQuery {
+            source: Some(serde_json::json!({ "query": { "match_all": {} } })),
+            ..template_query
+        };
+        assert!(build_search_template_body(&ambiguous).is_err());
+    }
+
+    #[test]
+    fn test_assess_query_cost() {
+        let shards = vec![ShardDocCount {
+            index: "logs".to_string(),
+            shard: 0,
+            primary: true,
+            state: "UNASSIGNED".to_string(),
+            docs: 0,
+            store_bytes: 0,
+        }];
+
+        let estimate = assess_query_cost("logs", 2_000_000, 4_000_000, 1, 8_000, shards);
+        assert_eq!(estimate.risk_level, "high");
+        assert_eq!(estimate.estimated_bytes, 4_000);
+        assert_eq!(estimate.warnings.len(), 2);
+
+        let small = assess_query_cost("logs", 10, 0, 1, 0, Vec::new());
+        assert_eq!(small.risk_level, "low");
+        assert_eq!(small.estimated_bytes, 0);
+        assert!(small.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_build_sql_body() {
+        let sql_query = SqlQuery {
+            query: "SELECT * FROM logs WHERE status = ?".to_string(),
+            params: Some(vec![serde_json::json!(500)]),
+            filter: Some(serde_json::json!({ "term": { "host": "web01" } })),
+            time_zone: Some("Asia/Shanghai".to_string()),
+            columnar: Some(true),
+            ..Default::default()
+        };
+
+        let body = build_sql_body(&sql_query, true);
+        assert_eq!(body["params"][0], 500);
+        assert_eq!(body["filter"]["term"]["host"], "web01");
+        assert_eq!(body["time_zone"], "Asia/Shanghai");
+        assert_eq!(body["columnar"], true);
+        assert!(body.get("fetch_size").is_none());
+
+        assert!(build_sql_body(&sql_query, false).get("columnar").is_none());
+    }
+
+    #[test]
+    fn test_parse_sql_response_columnar() {
+        let response = serde_json::json!({
+            "columns": [{ "name": "host", "type": "keyword" }, { "name": "n", "type": "long" }],
+            "values": [["web01", "web02"], [3, 4]]
+        });
+
+        let result = parse_sql_response(&response);
+        assert_eq!(result.columns.len(), 2);
+        assert!(result.rows.is_empty());
+        assert_eq!(result.values.unwrap()[1][0], 3);
+        assert!(result.cursor.is_none());
+    }
+
+    #[test]
+    fn test_parse_eql_sequences() {
+        let response = serde_json::json!({
+            "id": "FmNJRUZ1YWZCU3dHY1BIOUhaenVSRkEaaXFlZ3h4c1RTWFNocDdnY2FSaERnUTozNDE=",
+            "is_partial": false,
+            "is_running": false,
+            "took": 6,
+            "timed_out": false,
+            "hits": {
+                "total": { "value": 1, "relation": "eq" },
+                "sequences": [{
+                    "join_keys": ["2230"],
+                    "events": [
+                        { "_index": "logs", "_id": "a", "_source": { "process": { "pid": 2230 } } },
+                        { "_index": "logs", "_id": "b", "_source": { "process": { "pid": 2230 } } }
+                    ]
+                }]
+            }
+        });
+
+        let result = parse_eql_response(&response);
+        assert!(result.id.is_some());
+        assert_eq!(result.total, 1);
+        assert!(result.events.is_empty());
+        assert_eq!(result.sequences[0].join_keys[0], "2230");
+        assert_eq!(result.sequences[0].events[1].id, "b");
+        assert_eq!(result.sequences[0].events[0].source["process"]["pid"], 2230);
+    }
+
+    #[test]
+    fn test_parse_esql_response_row_and_columnar() {
+        let response = serde_json::json!({
+            "columns": [{ "name": "host", "type": "keyword" }, { "name": "n", "type": "long" }],
+            "values": [["web01", 3], ["web02", 4]]
+        });
+
+        let rows = parse_esql_response(&response, false);
+        assert_eq!(rows.rows.len(), 2);
+        assert_eq!(rows.rows[1][0], "web02");
+        assert!(rows.values.is_none());
+        assert!(!rows.is_running);
+
+        let columnar = parse_esql_response(&response, true);
+        assert!(columnar.rows.is_empty());
+        assert_eq!(columnar.values.unwrap().len(), 2);
+
+        // 未记录布局时按形状推断：3 行 2 列
+        let row_shaped = serde_json::json!({
+            "columns": [{ "name": "host", "type": "keyword" }, { "name": "n", "type": "long" }],
+            "values": [["web01", 3], ["web02", 4], ["web03", 5]]
+        });
+        let column_shaped = serde_json::json!({
+            "columns": [{ "name": "host", "type": "keyword" }, { "name": "n", "type": "long" }],
+            "values": [["web01", "web02", "web03"], [3, 4, 5]]
+        });
+        assert!(!esql_values_are_columnar(&row_shaped));
+        assert!(esql_values_are_columnar(&column_shaped));
+        assert!(!esql_values_are_columnar(&response));
+        assert!(!esql_values_are_columnar(&serde_json::json!({ "columns": [], "values": [] })));
+    }
+
+    fn knn(field: &str) -> KnnQuery {
+        KnnQuery {
+            field: field.to_string(),
+            query_vector: vec![0.1, 0.2, 0.3],
+            k: 10,
+            num_candidates: 100,
+            filter: None,
+            similarity: None,
+            boost: None,
+        }
+    }
+
+    #[test]
+    fn test_build_search_body_knn_and_rrf() {
+        let mut knn_query = query("products");
+        knn_query.query = Value::Null;
+        knn_query.knn = Some(vec![knn("embedding")]);
+
+        let body = build_search_body(&knn_query);
+        assert!(body.get("query").is_none());
+        assert_eq!(body["knn"][0]["field"], "embedding");
+        assert_eq!(body["knn"][0]["num_candidates"], 100);
+
+        let mut hybrid = query("products");
+        hybrid.knn = Some(vec![knn("embedding")]);
+        hybrid.rrf = Some(RrfConfig { rank_constant: Some(60), rank_window_size: None });
+
+        let body = build_search_body(&hybrid);
+        assert!(body.get("query").is_none());
+        assert!(body.get("knn").is_none());
+        let retrievers = &body["retriever"]["rrf"]["retrievers"];
+        assert_eq!(retrievers[0]["standard"]["query"], serde_json::json!({ "match_all": {} }));
+        assert_eq!(retrievers[1]["knn"]["k"], 10);
+        assert_eq!(body["retriever"]["rrf"]["rank_constant"], 60);
+    }
+
+    #[test]
+    fn test_validate_knn_query() {
+        let mut invalid = query("products");
+        let mut clause = knn("embedding");
+        clause.num_candidates = 5;
+        invalid.knn = Some(vec![clause]);
+        assert!(validate_search_query(&invalid).is_err());
+
+        let mut rrf_only = query("products");
+        rrf_only.rrf = Some(RrfConfig::default());
+        assert!(validate_search_query(&rrf_only).is_err());
+
+        // 没有 query 时单个 knn 无法融合
+        let mut single_knn = query("products");
+        single_knn.query = Value::Null;
+        single_knn.knn = Some(vec![knn("embedding")]);
+        single_knn.rrf = Some(RrfConfig::default());
+        assert!(validate_search_query(&single_knn).is_err());
+        single_knn.knn = Some(vec![knn("embedding"), knn("title_vector")]);
+        assert!(validate_search_query(&single_knn).is_ok());
+
+        let mut boosted = query("products");
+        let mut clause = knn("embedding");
+        clause.boost = Some(2.0);
+        boosted.knn = Some(vec![clause]);
+        assert!(validate_search_query(&boosted).is_ok());
+        assert_eq!(build_search_body(&boosted)["knn"][0]["boost"], 2.0);
+        boosted.rrf = Some(RrfConfig::default());
+        assert!(validate_search_query(&boosted).is_err());
+        assert!(build_search_body(&boosted)["retriever"]["rrf"]["retrievers"][1]["knn"].get("boost").is_none());
+    }
+
+    #[test]
+    fn test_extract_field_infos_flags_vectors() {
+        let properties = serde_json::json!({
+            "title": { "type": "text", "fields": { "keyword": { "type": "keyword" } } },
+            "embedding": { "type": "dense_vector", "dims": 384, "similarity": "dot_product" },
+            "user": { "properties": { "name": { "type": "keyword" } } }
+        });
+
+        let mut fields = Vec::new();
+        extract_field_infos(&properties, "", &mut fields);
+
+        let embedding = fields.iter().find(|f| f.name == "embedding").unwrap();
+        assert_eq!(embedding.dims, Some(384));
+        assert_eq!(embedding.similarity.as_deref(), Some("dot_product"));
+        assert!(fields.iter().any(|f| f.name == "title.keyword" && f.field_type == "keyword"));
+        assert!(fields.iter().any(|f| f.name == "user" && f.field_type == "object"));
+        assert!(fields.iter().find(|f| f.name == "title").unwrap().dims.is_none());
+
+        // 自动补全用的字段列表同样标出向量字段
+        let mut names = Vec::new();
+        extract_field_names(&properties, "", &mut names);
+        let embedding = names.iter().find(|f| f.name == "embedding").unwrap();
+        assert_eq!(embedding.field_type, "dense_vector");
+        assert_eq!(embedding.dims, Some(384));
+        assert_eq!(embedding.similarity.as_deref(), Some("dot_product"));
+        assert!(names.iter().any(|f| f.name == "user.name" && f.dims.is_none()));
+    }
+
+    #[test]
+    fn test_build_geo_queries() {
+        let distance = build_geo_query(&GeoQuery::Distance {
+            field: "location".to_string(),
+            center: GeoPoint { lat: 40.0, lon: -70.0 },
+            distance: "12km".to_string(),
+            distance_type: None,
+        });
+        assert_eq!(distance["geo_distance"]["distance"], "12km");
+        assert_eq!(distance["geo_distance"]["location"]["lat"], 40.0);
+
+        let shape = build_geo_query(&GeoQuery::Shape {
+            field: "area".to_string(),
+            shape: serde_json::json!({ "type": "point", "coordinates": [13.4, 52.5] }),
+            relation: None,
+        });
+        assert_eq!(shape["geo_shape"]["area"]["relation"], "intersects");
+    }
+
+    #[test]
+    fn test_build_geo_aggregations() {
+        let agg = AggregationConfig {
+            id: "1".to_string(),
+            name: "grid".to_string(),
+            r#type: "geotile_grid".to_string(),
+            field: Some("location".to_string()),
+            params: Some(serde_json::json!({ "precision": 8 })),
+            buckets_path: None,
+            sub_aggregations: None,
+        };
+        let built = build_single_aggregation(&agg).unwrap();
+        assert_eq!(built["geotile_grid"]["precision"], 8);
+
+        let bounds = AggregationConfig { r#type: "geo_bounds".to_string(), params: None, ..agg };
+        assert_eq!(build_single_aggregation(&bounds).unwrap()["geo_bounds"]["field"], "location");
+    }
+
+    fn agg(agg_type: &str, field: &str, params: Option<Value>) -> AggregationConfig {
+        AggregationConfig {
+            id: agg_type.to_string(),
+            name: agg_type.to_string(),
+            r#type: agg_type.to_string(),
+            field: (!field.is_empty()).then(|| field.to_string()),
+            params,
+            buckets_path: None,
+            sub_aggregations: None,
+        }
+    }
+
+    #[test]
+    fn test_build_catalogue_aggregations() {
+        use serde_json::json;
+
+        let cases = vec![
+            (agg("percentiles", "latency", Some(json!({ "percents": [95, 99] }))),
+                json!({ "percentiles": { "field": "latency", "percents": [95, 99] } })),
+            (agg("percentile_ranks", "latency", Some(json!({ "values": [500] }))),
+                json!({ "percentile_ranks": { "field": "latency", "values": [500] } })),
+            (agg("stats", "price", None),
+                json!({ "stats": { "field": "price" } })),
+            (agg("extended_stats", "price", Some(json!({ "sigma": 3 }))),
+                json!({ "extended_stats": { "field": "price", "sigma": 3 } })),
+            (agg("top_hits", "", Some(json!({ "size": 1, "sort": [{ "date": "desc" }] }))),
+                json!({ "top_hits": { "size": 1, "sort": [{ "date": "desc" }] } })),
+            (agg("filters", "", Some(json!({ "filters": { "errors": { "term": { "level": "error" } } } }))),
+                json!({ "filters": { "filters": { "errors": { "term": { "level": "error" } } } } })),
+            (agg("filter", "", Some(json!({ "filter": { "term": { "type": "t-shirt" } } }))),
+                json!({ "filter": { "term": { "type": "t-shirt" } } })),
+            (agg("date_range", "date", Some(json!({ "ranges": [{ "to": "now-10M/M" }], "format": "MM-yyyy" }))),
+                json!({ "date_range": { "field": "date", "ranges": [{ "to": "now-10M/M" }], "format": "MM-yyyy" } })),
+            (agg("significant_terms", "crime_type", Some(json!({ "min_doc_count": 5 }))),
+                json!({ "significant_terms": { "field": "crime_type", "min_doc_count": 5 } })),
+            (agg("rare_terms", "genre", Some(json!({ "max_doc_count": 2 }))),
+                json!({ "rare_terms": { "field": "genre", "max_doc_count": 2 } })),
+            (agg("multi_terms", "", Some(json!({ "terms": [{ "field": "genre" }, { "field": "product" }] }))),
+                json!({ "multi_terms": { "terms": [{ "field": "genre" }, { "field": "product" }] } })),
+            (agg("nested", "resellers", None),
+                json!({ "nested": { "path": "resellers" } })),
+            (agg("reverse_nested", "", None),
+                json!({ "reverse_nested": {} })),
+            (agg("missing", "price", None),
+                json!({ "missing": { "field": "price" } })),
+            (agg("auto_date_histogram", "date", Some(json!({ "buckets": 10 }))),
+                json!({ "auto_date_histogram": { "field": "date", "buckets": 10 } })),
+            (agg("sampler", "", Some(json!({ "shard_size": 200 }))),
+                json!({ "sampler": { "shard_size": 200 } })),
+        ];
+
+        for (config, expected) in cases {
+            let built = build_single_aggregation(&config).unwrap();
+            assert_eq!(built, expected, "aggregation type {}", config.r#type);
+        }
+    }
+
+    #[test]
+    fn test_build_aggregation_missing_required_params() {
+        assert!(build_single_aggregation(&agg("percentile_ranks", "latency", None)).is_err());
+        assert!(build_single_aggregation(&agg("filter", "", None)).is_err());
+        assert!(build_single_aggregation(&agg("multi_terms", "", Some(serde_json::json!({})))).is_err());
+        assert!(build_single_aggregation(&agg("nested", "", None)).is_err());
+    }
+
+    fn pipeline(name: &str, agg_type: &str, buckets_path: Value, params: Option<Value>) -> AggregationConfig {
+        AggregationConfig {
+            name: name.to_string(),
+            buckets_path: Some(buckets_path),
+            ..agg(agg_type, "", params)
+        }
+    }
+
+    fn monthly_sales(sub_aggregations: Vec<AggregationConfig>) -> AggregationConfig {
+        AggregationConfig {
+            name: "sales_per_month".to_string(),
+            params: Some(serde_json::json!({ "calendar_interval": "month" })),
+            sub_aggregations: Some(sub_aggregations),
+            ..agg("date_histogram", "date", None)
+        }
+    }
+
+    #[test]
+    fn test_build_pipeline_aggregations() {
+        use serde_json::json;
+
+        let sales = AggregationConfig { name: "sales".to_string(), ..agg("sum", "price", None) };
+        let tree = vec![
+            monthly_sales(vec![
+                sales,
+                pipeline("sales_deriv", "derivative", json!("sales"), None),
+                pipeline("ratio", "bucket_script", json!({ "total": "sales", "count": "_count" }),
+                    Some(json!({ "script": "params.total / params.count" }))),
+                AggregationConfig { name: "top".to_string(), ..agg("bucket_sort", "", Some(json!({ "size": 3 }))) },
+            ]),
+            pipeline("best_month", "max_bucket", json!("sales_per_month>sales"), None),
+        ];
+
+        validate_pipeline_aggregations(&tree, None).unwrap();
+        let built = build_aggregations(&tree).unwrap();
+        let month_aggs = &built["sales_per_month"]["aggs"];
+        assert_eq!(month_aggs["sales_deriv"], json!({ "derivative": { "buckets_path": "sales" } }));
+        assert_eq!(month_aggs["ratio"]["bucket_script"]["buckets_path"]["count"], "_count");
+        assert_eq!(month_aggs["top"], json!({ "bucket_sort": { "size": 3 } }));
+        assert_eq!(built["best_month"], json!({ "max_bucket": { "buckets_path": "sales_per_month>sales" } }));
+    }
+
+    #[test]
+    fn test_validate_broken_pipeline_paths() {
+        use serde_json::json;
+
+        let sales = || AggregationConfig { name: "sales".to_string(), ..agg("sum", "price", None) };
+
+        // 引用不存在的同级聚合
+        let missing = vec![monthly_sales(vec![sales(), pipeline("d", "derivative", json!("revenue"), None)])];
+        let err = validate_pipeline_aggregations(&missing, None).unwrap_err().to_string();
+        assert!(err.contains("revenue") && err.contains("sales"));
+
+        // 父管道聚合不在 histogram 之下
+        let orphan = vec![sales(), pipeline("cum", "cumulative_sum", json!("sales"), None)];
+        assert!(validate_pipeline_aggregations(&orphan, None).is_err());
+
+        // 同级管道聚合必须以多桶聚合开头
+        let not_bucket = vec![sales(), pipeline("avg", "avg_bucket", json!("sales"), None)];
+        assert!(validate_pipeline_aggregations(&not_bucket, None).is_err());
+
+        // 路径中间段不存在
+        let bad_nested = vec![
+            monthly_sales(vec![sales()]),
+            pipeline("avg", "avg_bucket", json!("sales_per_month>profit"), None),
+        ];
+        assert!(validate_pipeline_aggregations(&bad_nested, None).is_err());
+
+        // _count 只能在末尾
+        let bad_special = vec![
+            monthly_sales(vec![sales()]),
+            pipeline("avg", "avg_bucket", json!("sales_per_month>_count>sales"), None),
+        ];
+        assert!(validate_pipeline_aggregations(&bad_special, None).is_err());
+    }
+
+    fn composite_request() -> CompositeAggregationRequest {
+        CompositeAggregationRequest {
+            index: "logs".to_string(),
+            query: None,
+            sources: vec![
+                CompositeSource {
+                    name: "host".to_string(),
+                    r#type: "terms".to_string(),
+                    field: "host.keyword".to_string(),
+                    params: None,
+                    missing_bucket: Some(true),
+                },
+                CompositeSource {
+                    name: "day".to_string(),
+                    r#type: "date_histogram".to_string(),
+                    field: "@timestamp".to_string(),
+                    params: Some(serde_json::json!({ "calendar_interval": "1d" })),
+                    missing_bucket: None,
+                },
+            ],
+            page_size: Some(2),
+            sub_aggregations: Some(vec![AggregationConfig { name: "bytes".to_string(), ..agg("sum", "bytes", None) }]),
+            after: None,
+        }
+    }
+
+    #[test]
+    fn test_build_composite_body() {
+        let request = composite_request();
+        let after = serde_json::json!({ "host": "a", "day": 1 });
+        let body = build_composite_body(&request, Some(&after)).unwrap();
+
+        let composite = &body["aggs"][COMPOSITE_AGG_NAME];
+        assert_eq!(body["size"], 0);
+        assert_eq!(composite["composite"]["size"], 2);
+        assert_eq!(composite["composite"]["after"], after);
+        assert_eq!(composite["composite"]["sources"][0]["host"]["terms"]["missing_bucket"], true);
+        assert_eq!(composite["composite"]["sources"][1]["day"]["date_histogram"]["calendar_interval"], "1d");
+        assert_eq!(composite["aggs"]["bytes"]["sum"]["field"], "bytes");
+
+        let mut invalid = composite_request();
+        invalid.sources[0].r#type = "range".to_string();
+        assert!(build_composite_body(&invalid, None).is_err());
+
+        // composite 下允许 bucket_selector，但按桶序的管道聚合仍需 histogram 父聚合
+        let mut selected = composite_request();
+        if let Some(sub_aggs) = selected.sub_aggregations.as_mut() {
+            sub_aggs.push(pipeline("busy", "bucket_selector", serde_json::json!({ "b": "bytes" }),
+                Some(serde_json::json!({ "script": "params.b > 100" }))));
+        }
+        let body = build_composite_body(&selected, None).unwrap();
+        assert_eq!(body["aggs"][COMPOSITE_AGG_NAME]["aggs"]["busy"]["bucket_selector"]["buckets_path"]["b"], "bytes");
+
+        let mut derived = composite_request();
+        if let Some(sub_aggs) = derived.sub_aggregations.as_mut() {
+            sub_aggs.push(pipeline("d", "derivative", serde_json::json!("missing"), None));
+        }
+        assert!(build_composite_body(&derived, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_composite_page_and_rows() {
+        let request = composite_request();
+        let response = serde_json::json!({
+            "aggregations": {
+                COMPOSITE_AGG_NAME: {
+                    "after_key": { "host": "b", "day": 86400000 },
+                    "buckets": [
+                        { "key": { "host": "a", "day": 0 }, "doc_count": 3, "bytes": { "value": 120.0 } },
+                        { "key": { "host": "b", "day": 86400000 }, "doc_count": 1, "bytes": { "value": 7.0 } }
+                    ]
+                }
+            }
+        });
+
+        let page = parse_composite_page(&response);
+        assert_eq!(page.buckets.len(), 2);
+        assert_eq!(page.after_key.unwrap()["host"], "b");
+
+        assert_eq!(composite_columns(&request), vec!["host", "day", "doc_count", "bytes"]);
+        let row = composite_bucket_row(&page.buckets[0], &request);
+        assert_eq!(row, vec![Value::from("a"), Value::from(0), Value::from(3), Value::from(120.0)]);
+
+        let last = parse_composite_page(&serde_json::json!({ "aggregations": { COMPOSITE_AGG_NAME: { "buckets": [] } } }));
+        assert!(last.buckets.is_empty() && last.after_key.is_none());
+    }
+
+    #[test]
+    fn test_build_pit_page_body() {
+        let mut export_query = query("logs");
+        export_query.from = Some(100);
+        export_query.aggs = Some(serde_json::json!({ "hosts": { "terms": { "field": "host" } } }));
+
+        let base = build_export_base_body(&export_query);
+        assert!(base.get("from").is_none());
+        assert!(base.get("aggs").is_none());
+
+        let first = build_pit_page_body(&base, "pit-1", "2m", 500, None);
+        assert_eq!(first["pit"]["id"], "pit-1");
+        assert_eq!(first["size"], 500);
+        assert_eq!(first["sort"], serde_json::json!(["_shard_doc"]));
+        assert!(first.get("search_after").is_none());
+
+        let after = serde_json::json!([1700000000000i64, 42]);
+        let next = build_pit_page_body(&base, "pit-2", "2m", 500, Some(&after));
+        assert_eq!(next["search_after"], after);
+    }
+
+    #[test]
+    fn test_decode_geo_cells() {
+        let cell = decode_geohash("u4pruyd").unwrap();
+        assert!((cell.top_left.lat - 57.649).abs() < 0.01);
+        assert!((cell.top_left.lon - 10.407).abs() < 0.01);
+        assert!(cell.top_left.lat > cell.bottom_right.lat);
+        assert!(decode_geohash("a").is_none());
+
+        let tile = decode_geotile("1/1/0").unwrap();
+        assert_eq!(tile.top_left.lon, 0.0);
+        assert_eq!(tile.bottom_right.lon, 180.0);
+        assert_eq!(tile.bottom_right.lat, 0.0);
+
+        let grid = serde_json::json!({ "buckets": [{ "key": "0/0/0", "doc_count": 3 }] });
+        let cells = parse_geo_cells(&grid, "geotile_grid");
+        assert_eq!(cells[0].doc_count, 3);
+        assert!(cells[0].center.lat.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_msearch_item_keeps_partial_failures() {
+        let ok = serde_json::json!({
+            "took": 3,
+            "timed_out": false,
+            "hits": { "total": { "value": 1, "relation": "eq" }, "hits": [{ "_id": "1" }] },
+            "status": 200
+        });
+        let failed = serde_json::json!({
+            "error": { "type": "index_not_found_exception", "reason": "no such index [missing]" },
+            "status": 404
+        });
+
+        let ok_item = parse_msearch_item(&ok);
+        assert_eq!(ok_item.result.unwrap().total, 1);
+        assert!(ok_item.error.is_none());
+
+        let failed_item = parse_msearch_item(&failed);
+        assert!(failed_item.result.is_none());
+        assert_eq!(failed_item.error.unwrap().code, "INDEX_NOT_FOUND");
+    }
+}
@@ -1,222 +1,1740 @@
-use crate::types::{ExportFormat, ExportRequest, ExportResult};
+use crate::columnar::{date_fields, detect_list_fields, merged_mapping_properties, parse_es_date, schema_from_mapping, ColumnarFormat, ColumnarWriter, EpochUnit, DEFAULT_ROW_GROUP_SIZE};
+use crate::es_client::{composite_bucket_row, composite_columns, EsClient, HitPages};
+use crate::flatten::{is_hit_metadata, mapping_columns, validate_flatten_options, ColumnCollector, Flattener};
+use crate::redaction::{dropped_fields, redact_mapping_properties, Redactor};
+use crate::output::{available_path, checkpoint_path, discard_partial_output, finish_buffered, prepare_directory, sanitize_filename, validate_output_options, with_format_extension, write_checkpoint, ExportParts, OutputFile};
+use crate::types::{BulkExportOptions, CompositeExportRequest, ExportCheckpoint, ExportCompression, ExportFormat, ExportProgress, ExportRequest, ExportResult, ExportTarget, FlattenOptions, HeaderDiscovery, RedactionConfig, RedactionRule, SearchQuery, SqlExportRequest};
 use anyhow::{anyhow, Result};
 use csv::WriterBuilder;
 use serde_json::Value;
-use std::fs::{create_dir_all, File};
-use std::io::BufWriter;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
-use rust_xlsxwriter::{Workbook, Format};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
-pub struct ExportService;
+pub struct ExportService {
+    default_directory: Option<PathBuf>,
+}
 
 impl ExportService {
-    pub fn new() -> Self {
-        Self
+    // 使用应用设置中的默认导出目录，为空时回退到 ~/Documents/ES_Client_Exports
+    pub fn with_default_directory(directory: Option<String>) -> Self {
+        Self { default_directory: directory.map(PathBuf::from) }
     }
 
-    pub async fn export_data(&self, request: ExportRequest, data: Vec<Value>) -> Result<ExportResult> {
-        // 创建导出目录
-        let export_dir = self.get_export_directory()?;
-        create_dir_all(&export_dir)?;
+    // 通过 PIT/search_after（或 scroll）逐页取命中并立即写入文件，内存占用与结果总量无关。
+    // max_records 为空时导出全部命中；设置 split 时按条数或大小切换分片并写出清单。
+    pub async fn export_search_results(
+        &self,
+        client: &EsClient,
+        request: &ExportRequest,
+        job_id: &str,
+        cancelled: &AtomicBool,
+        on_progress: &mut (dyn FnMut(&ExportProgress) + Send),
+    ) -> Result<ExportResult> {
+        validate_output_options(&request.format, request.compression, request.split.as_ref())?;
+        if let Some(flatten) = &request.flatten {
+            validate_flatten_options(&request.format, flatten, request.split.as_ref())?;
+        }
+        let redactor = Redactor::new(request.redaction.as_ref())?;
+
+        // 拆分时以清单文件判断是否重名
+        let base_path = self.resolve_output_path(&request.target, &request.filename, request.format.extension())?;
+        let base_path = available_path(base_path, request.target.overwrite, |candidate| {
+            ExportParts::new(candidate.to_path_buf(), request.compression, request.split.clone())
+                .primary_path()
+                .exists()
+        });
+        let parts = ExportParts::new(base_path, request.compression, request.split.clone());
+
+        // 列式格式的 schema、Excel 的日期列以及按映射生成的表头都需要映射
+        let needs_mapping = matches!(request.format, ExportFormat::Parquet | ExportFormat::ArrowIpc | ExportFormat::Excel)
+            || request.flatten.as_ref().is_some_and(|f| f.headers == HeaderDiscovery::Mapping);
+        let mapping = match needs_mapping {
+            true => Some(client.get_mapping(&request.query.index).await?),
+            false => None,
+        };
+
+        // 可续传时断点里保存固定了盐的请求，续传部分的哈希与之前一致。
+        // 盐以明文写在断点文件中（仅当前用户可读），导出成功后断点即被删除
+        let checkpoint_request = is_resumable(request).then(|| {
+            let mut checkpoint_request = request.clone();
+            if let Some(redaction) = &mut checkpoint_request.redaction {
+                redaction.salt = Some(redactor.salt().to_string());
+            }
+            checkpoint_request
+        });
+        let mut state = HitExportState {
+            parts,
+            writer: None,
+            redactor,
+            records_written: 0,
+            checkpoint_request,
+            unknown_columns: BTreeSet::new(),
+        };
+
+        // 表头和列式 schema 依据第一页命中确定，后续分片沿用同一页推断
+        let options = HitWriterOptions::from(request);
+        let create_writer = |path: &Path, first_page: &[Value]| HitWriter::create(path, &options, mapping.as_ref(), first_page);
+
+        let query = state.redactor.export_query(&request.query);
+        let mut pages = client.hit_pages(&query, self.page_size(request.max_records, 0)).await?;
+        let outcome = self
+            .drain_hits(&mut pages, request, &mut state, &create_writer, job_id, cancelled, on_progress)
+            .await;
+        pages.close().await;
+
+        outcome?;
+        self.finish_hit_export(&request.format, state)
+    }
+
+    // 从断点续传：把部分文件截断到断点记录的位置，再用新的 PIT 从 search_after 之后继续追加
+    pub async fn resume_search_export(
+        &self,
+        client: &EsClient,
+        checkpoint: &ExportCheckpoint,
+        job_id: &str,
+        cancelled: &AtomicBool,
+        on_progress: &mut (dyn FnMut(&ExportProgress) + Send),
+    ) -> Result<ExportResult> {
+        let request = &checkpoint.request;
+        if !has_stable_sort(&request.query) {
+            return Err(anyhow!("该导出不支持续传：需要显式指定排序，且不包含 _score、_doc 或 _shard_doc，最后一项应为唯一字段（如文档 ID 字段）"));
+        }
+        if !is_resumable(request) {
+            return Err(anyhow!("该导出不支持续传：仅未压缩、未拆分的 JSON、NDJSON、Bulk 和 CSV 导出可以续传"));
+        }
+
+        let file_path = PathBuf::from(&checkpoint.file_path);
+        let options = HitWriterOptions::from(request);
+        let writer = HitWriter::resume(&file_path, &options, checkpoint)?;
+
+        let mut parts = ExportParts::new(file_path, None, None);
+        parts.open_next();
+        let mut state = HitExportState {
+            parts,
+            writer: Some(writer),
+            redactor: Redactor::new(request.redaction.as_ref())?,
+            records_written: checkpoint.records_written,
+            checkpoint_request: Some(request.clone()),
+            unknown_columns: BTreeSet::new(),
+        };
+
+        // 不拆分时写入器一直存在，不会再创建新文件
+        let create_writer = |path: &Path, first_page: &[Value]| HitWriter::create(path, &options, None, first_page);
 
-        // 生成文件路径
-        let file_path = export_dir.join(&request.filename);
-        
-        // 限制导出记录数量
-        let max_records = request.max_records.unwrap_or(10000) as usize;
-        let limited_data: Vec<Value> = data.into_iter().take(max_records).collect();
-        let total_records = limited_data.len() as u64;
+        let page_size = self.page_size(request.max_records, checkpoint.records_written);
+        let query = state.redactor.export_query(&request.query);
+        let mut pages = client.hit_pages_after(&query, page_size, checkpoint.search_after.clone()).await?;
+        let outcome = self
+            .drain_hits(&mut pages, request, &mut state, &create_writer, job_id, cancelled, on_progress)
+            .await;
+        pages.close().await;
 
-        // 根据格式导出
-        match request.format {
-            ExportFormat::JSON => self.export_json(&file_path, &limited_data, &request.selected_fields)?,
-            ExportFormat::CSV => self.export_csv(&file_path, &limited_data, &request.selected_fields)?,
-            ExportFormat::Excel => self.export_excel(&file_path, &limited_data, &request.selected_fields)?,
+        outcome?;
+        self.finish_hit_export(&request.format, state)
+    }
+
+    // 写出清单并删除已经用不到的断点
+    fn finish_hit_export(&self, format: &ExportFormat, state: HitExportState) -> Result<ExportResult> {
+        let parts = &state.parts;
+        let total_records = state.records_written;
+        let manifest_path = parts.write_manifest(format)?;
+
+        let checkpoint_file = checkpoint_path(&parts.primary_path());
+        if checkpoint_file.exists() {
+            if let Err(e) = std::fs::remove_file(&checkpoint_file) {
+                tracing::warn!("Failed to remove export checkpoint: {}", e);
+            }
+        }
+
+        let message = match &manifest_path {
+            Some(_) => format!("成功导出 {} 条记录到 {} 个文件", total_records, parts.parts().len()),
+            None => format!("成功导出 {} 条记录到 {}", total_records, parts.primary_path().display()),
+        };
+
+        let mut warnings = Vec::new();
+        if !state.unknown_columns.is_empty() {
+            let columns: Vec<&str> = state.unknown_columns.iter().map(String::as_str).collect();
+            let warning = format!("以下字段在表头确定之后才出现，未写入导出文件：{}；可改用完整扫描或按映射生成表头", columns.join(", "));
+            tracing::warn!("{}", warning);
+            warnings.push(warning);
         }
 
         Ok(ExportResult {
             success: true,
-            file_path: file_path.to_string_lossy().to_string(),
+            file_path: parts.primary_path().to_string_lossy().to_string(),
             total_records,
-            message: format!("成功导出 {} 条记录到 {}", total_records, request.filename),
+            message,
+            manifest_path: manifest_path.map(|p| p.to_string_lossy().to_string()),
+            redacted_values: state.redactor.redacted_values(),
+            warnings,
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
+    async fn drain_hits(
+        &self,
+        pages: &mut HitPages<'_>,
+        request: &ExportRequest,
+        state: &mut HitExportState,
+        create_writer: &(dyn Fn(&Path, &[Value]) -> Result<HitWriter> + Sync),
+        job_id: &str,
+        cancelled: &AtomicBool,
+        on_progress: &mut (dyn FnMut(&ExportProgress) + Send),
+    ) -> Result<()> {
+        let mut tracker = ProgressTracker::new(job_id, state.records_written);
+        let mut first_page: Vec<Value> = Vec::new();
+        let mut page_count = 0u32;
+        let mut last_checkpoint = Instant::now();
+        let mut checkpoint_file: Option<PathBuf> = None;
+
+        while let Some(mut hits) = pages.next_page().await? {
+            if tracker.total_records.is_none() {
+                tracker.total_records = pages.total_hits().map(|total| request.max_records.map_or(total, |max| total.min(max)));
+            }
+            if let Some(max_records) = request.max_records {
+                hits.truncate(max_records.saturating_sub(state.records_written) as usize);
+            }
+            // 先脱敏再推断表头，原始值不会出现在任何格式的输出中
+            for hit in &mut hits {
+                state.redactor.redact_hit(hit);
+            }
+            if first_page.is_empty() {
+                first_page = hits.clone();
+            }
+
+            for hit in &hits {
+                // 当前分片写满后收尾，下一条命中写入新分片
+                let parts = &mut state.parts;
+                if let Some(full) = state.writer.take_if(|w| parts.is_full(w.records_written(), w.bytes_written())) {
+                    let part_records = full.records_written();
+                    state.unknown_columns.extend(full.unknown_columns().iter().cloned());
+                    full.finish()?;
+                    parts.close_current(part_records)?;
+                }
+
+                let hit_writer = match &mut state.writer {
+                    Some(hit_writer) => hit_writer,
+                    None => state.writer.insert(create_writer(&parts.open_next(), &first_page)?),
+                };
+                hit_writer.write_hit(hit)?;
+                state.records_written += 1;
+            }
+            page_count += 1;
+
+            let stopping = cancelled.load(Ordering::Relaxed);
+            let limit_reached = request.max_records.is_some_and(|max| state.records_written >= max);
+
+            // 定期在页边界写断点；取消时也写一次，之后可以从这里续传
+            if !limit_reached && (stopping || last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL) {
+                if let Some(path) = state.write_checkpoint(pages.search_after())? {
+                    checkpoint_file = Some(path);
+                }
+                last_checkpoint = Instant::now();
+            }
+
+            on_progress(&ExportProgress {
+                checkpoint_path: checkpoint_file.as_ref().map(|p| p.to_string_lossy().to_string()),
+                ..tracker.progress(state.records_written, state.bytes_written(), page_count, false)
+            });
+
+            if stopping {
+                return Err(anyhow!("导出已取消"));
+            }
+
+            if limit_reached {
+                break;
+            }
+            pages.set_page_size(self.page_size(request.max_records, state.records_written));
+        }
+
+        // 没有命中时也生成文件（空数组或只有表头），定时导出不会因此记为失败
+        let writer = match state.writer.take() {
+            Some(writer) => writer,
+            None => create_writer(&state.parts.open_next(), &first_page)?,
+        };
+        let part_records = writer.records_written();
+        state.unknown_columns.extend(writer.unknown_columns().iter().cloned());
+        writer.finish()?;
+        state.parts.close_current(part_records)?;
+
+        on_progress(&tracker.progress(state.records_written, state.parts.bytes_written(), page_count, true));
+
+        Ok(())
+    }
+
+    fn page_size(&self, max_records: Option<u64>, written: u64) -> u32 {
+        match max_records {
+            Some(max) => max.saturating_sub(written).clamp(1, EXPORT_PAGE_SIZE as u64) as u32,
+            None => EXPORT_PAGE_SIZE,
+        }
+    }
+
     pub fn get_export_directory(&self) -> Result<PathBuf> {
+        if let Some(directory) = &self.default_directory {
+            return Ok(directory.clone());
+        }
         let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("无法获取用户主目录"))?;
         Ok(home_dir.join("Documents").join("ES_Client_Exports"))
     }
 
-    fn export_json(&self, file_path: &Path, data: &[Value], selected_fields: &Option<Vec<String>>) -> Result<()> {
-        let file = File::create(file_path)?;
-        let writer = BufWriter::new(file);
+    // 解析导出文件路径：文件名清理成单个路径组件并改成格式对应的扩展名，目录必须是绝对路径。
+    // 不处理重名，由调用方按 overwrite 决定
+    pub fn resolve_output_path(&self, target: &ExportTarget, filename: &str, extension: &str) -> Result<PathBuf> {
+        let (directory, filename) = match &target.file_path {
+            Some(file_path) => {
+                let path = Path::new(file_path);
+                let name = path
+                    .file_name()
+                    .ok_or_else(|| anyhow!("导出路径缺少文件名：{}", file_path))?
+                    .to_string_lossy()
+                    .to_string();
+                let parent = path.parent().unwrap_or(path).to_path_buf();
+                (parent, name)
+            }
+            None => {
+                let directory = match &target.directory {
+                    Some(directory) => PathBuf::from(directory),
+                    None => self.get_export_directory()?,
+                };
+                (directory, filename.to_string())
+            }
+        };
+
+        let directory = prepare_directory(&directory)?;
+        Ok(directory.join(with_format_extension(&sanitize_filename(&filename)?, extension)))
+    }
+
+    // 解析路径，不覆盖时为同名文件追加序号
+    pub fn output_path(&self, target: &ExportTarget, filename: &str, extension: &str) -> Result<PathBuf> {
+        let path = self.resolve_output_path(target, filename, extension)?;
+        Ok(available_path(path, target.overwrite, |candidate| candidate.exists()))
+    }
+
+    // 导出已经取得的列加行结果（如 ES|QL）
+    pub fn export_table(
+        &self,
+        target: &ExportTarget,
+        filename: &str,
+        format: &ExportFormat,
+        columns: Vec<String>,
+        rows: &[Vec<Value>],
+        redaction: Option<&RedactionConfig>,
+    ) -> Result<ExportResult> {
+        let file_path = self.output_path(target, filename, format.extension())?;
+        let mut redactor = Redactor::new(redaction)?;
 
-        if let Some(fields) = selected_fields {
-            // 只导出选中的字段
-            let filtered_data: Vec<Value> = data.iter()
-                .map(|doc| self.filter_fields(doc, fields))
-                .collect();
-            serde_json::to_writer_pretty(writer, &filtered_data)?;
-        } else {
-            // 导出所有数据
-            serde_json::to_writer_pretty(writer, data)?;
+        let mut writer = TableWriter::create(&file_path, format, redactor.visible_columns(&columns))?;
+        for row in rows {
+            writer.write_row(&redactor.redact_row(&columns, row))?;
         }
+        let total_records = writer.rows_written();
+        writer.finish()?;
 
-        Ok(())
+        Ok(ExportResult {
+            success: true,
+            file_path: file_path.to_string_lossy().to_string(),
+            total_records,
+            message: format!("成功导出 {} 条记录到 {}", total_records, file_path.display()),
+            manifest_path: None,
+            redacted_values: redactor.redacted_values(),
+            warnings: Vec::new(),
+        })
     }
 
-    fn export_csv(&self, file_path: &Path, data: &[Value], selected_fields: &Option<Vec<String>>) -> Result<()> {
-        if data.is_empty() {
-            return Err(anyhow!("没有数据可以导出"));
+    // 按 after_key 翻页导出 composite 聚合的全部桶，每页写完即释放；失败或取消时删除写了一半的文件
+    pub async fn export_composite_aggregation(
+        &self,
+        client: &EsClient,
+        request: &CompositeExportRequest,
+        job_id: &str,
+        cancelled: &AtomicBool,
+        on_progress: &mut (dyn FnMut(&ExportProgress) + Send),
+    ) -> Result<ExportResult> {
+        let file_path = self.output_path(&request.target, &request.filename, request.format.extension())?;
+
+        let mut redactor = Redactor::new(request.redaction.as_ref())?;
+
+        let outcome = self
+            .drain_composite(client, request, &file_path, &mut redactor, job_id, cancelled, on_progress)
+            .await;
+        if outcome.is_err() {
+            discard_partial_output(&file_path);
         }
 
-        let file = File::create(file_path)?;
-        let mut writer = WriterBuilder::new().from_writer(file);
+        let total_records = outcome?;
+        Ok(ExportResult {
+            success: true,
+            file_path: file_path.to_string_lossy().to_string(),
+            total_records,
+            message: format!("成功导出 {} 个桶到 {}", total_records, file_path.display()),
+            manifest_path: None,
+            redacted_values: redactor.redacted_values(),
+            warnings: Vec::new(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn drain_composite(
+        &self,
+        client: &EsClient,
+        request: &CompositeExportRequest,
+        file_path: &Path,
+        redactor: &mut Redactor,
+        job_id: &str,
+        cancelled: &AtomicBool,
+        on_progress: &mut (dyn FnMut(&ExportProgress) + Send),
+    ) -> Result<u64> {
+        let columns = composite_columns(&request.request);
+
+        let mut writer = TableWriter::create(file_path, &request.format, redactor.visible_columns(&columns))?;
+        let mut pages_iter = client.composite_pages(&request.request);
+        let tracker = ProgressTracker::new(job_id, 0);
+        let mut pages = 0u32;
+
+        while let Some(page) = pages_iter.next_page().await? {
+            for bucket in &page.buckets {
+                writer.write_row(&redactor.redact_row(&columns, &composite_bucket_row(bucket, &request.request)))?;
+            }
+            pages += 1;
 
-        // 提取字段名
-        let headers = if let Some(fields) = selected_fields {
-            fields.clone()
-        } else {
-            self.extract_all_fields(data)
+            on_progress(&tracker.progress(writer.rows_written(), writer.bytes_written(), pages, false));
+
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(anyhow!("导出已取消"));
+            }
+        }
+
+        let total_records = writer.rows_written();
+        writer.finish()?;
+
+        let bytes_written = std::fs::metadata(file_path).map(|m| m.len()).unwrap_or(0);
+        on_progress(&tracker.progress(total_records, bytes_written, pages, true));
+        Ok(total_records)
+    }
+
+    // 执行 SQL 并跟随游标直到结果耗尽，逐页写入文件。
+    // 取消或失败时会关闭仍未耗尽的游标，避免在集群上残留搜索上下文，并删除写了一半的文件。
+    pub async fn export_sql_results(
+        &self,
+        client: &EsClient,
+        request: &SqlExportRequest,
+        job_id: &str,
+        cancelled: &AtomicBool,
+        on_progress: &mut (dyn FnMut(&ExportProgress) + Send),
+    ) -> Result<ExportResult> {
+        let file_path = self.output_path(&request.target, &request.filename, request.format.extension())?;
+        let mut redactor = Redactor::new(request.redaction.as_ref())?;
+
+        let mut cursor: Option<String> = None;
+        let outcome = self
+            .drain_sql(client, request, &file_path, &mut redactor, job_id, cancelled, &mut cursor, on_progress)
+            .await;
+
+        if outcome.is_err() {
+            if let Some(open_cursor) = &cursor {
+                if let Err(e) = client.close_sql_cursor(open_cursor).await {
+                    tracing::warn!("Failed to close SQL cursor after export failure: {}", e);
+                }
+            }
+            discard_partial_output(&file_path);
+        }
+
+        let total_records = outcome?;
+        Ok(ExportResult {
+            success: true,
+            file_path: file_path.to_string_lossy().to_string(),
+            total_records,
+            message: format!("成功导出 {} 条记录到 {}", total_records, file_path.display()),
+            manifest_path: None,
+            redacted_values: redactor.redacted_values(),
+            warnings: Vec::new(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn drain_sql(
+        &self,
+        client: &EsClient,
+        request: &SqlExportRequest,
+        file_path: &Path,
+        redactor: &mut Redactor,
+        job_id: &str,
+        cancelled: &AtomicBool,
+        cursor: &mut Option<String>,
+        on_progress: &mut (dyn FnMut(&ExportProgress) + Send),
+    ) -> Result<u64> {
+        // 导出需要按行读取，忽略 columnar
+        let mut query = request.query.clone();
+        query.columnar = None;
+
+        let mut page = client.execute_sql(&query).await?;
+        *cursor = page.cursor.clone();
+
+        let columns: Vec<String> = page.columns.iter().map(|c| c.name.clone()).collect();
+        let mut writer = TableWriter::create(file_path, &request.format, redactor.visible_columns(&columns))?;
+        let tracker = ProgressTracker::new(job_id, 0);
+        let mut pages = 0u32;
+
+        loop {
+            for row in &page.rows {
+                writer.write_row(&redactor.redact_row(&columns, row))?;
+            }
+            pages += 1;
+
+            on_progress(&tracker.progress(writer.rows_written(), writer.bytes_written(), pages, false));
+
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(anyhow!("导出已取消"));
+            }
+
+            match cursor.clone() {
+                Some(next) => {
+                    page = client.execute_sql_cursor(&next).await?;
+                    *cursor = page.cursor.clone();
+                }
+                None => break,
+            }
+        }
+
+        let total_records = writer.rows_written();
+        writer.finish()?;
+
+        let bytes_written = std::fs::metadata(file_path).map(|m| m.len()).unwrap_or(0);
+        on_progress(&tracker.progress(total_records, bytes_written, pages, true));
+
+        Ok(total_records)
+    }
+}
+
+// 搜索导出每页取回的命中数
+const EXPORT_PAGE_SIZE: u32 = 1000;
+
+// 两次写断点之间的最短间隔
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+// 只有截断后追加写入仍然有效的输出才能续传：未压缩、未拆分的文本格式，
+// 表头不依赖完整扫描（完整扫描在收尾时才生成文件），且排序值能在新的 PIT 中定位断点
+fn is_resumable(request: &ExportRequest) -> bool {
+    matches!(request.format, ExportFormat::JSON | ExportFormat::NDJSON | ExportFormat::Bulk | ExportFormat::CSV)
+        && has_stable_sort(&request.query)
+        && request.compression.is_none()
+        && request.split.is_none()
+        && !request.flatten.as_ref().is_some_and(|f| f.headers == HeaderDiscovery::FullScan)
+}
+
+// 未指定排序时按 _shard_doc 排序，它的值只在产生它的 PIT 内有效，续传时新开的 PIT 会跳过或重复文档；
+// _score 和 _doc 同样会随索引写入或合并而变化。这里无法确认最后一项是否唯一，由用户保证
+fn has_stable_sort(query: &SearchQuery) -> bool {
+    let sort = query.sort.as_deref().unwrap_or_default();
+    !sort.is_empty()
+        && sort.iter().all(|entry| {
+            let field = match entry {
+                Value::String(field) => Some(field.as_str()),
+                Value::Object(object) => object.keys().next().map(String::as_str),
+                _ => None,
+            };
+            field.is_some_and(|f| !matches!(f, "_score" | "_doc" | "_shard_doc"))
+        })
+}
+
+// 一次搜索导出过程中的状态，续传时写入器和已写条数从断点恢复
+struct HitExportState {
+    parts: ExportParts,
+    writer: Option<HitWriter>,
+    redactor: Redactor,
+    records_written: u64,
+    checkpoint_request: Option<ExportRequest>, // 为空表示不可续传，不写断点
+    unknown_columns: BTreeSet<String>,         // 各分片中不在表头里的字段
+}
+
+impl HitExportState {
+    // 已写完的分片加上当前分片的字节数
+    fn bytes_written(&self) -> u64 {
+        self.parts.bytes_written() + self.writer.as_ref().map_or(0, |w| w.bytes_written())
+    }
+
+    // 把缓冲写入磁盘后记录断点；scroll 模式没有 search_after，无法续传
+    fn write_checkpoint(&mut self, search_after: Option<&Value>) -> Result<Option<PathBuf>> {
+        let (Some(request), Some(search_after), Some(writer)) = (&self.checkpoint_request, search_after, &mut self.writer) else {
+            return Ok(None);
+        };
+        let Some(bytes_written) = writer.flush_for_checkpoint()? else {
+            return Ok(None);
         };
 
-        // 写入表头
-        writer.write_record(&headers)?;
+        let file_path = self.parts.primary_path();
+        let path = checkpoint_path(&file_path);
+        write_checkpoint(&path, &ExportCheckpoint {
+            request: request.clone(),
+            file_path: file_path.to_string_lossy().to_string(),
+            records_written: self.records_written,
+            bytes_written,
+            search_after: search_after.clone(),
+            headers: writer.headers().map(|h| h.to_vec()),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        })?;
+        Ok(Some(path))
+    }
+}
 
-        // 写入数据行
-        for doc in data {
-            let mut row = Vec::new();
-            for header in &headers {
-                let value = self.get_nested_field(doc, header);
-                row.push(self.value_to_string(&value));
+// 按本次运行写入的条数计算速率和预计剩余时间，续传时不计入断点之前的记录
+struct ProgressTracker {
+    job_id: String,
+    started: Instant,
+    initial_records: u64,
+    total_records: Option<u64>,
+}
+
+impl ProgressTracker {
+    fn new(job_id: &str, initial_records: u64) -> Self {
+        Self {
+            job_id: job_id.to_string(),
+            started: Instant::now(),
+            initial_records,
+            total_records: None,
+        }
+    }
+
+    fn progress(&self, records_written: u64, bytes_written: u64, pages: u32, finished: bool) -> ExportProgress {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let records_per_second = match elapsed > 0.0 {
+            true => records_written.saturating_sub(self.initial_records) as f64 / elapsed,
+            false => 0.0,
+        };
+        let eta_seconds = match (finished, self.total_records) {
+            (true, _) => Some(0),
+            (false, Some(total)) if records_per_second > 0.0 => {
+                Some((total.saturating_sub(records_written) as f64 / records_per_second).ceil() as u64)
             }
-            writer.write_record(&row)?;
+            _ => None,
+        };
+
+        ExportProgress {
+            job_id: self.job_id.clone(),
+            records_written,
+            pages,
+            finished,
+            bytes_written,
+            total_records: self.total_records,
+            records_per_second,
+            eta_seconds,
+            checkpoint_path: None,
         }
+    }
+}
 
-        writer.flush()?;
-        Ok(())
+// 逐条写出搜索命中：JSON 为格式化数组，NDJSON 每行一个 _source，
+// Bulk 为可直接回放到 _bulk 的动作行加 _source 行，CSV/Excel 按表头展开字段，
+// Parquet/Arrow IPC 按映射生成的 schema 写成列式文件
+pub struct HitWriter {
+    sink: HitSink,
+    selected_fields: Option<Vec<String>>,
+    records_written: u64,
+    // 表头由程序推断时的全部列，用于发现之后才出现的字段；选择了字段时为空
+    known_columns: Option<HashSet<String>>,
+    unknown_columns: BTreeSet<String>,
+}
+
+enum HitSink {
+    Json { writer: BufWriter<OutputFile>, first: bool },
+    NdJson(BufWriter<OutputFile>),
+    Bulk { writer: BufWriter<OutputFile>, options: BulkExportOptions },
+    Table { writer: TableWriter, headers: Vec<String>, flattener: Option<Flattener> },
+    Spool(Box<SpoolSink>),
+    Columnar(Box<ColumnarWriter>),
+}
+
+// 命中写入器的格式相关选项，一般直接取自 ExportRequest
+#[derive(Debug, Clone)]
+pub struct HitWriterOptions {
+    pub format: ExportFormat,
+    pub selected_fields: Option<Vec<String>>,
+    pub bulk_options: Option<BulkExportOptions>,
+    pub compression: Option<ExportCompression>,
+    pub flatten: Option<FlattenOptions>,
+    // 已合并保存的配置的脱敏规则，用于让表头和 schema 与脱敏后的文档一致
+    pub redaction_rules: Vec<RedactionRule>,
+}
+
+impl HitWriterOptions {
+    #[cfg(test)]
+    pub fn new(format: ExportFormat) -> Self {
+        Self {
+            format,
+            selected_fields: None,
+            bulk_options: None,
+            compression: None,
+            flatten: None,
+            redaction_rules: Vec::new(),
+        }
     }
+}
 
-    fn export_excel(&self, file_path: &Path, data: &[Value], selected_fields: &Option<Vec<String>>) -> Result<()> {
-        if data.is_empty() {
-            return Err(anyhow!("没有数据可以导出"));
+impl From<&ExportRequest> for HitWriterOptions {
+    fn from(request: &ExportRequest) -> Self {
+        Self {
+            format: request.format.clone(),
+            selected_fields: request.selected_fields.clone(),
+            bulk_options: request.bulk_options.clone(),
+            compression: request.compression,
+            flatten: request.flatten.clone(),
+            redaction_rules: request.redaction.as_ref().map(|r| r.rules.clone()).unwrap_or_default(),
         }
+    }
+}
+
+impl HitWriter {
+    // 未选择字段时，CSV/Excel 的表头从第一页命中、映射或完整扫描得到
+    pub fn create(file_path: &Path, options: &HitWriterOptions, mapping: Option<&Value>, first_page: &[Value]) -> Result<Self> {
+        let format = &options.format;
+        let compression = options.compression;
+        let selected_fields = options.selected_fields.clone();
+        let open = || -> Result<BufWriter<OutputFile>> { Ok(BufWriter::new(OutputFile::create(file_path, compression)?)) };
+        let properties = |mapping: &Value| {
+            let mut properties = merged_mapping_properties(mapping);
+            redact_mapping_properties(&mut properties, &options.redaction_rules);
+            properties
+        };
+        let sink = match format {
+            ExportFormat::JSON => {
+                let mut writer = open()?;
+                writer.write_all(b"[")?;
+                HitSink::Json { writer, first: true }
+            }
+            ExportFormat::NDJSON => HitSink::NdJson(open()?),
+            ExportFormat::Bulk => {
+                let bulk_options = options.bulk_options.clone().unwrap_or_default();
+                if !matches!(bulk_options.action.as_deref(), None | Some("index") | Some("create")) {
+                    return Err(anyhow!("Bulk 导出只支持 index 或 create 动作"));
+                }
+                HitSink::Bulk { writer: open()?, options: bulk_options }
+            }
+            ExportFormat::CSV | ExportFormat::Excel => {
+                let dates = mapping.map(|m| date_fields(&properties(m))).unwrap_or_default();
+                let flattener = options.flatten.as_ref().map(Flattener::new);
+                let headers = match (&selected_fields, &options.flatten, &flattener) {
+                    (Some(fields), _, _) => Some(fields.clone()),
+                    (None, Some(flatten), Some(flattener)) => match flatten.headers {
+                        HeaderDiscovery::Sample => Some(sample_headers(flattener, first_page)?),
+                        HeaderDiscovery::Mapping => {
+                            let mapping = mapping.ok_or_else(|| anyhow!("按映射生成表头需要索引映射"))?;
+                            Some(mapping_columns(&properties(mapping)))
+                        }
+                        HeaderDiscovery::FullScan => None,
+                    },
+                    _ => Some(extract_all_fields(first_page)),
+                };
 
-        let mut workbook = Workbook::new();
-        let worksheet = workbook.add_worksheet();
+                let dropped = dropped_fields(&options.redaction_rules);
+                let headers = headers.map(|h| h.into_iter().filter(|c| !dropped.contains(c)).collect::<Vec<_>>());
 
-        // 提取字段名
-        let headers = if let Some(fields) = selected_fields {
-            fields.clone()
-        } else {
-            self.extract_all_fields(data)
+                match (headers, flattener) {
+                    (Some(headers), flattener) => {
+                        let writer = TableWriter::create_compressed(file_path, format, headers.clone(), compression)?.with_date_columns(&dates);
+                        HitSink::Table { writer, headers, flattener }
+                    }
+                    (None, Some(flattener)) => HitSink::Spool(Box::new(SpoolSink::create(file_path, format, compression, dates, flattener)?)),
+                    (None, None) => return Err(anyhow!("无法确定导出表头")),
+                }
+            }
+            ExportFormat::Parquet | ExportFormat::ArrowIpc => {
+                let mapping = mapping.ok_or_else(|| anyhow!("列式导出需要索引映射"))?;
+                // 映射不区分单值和数组，数组字段从第一页推断
+                let schema = schema_from_mapping(
+                    &properties(mapping),
+                    &detect_list_fields(first_page),
+                    selected_fields.as_deref(),
+                );
+                let columnar_format = if matches!(format, ExportFormat::Parquet) {
+                    ColumnarFormat::Parquet
+                } else {
+                    ColumnarFormat::ArrowIpc
+                };
+                HitSink::Columnar(Box::new(ColumnarWriter::create(file_path, columnar_format, schema, DEFAULT_ROW_GROUP_SIZE)?))
+            }
         };
 
-        // 创建表头格式
-        let header_format = Format::new().set_bold();
+        let known_columns = match (&sink, &selected_fields) {
+            (HitSink::Table { headers, .. }, None) => Some(headers.iter().cloned().collect()),
+            _ => None,
+        };
+
+        Ok(Self {
+            sink,
+            selected_fields,
+            records_written: 0,
+            known_columns,
+            unknown_columns: BTreeSet::new(),
+        })
+    }
+
+    // 按断点打开部分文件继续追加，不再写 JSON 的开头和 CSV 表头
+    pub fn resume(file_path: &Path, options: &HitWriterOptions, checkpoint: &ExportCheckpoint) -> Result<Self> {
+        let offset = checkpoint.bytes_written;
+        let open = || -> Result<BufWriter<OutputFile>> { Ok(BufWriter::new(OutputFile::append(file_path, offset)?)) };
+        let sink = match &options.format {
+            ExportFormat::JSON => HitSink::Json { writer: open()?, first: false },
+            ExportFormat::NDJSON => HitSink::NdJson(open()?),
+            ExportFormat::Bulk => HitSink::Bulk { writer: open()?, options: options.bulk_options.clone().unwrap_or_default() },
+            ExportFormat::CSV => {
+                let headers = checkpoint.headers.clone().ok_or_else(|| anyhow!("断点中缺少 CSV 表头"))?;
+                let writer = TableWriter::append(file_path, &ExportFormat::CSV, headers.clone(), offset)?;
+                HitSink::Table { writer, headers, flattener: options.flatten.as_ref().map(Flattener::new) }
+            }
+            _ => return Err(anyhow!("{:?} 格式不支持续传", options.format)),
+        };
+
+        let known_columns = match (&sink, &options.selected_fields) {
+            (HitSink::Table { headers, .. }, None) => Some(headers.iter().cloned().collect()),
+            _ => None,
+        };
+
+        Ok(Self {
+            sink,
+            selected_fields: options.selected_fields.clone(),
+            records_written: checkpoint.records_written,
+            known_columns,
+            unknown_columns: BTreeSet::new(),
+        })
+    }
+
+    pub fn records_written(&self) -> u64 {
+        self.records_written
+    }
+
+    // 表头确定之后才出现、因而没有写入文件的字段
+    pub fn unknown_columns(&self) -> &BTreeSet<String> {
+        &self.unknown_columns
+    }
+
+    // 把缓冲写入磁盘并返回文件中已完整写入的字节数；无法在中途截断续写的格式返回 None
+    pub fn flush_for_checkpoint(&mut self) -> Result<Option<u64>> {
+        match &mut self.sink {
+            HitSink::Json { writer, .. } | HitSink::NdJson(writer) | HitSink::Bulk { writer, .. } => {
+                writer.flush()?;
+                Ok(Some(writer.get_ref().bytes_written()))
+            }
+            HitSink::Table { writer, .. } => writer.flush_for_checkpoint(),
+            HitSink::Spool(_) | HitSink::Columnar(_) => Ok(None),
+        }
+    }
+
+    // CSV/Excel 的表头
+    pub fn headers(&self) -> Option<&[String]> {
+        match &self.sink {
+            HitSink::Table { headers, .. } => Some(headers),
+            _ => None,
+        }
+    }
 
-        // 写入表头
-        for (col, header) in headers.iter().enumerate() {
-            worksheet.write_string_with_format(0, col as u16, header, &header_format)?;
+    // 已写入磁盘的字节数，用于按大小拆分；列式格式在收尾前无法确定，返回 0
+    pub fn bytes_written(&self) -> u64 {
+        match &self.sink {
+            HitSink::Json { writer, .. } | HitSink::NdJson(writer) | HitSink::Bulk { writer, .. } => writer.get_ref().bytes_written(),
+            HitSink::Table { writer, .. } => writer.bytes_written(),
+            HitSink::Spool(_) | HitSink::Columnar(_) => 0,
         }
+    }
+
+    pub fn write_hit(&mut self, hit: &Value) -> Result<()> {
+        match &mut self.sink {
+            HitSink::Json { writer, first } => {
+                writer.write_all(if *first { b"\n" } else { b",\n" })?;
+                *first = false;
+                match &self.selected_fields {
+                    Some(fields) => serde_json::to_writer_pretty(&mut *writer, &filter_fields(hit, fields))?,
+                    None => serde_json::to_writer_pretty(&mut *writer, hit)?,
+                }
+            }
+            HitSink::NdJson(writer) => {
+                serde_json::to_writer(&mut *writer, &selected_source(hit, &self.selected_fields))?;
+                writer.write_all(b"\n")?;
+            }
+            HitSink::Bulk { writer, options } => {
+                serde_json::to_writer(&mut *writer, &bulk_action_line(hit, options))?;
+                writer.write_all(b"\n")?;
+                serde_json::to_writer(&mut *writer, &selected_source(hit, &self.selected_fields))?;
+                writer.write_all(b"\n")?;
+            }
+            HitSink::Table { writer, headers, flattener: Some(flattener) } => {
+                for flattened in flattener.flatten_hit(hit)? {
+                    if let Some(known) = &self.known_columns {
+                        let unknown = flattened.keys().filter(|k| !known.contains(*k) && !is_hit_metadata(k));
+                        self.unknown_columns.extend(unknown.cloned());
+                    }
+                    let row: Vec<Value> = headers.iter().map(|h| flattened.get(h).cloned().unwrap_or(Value::Null)).collect();
+                    writer.write_row(&row)?;
+                }
+            }
+            HitSink::Table { writer, headers, flattener: None } => {
+                if let (Some(known), Some(source)) = (&self.known_columns, hit.get("_source").and_then(Value::as_object)) {
+                    self.unknown_columns.extend(source.keys().filter(|k| !known.contains(*k)).cloned());
+                }
+                let row: Vec<Value> = headers.iter().map(|header| get_nested_field(hit, header)).collect();
+                writer.write_row(&row)?;
+            }
+            HitSink::Spool(spool) => spool.write_hit(hit)?,
+            HitSink::Columnar(writer) => writer.write_hit(hit)?,
+        }
+
+        self.records_written += 1;
+        Ok(())
+    }
 
-        // 写入数据
-        for (row_idx, doc) in data.iter().enumerate() {
-            for (col_idx, header) in headers.iter().enumerate() {
-                let value = self.get_nested_field(doc, header);
-                let cell_value = self.value_to_string(&value);
-                worksheet.write_string((row_idx + 1) as u32, col_idx as u16, &cell_value)?;
+    pub fn finish(self) -> Result<()> {
+        match self.sink {
+            HitSink::Json { mut writer, .. } => {
+                writer.write_all(b"\n]")?;
+                finish_buffered(writer)?;
             }
+            HitSink::NdJson(writer) | HitSink::Bulk { writer, .. } => finish_buffered(writer)?,
+            HitSink::Table { writer, .. } => writer.finish()?,
+            HitSink::Spool(spool) => spool.finish()?,
+            HitSink::Columnar(writer) => writer.finish()?,
         }
+        Ok(())
+    }
+}
 
-        // 自动调整列宽
-        for col in 0..headers.len() {
-            worksheet.set_column_width(col as u16, 15.0)?;
+fn sample_headers(flattener: &Flattener, first_page: &[Value]) -> Result<Vec<String>> {
+    let mut collector = ColumnCollector::default();
+    for hit in first_page {
+        for row in flattener.flatten_hit(hit)? {
+            collector.add_row(&row);
         }
+    }
+    Ok(collector.into_headers())
+}
+
+// 完整扫描表头：展开后的行先写入同目录下的临时文件并收集列，收尾时再按全部列生成表格
+struct SpoolSink {
+    spool: BufWriter<File>,
+    spool_path: PathBuf,
+    file_path: PathBuf,
+    format: ExportFormat,
+    compression: Option<ExportCompression>,
+    date_fields: HashMap<String, EpochUnit>,
+    flattener: Flattener,
+    collector: ColumnCollector,
+}
 
-        workbook.save(file_path)?;
+impl SpoolSink {
+    fn create(
+        file_path: &Path,
+        format: &ExportFormat,
+        compression: Option<ExportCompression>,
+        date_fields: HashMap<String, EpochUnit>,
+        flattener: Flattener,
+    ) -> Result<Self> {
+        let mut spool_name = file_path.as_os_str().to_os_string();
+        spool_name.push(".spool");
+        let spool_path = PathBuf::from(spool_name);
+
+        Ok(Self {
+            spool: BufWriter::new(File::create(&spool_path)?),
+            spool_path,
+            file_path: file_path.to_path_buf(),
+            format: format.clone(),
+            compression,
+            date_fields,
+            flattener,
+            collector: ColumnCollector::default(),
+        })
+    }
+
+    fn write_hit(&mut self, hit: &Value) -> Result<()> {
+        for row in self.flattener.flatten_hit(hit)? {
+            self.collector.add_row(&row);
+            serde_json::to_writer(&mut self.spool, &row)?;
+            self.spool.write_all(b"\n")?;
+        }
         Ok(())
     }
 
-    fn filter_fields(&self, doc: &Value, fields: &[String]) -> Value {
-        let mut filtered = serde_json::Map::new();
-        
-        if let Some(source) = doc.get("_source").and_then(|v| v.as_object()) {
+    fn finish(self) -> Result<()> {
+        self.spool.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+
+        let headers = self.collector.into_headers();
+        let mut writer = TableWriter::create_compressed(&self.file_path, &self.format, headers.clone(), self.compression)?
+            .with_date_columns(&self.date_fields);
+        for line in BufReader::new(File::open(&self.spool_path)?).lines() {
+            let flattened: serde_json::Map<String, Value> = serde_json::from_str(&line?)?;
+            let row: Vec<Value> = headers.iter().map(|h| flattened.get(h).cloned().unwrap_or(Value::Null)).collect();
+            writer.write_row(&row)?;
+        }
+        writer.finish()?;
+
+        std::fs::remove_file(&self.spool_path)?;
+        Ok(())
+    }
+}
+
+fn selected_source(hit: &Value, selected_fields: &Option<Vec<String>>) -> Value {
+    let source = hit.get("_source").cloned().unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+    match selected_fields {
+        Some(fields) => {
+            let mut filtered = serde_json::Map::new();
             for field in fields {
                 if let Some(value) = source.get(field) {
                     filtered.insert(field.clone(), value.clone());
                 }
             }
+            Value::Object(filtered)
+        }
+        None => source,
+    }
+}
+
+fn bulk_action_line(hit: &Value, options: &BulkExportOptions) -> Value {
+    let mut meta = serde_json::Map::new();
+    let index = options.target_index.as_ref().map(|i| Value::from(i.as_str())).or_else(|| hit.get("_index").cloned());
+    if let Some(index) = index {
+        meta.insert("_index".to_string(), index);
+    }
+    if let Some(id) = hit.get("_id") {
+        meta.insert("_id".to_string(), id.clone());
+    }
+    if options.include_routing {
+        if let Some(routing) = hit.get("_routing") {
+            meta.insert("routing".to_string(), routing.clone());
+        }
+    }
+
+    let action = options.action.as_deref().unwrap_or("index");
+    serde_json::json!({ action: meta })
+}
+
+fn filter_fields(doc: &Value, fields: &[String]) -> Value {
+    let mut filtered = serde_json::Map::new();
+    
+    if let Some(source) = doc.get("_source").and_then(|v| v.as_object()) {
+        for field in fields {
+            if let Some(value) = source.get(field) {
+                filtered.insert(field.clone(), value.clone());
+            }
+        }
+    }
+
+    // 保留基础信息
+    if let Some(id) = doc.get("_id") {
+        filtered.insert("_id".to_string(), id.clone());
+    }
+    if let Some(score) = doc.get("_score") {
+        filtered.insert("_score".to_string(), score.clone());
+    }
+
+    Value::Object(filtered)
+}
+
+fn extract_all_fields(data: &[Value]) -> Vec<String> {
+    let mut fields_set = std::collections::HashSet::new();
+    
+    // 遍历第一页的所有文档收集字段名，之后才出现的字段由 HitWriter 记录并提示
+    for doc in data {
+        if let Some(source) = doc.get("_source").and_then(|v| v.as_object()) {
+            for key in source.keys() {
+                fields_set.insert(key.clone());
+            }
+        }
+    }
+
+    let mut fields: Vec<String> = fields_set.into_iter().collect();
+    fields.sort();
+
+    // 添加基础字段
+    let mut result = vec!["_id".to_string(), "_score".to_string()];
+    result.extend(fields);
+    result
+}
+
+fn get_nested_field(doc: &Value, field_path: &str) -> Value {
+    // 处理嵌套字段，如 "user.name"
+    let parts: Vec<&str> = field_path.split('.').collect();
+    let mut current = doc;
+
+    // 首先尝试从 _source 中获取
+    if let Some(source) = doc.get("_source") {
+        current = source;
+    }
+
+    // 如果是基础字段，直接从根级别获取
+    if parts.len() == 1 && (field_path == "_id" || field_path == "_score" || field_path == "_index") {
+        return doc.get(field_path).unwrap_or(&Value::Null).clone();
+    }
+
+    // 遍历嵌套路径
+    for part in parts {
+        match current.get(part) {
+            Some(value) => current = value,
+            None => return Value::Null,
         }
+    }
+
+    current.clone()
+}
+
+// 按列名和行数据逐行写出表格结果（SQL、ES|QL 等），不在内存中保留已写出的行
+pub struct TableWriter {
+    sink: TableSink,
+    columns: Vec<String>,
+    rows_written: u64,
+}
+
+enum TableSink {
+    Json { writer: BufWriter<OutputFile>, first: bool },
+    NdJson(BufWriter<OutputFile>),
+    Csv(Box<csv::Writer<OutputFile>>),
+    Excel(Box<ExcelSink>),
+}
+
+impl TableWriter {
+    pub fn create(file_path: &Path, format: &ExportFormat, columns: Vec<String>) -> Result<Self> {
+        Self::create_compressed(file_path, format, columns, None)
+    }
+
+    // Excel 自身已是压缩格式，忽略 compression
+    pub fn create_compressed(
+        file_path: &Path,
+        format: &ExportFormat,
+        columns: Vec<String>,
+        compression: Option<ExportCompression>,
+    ) -> Result<Self> {
+        let sink = match format {
+            ExportFormat::JSON => {
+                let mut writer = BufWriter::new(OutputFile::create(file_path, compression)?);
+                writer.write_all(b"[")?;
+                TableSink::Json { writer, first: true }
+            }
+            ExportFormat::NDJSON => TableSink::NdJson(BufWriter::new(OutputFile::create(file_path, compression)?)),
+            ExportFormat::Bulk => return Err(anyhow!("Bulk 格式需要 _index 和 _id，仅支持导出搜索结果")),
+            ExportFormat::Parquet | ExportFormat::ArrowIpc => return Err(anyhow!("列式格式依赖索引映射，仅支持导出搜索结果")),
+            ExportFormat::CSV => {
+                let mut writer = WriterBuilder::new().from_writer(OutputFile::create(file_path, compression)?);
+                writer.write_record(&columns)?;
+                TableSink::Csv(Box::new(writer))
+            }
+            ExportFormat::Excel => TableSink::Excel(Box::new(ExcelSink::create(file_path, columns.clone())?)),
+        };
+
+        Ok(Self { sink, columns, rows_written: 0 })
+    }
 
-        // 保留基础信息
-        if let Some(id) = doc.get("_id") {
-            filtered.insert("_id".to_string(), id.clone());
+    // 续传时截断部分文件并继续追加行，仅支持未压缩的文本格式
+    pub fn append(file_path: &Path, format: &ExportFormat, columns: Vec<String>, offset: u64) -> Result<Self> {
+        let output = OutputFile::append(file_path, offset)?;
+        let sink = match format {
+            ExportFormat::JSON => TableSink::Json { writer: BufWriter::new(output), first: false },
+            ExportFormat::NDJSON => TableSink::NdJson(BufWriter::new(output)),
+            ExportFormat::CSV => TableSink::Csv(Box::new(WriterBuilder::new().from_writer(output))),
+            _ => return Err(anyhow!("{:?} 格式不支持续传", format)),
+        };
+
+        Ok(Self { sink, columns, rows_written: 0 })
+    }
+
+    // 标记按日期写出的列（目前只有 Excel 使用），列名与映射中的字段路径对应
+    pub fn with_date_columns(mut self, date_fields: &HashMap<String, EpochUnit>) -> Self {
+        if let TableSink::Excel(sink) = &mut self.sink {
+            sink.date_columns = self.columns.iter().map(|c| date_fields.get(c).copied()).collect();
         }
-        if let Some(score) = doc.get("_score") {
-            filtered.insert("_score".to_string(), score.clone());
+        self
+    }
+
+    pub fn rows_written(&self) -> u64 {
+        self.rows_written
+    }
+
+    // Excel 在保存时才写出文件，返回 0
+    pub fn bytes_written(&self) -> u64 {
+        match &self.sink {
+            TableSink::Json { writer, .. } | TableSink::NdJson(writer) => writer.get_ref().bytes_written(),
+            TableSink::Csv(writer) => writer.get_ref().bytes_written(),
+            TableSink::Excel(_) => 0,
         }
+    }
 
-        Value::Object(filtered)
+    // 把缓冲写入磁盘并返回已完整写入的字节数，Excel 返回 None
+    pub fn flush_for_checkpoint(&mut self) -> Result<Option<u64>> {
+        match &mut self.sink {
+            TableSink::Json { writer, .. } | TableSink::NdJson(writer) => {
+                writer.flush()?;
+                Ok(Some(writer.get_ref().bytes_written()))
+            }
+            TableSink::Csv(writer) => {
+                writer.flush()?;
+                Ok(Some(writer.get_ref().bytes_written()))
+            }
+            TableSink::Excel(_) => Ok(None),
+        }
     }
 
-    fn extract_all_fields(&self, data: &[Value]) -> Vec<String> {
-        let mut fields_set = std::collections::HashSet::new();
-        
-        // 遍历所有文档收集字段名
-        for doc in data.iter().take(10) { // 只检查前10个文档以提高性能
-            if let Some(source) = doc.get("_source").and_then(|v| v.as_object()) {
-                for key in source.keys() {
-                    fields_set.insert(key.clone());
+    pub fn write_row(&mut self, row: &[Value]) -> Result<()> {
+        match &mut self.sink {
+            TableSink::Json { writer, first } => {
+                if !*first {
+                    writer.write_all(b",")?;
                 }
+                *first = false;
+                serde_json::to_writer(&mut *writer, &row_to_object(&self.columns, row))?;
             }
+            TableSink::NdJson(writer) => {
+                serde_json::to_writer(&mut *writer, &row_to_object(&self.columns, row))?;
+                writer.write_all(b"\n")?;
+            }
+            TableSink::Csv(writer) => {
+                let record: Vec<String> = row.iter().map(value_to_string).collect();
+                writer.write_record(&record)?;
+            }
+            TableSink::Excel(sink) => sink.write_row(row)?,
         }
 
-        let mut fields: Vec<String> = fields_set.into_iter().collect();
-        fields.sort();
+        self.rows_written += 1;
+        Ok(())
+    }
 
-        // 添加基础字段
-        let mut result = vec!["_id".to_string(), "_score".to_string()];
-        result.extend(fields);
-        result
+    pub fn finish(self) -> Result<()> {
+        match self.sink {
+            TableSink::Json { mut writer, .. } => {
+                writer.write_all(b"]")?;
+                finish_buffered(writer)?;
+            }
+            TableSink::NdJson(writer) => finish_buffered(writer)?,
+            TableSink::Csv(writer) => writer.into_inner().map_err(|e| anyhow!("{}", e.error()))?.finish()?,
+            TableSink::Excel(sink) => sink.finish()?,
+        }
+        Ok(())
     }
+}
+
+// Excel 单个工作表的最大行数（含表头）
+const EXCEL_MAX_ROWS: u32 = 1_048_576;
+
+// 自动列宽的上下限，单位为字符
+const EXCEL_MIN_COLUMN_WIDTH: f64 = 8.0;
+const EXCEL_MAX_COLUMN_WIDTH: f64 = 60.0;
+
+// "yyyy-mm-dd hh:mm:ss" 的显示宽度
+const EXCEL_DATE_WIDTH: f64 = 19.0;
+
+// Excel 数字是双精度浮点，绝对值超过 2^53 的整数按文本写出以免丢失精度
+const EXCEL_MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+// 1970-01-01 对应的 Excel 日期序号
+const EXCEL_UNIX_EPOCH_DAYS: f64 = 25569.0;
 
-    fn get_nested_field(&self, doc: &Value, field_path: &str) -> Value {
-        // 处理嵌套字段，如 "user.name"
-        let parts: Vec<&str> = field_path.split('.').collect();
-        let mut current = doc;
+// 以常量内存模式逐行写入 xlsx：数字、布尔值写成原生类型，映射中的日期字段写成 UTC 日期时间。
+// 表头冻结并带自动筛选，列宽按内容估算；超过行数上限时续写到新的工作表
+struct ExcelSink {
+    workbook: Workbook,
+    path: PathBuf,
+    columns: Vec<String>,
+    date_columns: Vec<Option<EpochUnit>>, // 日期列及其数值单位
+    widths: Vec<f64>,
+    sheet_index: usize,
+    sheet_rows: u32, // 当前工作表已写入的数据行数
+    max_rows: u32,
+    header_format: Format,
+    date_format: Format,
+}
 
-        // 首先尝试从 _source 中获取
-        if let Some(source) = doc.get("_source") {
-            current = source;
+impl ExcelSink {
+    fn create(file_path: &Path, columns: Vec<String>) -> Result<Self> {
+        let mut sink = Self {
+            workbook: Workbook::new(),
+            path: file_path.to_path_buf(),
+            date_columns: vec![None; columns.len()],
+            columns,
+            widths: Vec::new(),
+            sheet_index: 0,
+            sheet_rows: 0,
+            max_rows: EXCEL_MAX_ROWS,
+            header_format: Format::new().set_bold(),
+            date_format: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+        };
+        sink.add_sheet()?;
+        Ok(sink)
+    }
+
+    fn add_sheet(&mut self) -> Result<()> {
+        let worksheet = self.workbook.add_worksheet_with_constant_memory();
+        for (col, header) in self.columns.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, header, &self.header_format)?;
         }
+        worksheet.set_freeze_panes(1, 0)?;
+
+        // 表头单元格多出筛选按钮的宽度
+        self.widths = self.columns.iter().map(|header| text_width(header) + 2.0).collect();
+        self.sheet_rows = 0;
+        Ok(())
+    }
+
+    // 列宽和筛选范围要等工作表写完才能确定
+    fn close_sheet(&mut self) -> Result<()> {
+        let worksheet = self.workbook.worksheet_from_index(self.sheet_index)?;
+        for (col, width) in self.widths.iter().enumerate() {
+            worksheet.set_column_width(col as u16, width.clamp(EXCEL_MIN_COLUMN_WIDTH, EXCEL_MAX_COLUMN_WIDTH))?;
+        }
+        if !self.columns.is_empty() {
+            worksheet.autofilter(0, 0, self.sheet_rows, (self.columns.len() - 1) as u16)?;
+        }
+        Ok(())
+    }
 
-        // 如果是基础字段，直接从根级别获取
-        if parts.len() == 1 && (field_path == "_id" || field_path == "_score" || field_path == "_index") {
-            return doc.get(field_path).unwrap_or(&Value::Null).clone();
+    fn write_row(&mut self, row: &[Value]) -> Result<()> {
+        if self.sheet_rows + 1 >= self.max_rows {
+            self.close_sheet()?;
+            self.sheet_index += 1;
+            self.add_sheet()?;
         }
 
-        // 遍历嵌套路径
-        for part in parts {
-            match current.get(part) {
-                Some(value) => current = value,
-                None => return Value::Null,
+        let excel_row = self.sheet_rows + 1;
+        let worksheet = self.workbook.worksheet_from_index(self.sheet_index)?;
+        for (col, value) in row.iter().enumerate() {
+            let date_unit = self.date_columns.get(col).copied().flatten();
+            let width = write_excel_cell(worksheet, excel_row, col as u16, value, date_unit, &self.date_format)?;
+            if let Some(max_width) = self.widths.get_mut(col) {
+                *max_width = max_width.max(width);
             }
         }
 
-        current.clone()
+        self.sheet_rows += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.close_sheet()?;
+        self.workbook.save(&self.path)?;
+        Ok(())
+    }
+}
+
+// 写入一个单元格并返回其显示宽度；日期列中无法解析的值按原样写出
+fn write_excel_cell(
+    worksheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    value: &Value,
+    date_unit: Option<EpochUnit>,
+    date_format: &Format,
+) -> Result<f64> {
+    if let Some(serial) = date_unit.and_then(|unit| excel_date(value, unit)) {
+        worksheet.write_number_with_format(row, col, serial, date_format)?;
+        return Ok(EXCEL_DATE_WIDTH);
+    }
+
+    match value {
+        Value::Null => Ok(0.0),
+        Value::Bool(b) => {
+            worksheet.write_boolean(row, col, *b)?;
+            Ok(5.0)
+        }
+        Value::Number(n) => {
+            let text = n.to_string();
+            match excel_number(n) {
+                Some(number) => worksheet.write_number(row, col, number)?,
+                None => worksheet.write_string(row, col, &text)?,
+            };
+            Ok(text.len() as f64)
+        }
+        other => {
+            let text = value_to_string(other);
+            worksheet.write_string(row, col, &text)?;
+            Ok(text_width(&text))
+        }
+    }
+}
+
+fn excel_number(n: &serde_json::Number) -> Option<f64> {
+    if let Some(i) = n.as_i64() {
+        return (i.unsigned_abs() <= EXCEL_MAX_SAFE_INTEGER).then_some(i as f64);
+    }
+    if let Some(u) = n.as_u64() {
+        return (u <= EXCEL_MAX_SAFE_INTEGER).then_some(u as f64);
+    }
+    n.as_f64()
+}
+
+// 毫秒时间戳转换成 Excel 日期序号（1900 日期系统）
+// 按映射的 epoch 单位解析日期值，无法解析时返回 None
+fn excel_date(value: &Value, unit: EpochUnit) -> Option<f64> {
+    parse_es_date(value, unit).map(|date| excel_serial_date(date.timestamp_millis()))
+}
+
+fn excel_serial_date(millis: i64) -> f64 {
+    EXCEL_UNIX_EPOCH_DAYS + millis as f64 / 86_400_000.0
+}
+
+// 估算显示宽度：非 ASCII 字符（主要是中日韩文字）按两个字符宽计算，多行文本取最长一行
+fn text_width(text: &str) -> f64 {
+    text.lines()
+        .map(|line| line.chars().map(|c| if c.is_ascii() { 1.0 } else { 2.0 }).sum::<f64>())
+        .fold(0.0, f64::max)
+}
+
+fn row_to_object(columns: &[String], row: &[Value]) -> Value {
+    let object: serde_json::Map<String, Value> = columns
+        .iter()
+        .cloned()
+        .zip(row.iter().cloned())
+        .collect();
+    Value::Object(object)
+}
+
+pub(crate) fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Array(_) | Value::Object(_) => {
+            // 对于复杂对象，转换为JSON字符串
+            serde_json::to_string(value).unwrap_or_else(|_| "[Complex Object]".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("es_client_{}_{}", std::process::id(), name))
     }
 
-    fn value_to_string(&self, value: &Value) -> String {
-        match value {
-            Value::Null => String::new(),
-            Value::Bool(b) => b.to_string(),
-            Value::Number(n) => n.to_string(),
-            Value::String(s) => s.clone(),
-            Value::Array(_) | Value::Object(_) => {
-                // 对于复杂对象，转换为JSON字符串
-                serde_json::to_string(value).unwrap_or_else(|_| "[Complex Object]".to_string())
+    fn hit(id: &str, source: Value) -> Value {
+        serde_json::json!({ "_index": "logs", "_id": id, "_score": 1.0, "_source": source })
+    }
+
+    #[test]
+    fn test_hit_writer_streams_rows() {
+        let hits = [
+            hit("1", serde_json::json!({ "host": "web01", "user": { "name": "alice" } })),
+            hit("2", serde_json::json!({ "host": "web02" })),
+        ];
+
+        let csv_path = temp_file("hits.csv");
+        let mut writer = HitWriter::create(
+            &csv_path,
+            &HitWriterOptions {
+                selected_fields: Some(vec!["_id".to_string(), "user.name".to_string()]),
+                ..HitWriterOptions::new(ExportFormat::CSV)
+            },
+            None,
+            &hits,
+        )
+        .unwrap();
+        for h in &hits {
+            writer.write_hit(h).unwrap();
+        }
+        assert_eq!(writer.records_written(), 2);
+        writer.finish().unwrap();
+        assert_eq!(std::fs::read_to_string(&csv_path).unwrap(), "_id,user.name\n1,alice\n2,\n");
+
+        let json_path = temp_file("hits.json");
+        let mut writer = HitWriter::create(&json_path, &HitWriterOptions::new(ExportFormat::JSON), None, &hits).unwrap();
+        for h in &hits {
+            writer.write_hit(h).unwrap();
+        }
+        writer.finish().unwrap();
+        let parsed: Value = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 2);
+        assert_eq!(parsed[1]["_source"]["host"], "web02");
+
+        std::fs::remove_file(csv_path).ok();
+        std::fs::remove_file(json_path).ok();
+    }
+
+    #[test]
+    fn test_hit_writer_resume_from_checkpoint() {
+        let hits: Vec<Value> = (1..=3).map(|i| hit(&i.to_string(), serde_json::json!({ "n": i }))).collect();
+        let request: ExportRequest = serde_json::from_value(serde_json::json!({
+            "connection_id": "local",
+            "query": { "index": "logs", "query": { "match_all": {} }, "sort": [{ "@timestamp": "asc" }, "event_id"] },
+            "format": "CSV",
+            "filename": "hits",
+            "selected_fields": ["_id", "n"],
+            "max_records": null
+        }))
+        .unwrap();
+        assert!(is_resumable(&request));
+        assert!(!is_resumable(&ExportRequest { compression: Some(ExportCompression::Gzip), ..request.clone() }));
+        // 未指定排序时按 _shard_doc 分页，断点无法在新的 PIT 中使用
+        let mut unsorted = request.clone();
+        unsorted.query.sort = None;
+        assert!(!is_resumable(&unsorted));
+        unsorted.query.sort = Some(vec![serde_json::json!({ "_score": "desc" }), serde_json::json!("event_id")]);
+        assert!(!is_resumable(&unsorted));
+
+        for format in [ExportFormat::CSV, ExportFormat::JSON] {
+            let path = temp_file(&format!("resume.{}", format.extension()));
+            let options = HitWriterOptions { format: format.clone(), ..HitWriterOptions::from(&request) };
+
+            // 写完第一条后记录断点，第二条写入后中断（未收尾）
+            let mut writer = HitWriter::create(&path, &options, None, &hits).unwrap();
+            writer.write_hit(&hits[0]).unwrap();
+            let bytes_written = writer.flush_for_checkpoint().unwrap().unwrap();
+            let headers = writer.headers().map(|h| h.to_vec());
+            writer.write_hit(&hits[1]).unwrap();
+            drop(writer);
+
+            let checkpoint = ExportCheckpoint {
+                request: request.clone(),
+                file_path: path.to_string_lossy().to_string(),
+                records_written: 1,
+                bytes_written,
+                search_after: serde_json::json!([1]),
+                headers,
+                updated_at: String::new(),
+            };
+            let mut writer = HitWriter::resume(&path, &options, &checkpoint).unwrap();
+            for h in &hits[1..] {
+                writer.write_hit(h).unwrap();
             }
+            assert_eq!(writer.records_written(), 3);
+            writer.finish().unwrap();
+
+            let content = std::fs::read_to_string(&path).unwrap();
+            match format {
+                ExportFormat::CSV => assert_eq!(content, "_id,n\n1,1\n2,2\n3,3\n"),
+                _ => {
+                    let parsed: Value = serde_json::from_str(&content).unwrap();
+                    let ids: Vec<&str> = parsed.as_array().unwrap().iter().map(|h| h["_id"].as_str().unwrap()).collect();
+                    assert_eq!(ids, ["1", "2", "3"]);
+                }
+            }
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    #[test]
+    fn test_excel_sheet_rollover_and_cell_types() {
+        let columns = vec!["@timestamp".to_string(), "bytes".to_string(), "ok".to_string(), "message".to_string()];
+        let excel_path = temp_file("typed.xlsx");
+        let mut writer = TableWriter::create(&excel_path, &ExportFormat::Excel, columns)
+            .unwrap()
+            .with_date_columns(&HashMap::from([("@timestamp".to_string(), EpochUnit::Seconds)]));
+        if let TableSink::Excel(sink) = &mut writer.sink {
+            assert_eq!(sink.date_columns, vec![Some(EpochUnit::Seconds), None, None, None]);
+            sink.max_rows = 3;
         }
+
+        for i in 0..5 {
+            let row = vec![
+                Value::from("2024-01-01T12:00:00Z"),
+                Value::from(i * 1024),
+                Value::from(i % 2 == 0),
+                Value::from("请求处理完成"),
+            ];
+            writer.write_row(&row).unwrap();
+        }
+
+        // 每个工作表除表头外只能放 2 行，5 行需要 3 个工作表
+        if let TableSink::Excel(sink) = &writer.sink {
+            assert_eq!(sink.sheet_index, 2);
+            assert_eq!(sink.sheet_rows, 1);
+            assert_eq!(sink.widths[3], 12.0);
+        }
+        writer.finish().unwrap();
+        assert!(std::fs::metadata(&excel_path).unwrap().len() > 0);
+
+        assert_eq!(excel_serial_date(1704110400000), 45292.5);
+        assert_eq!(excel_date(&Value::from(1704110400), EpochUnit::Seconds), Some(45292.5));
+        assert_eq!(excel_date(&Value::from(1704110400000i64), EpochUnit::Millis), Some(45292.5));
+        assert_eq!(excel_number(&serde_json::Number::from(42)), Some(42.0));
+        assert_eq!(excel_number(&serde_json::Number::from(u64::MAX)), None);
+        assert_eq!(text_width("ab\n中文字"), 6.0);
+
+        std::fs::remove_file(excel_path).ok();
+    }
+
+    #[test]
+    fn test_resolve_output_path() {
+        let dir = temp_file("exports");
+        let service = ExportService::with_default_directory(Some(dir.to_string_lossy().to_string()));
+
+        // 文件名中的 ../ 不能逃出导出目录，扩展名与格式一致
+        let path = service.resolve_output_path(&ExportTarget::default(), "../secrets.json", "csv").unwrap();
+        assert_eq!(path, dir.join(".._secrets.csv"));
+
+        let target = ExportTarget {
+            file_path: Some(dir.join("nested").join("report").to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert_eq!(service.resolve_output_path(&target, "ignored", "xlsx").unwrap(), dir.join("nested").join("report.xlsx"));
+
+        let relative = ExportTarget { directory: Some("exports".to_string()), ..Default::default() };
+        assert!(service.resolve_output_path(&relative, "logs", "csv").is_err());
+
+        std::fs::write(dir.join("logs.csv"), "").unwrap();
+        assert_eq!(service.output_path(&ExportTarget::default(), "logs", "csv").unwrap(), dir.join("logs (1).csv"));
+        let overwrite = ExportTarget { overwrite: true, ..Default::default() };
+        assert_eq!(service.output_path(&overwrite, "logs", "csv").unwrap(), dir.join("logs.csv"));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_hit_writer_flattened_csv() {
+        let hits = [
+            hit("1", serde_json::json!({ "host": "web01", "tags": ["a", "b"] })),
+            hit("2", serde_json::json!({ "host": "web02", "user": { "name": "bob" } })),
+        ];
+
+        // 第二条命中才出现的 user.name 在完整扫描时也会成为列
+        let options = HitWriterOptions {
+            flatten: Some(FlattenOptions {
+                arrays: crate::types::ArrayFlattenMode::Explode,
+                headers: HeaderDiscovery::FullScan,
+                explode_path: Some("tags".to_string()),
+                ..Default::default()
+            }),
+            ..HitWriterOptions::new(ExportFormat::CSV)
+        };
+        let csv_path = temp_file("flattened.csv");
+        let mut writer = HitWriter::create(&csv_path, &options, None, &hits[..1]).unwrap();
+        for h in &hits {
+            writer.write_hit(h).unwrap();
+        }
+        assert_eq!(writer.records_written(), 2);
+        writer.finish().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&csv_path).unwrap(),
+            "_id,_score,host,tags,user.name\n1,1.0,web01,a,\n1,1.0,web01,b,\n2,1.0,web02,,bob\n"
+        );
+        let mut spool_name = csv_path.as_os_str().to_os_string();
+        spool_name.push(".spool");
+        assert!(!PathBuf::from(spool_name).exists());
+
+        std::fs::remove_file(csv_path).ok();
+    }
+
+    #[test]
+    fn test_hit_writer_without_hits() {
+        let csv_path = temp_file("empty.csv");
+        HitWriter::create(&csv_path, &HitWriterOptions::new(ExportFormat::CSV), None, &[]).unwrap().finish().unwrap();
+        assert_eq!(std::fs::read_to_string(&csv_path).unwrap(), "_id,_score\n");
+
+        let json_path = temp_file("empty.json");
+        HitWriter::create(&json_path, &HitWriterOptions::new(ExportFormat::JSON), None, &[]).unwrap().finish().unwrap();
+        let parsed: Value = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(parsed, serde_json::json!([]));
+
+        std::fs::remove_file(csv_path).ok();
+        std::fs::remove_file(json_path).ok();
+    }
+
+    #[test]
+    fn test_hit_writer_reports_unknown_columns() {
+        let hits = [
+            hit("1", serde_json::json!({ "host": "web01" })),
+            hit("2", serde_json::json!({ "host": "web02", "user": { "name": "bob" } })),
+        ];
+
+        let csv_path = temp_file("unknown.csv");
+        let mut writer = HitWriter::create(&csv_path, &HitWriterOptions::new(ExportFormat::CSV), None, &hits[..1]).unwrap();
+        for h in &hits {
+            writer.write_hit(h).unwrap();
+        }
+        assert_eq!(writer.unknown_columns().iter().collect::<Vec<_>>(), ["user"]);
+        writer.finish().unwrap();
+        std::fs::remove_file(csv_path).ok();
+    }
+
+    #[test]
+    fn test_hit_writer_ndjson_output() {
+        let hits = [
+            hit("1", serde_json::json!({ "host": "web01", "user": { "name": "alice" } })),
+            hit("2", serde_json::json!({ "host": "web02" })),
+        ];
+
+        let ndjson_path = temp_file("hits.ndjson");
+        let mut writer = HitWriter::create(&ndjson_path, &HitWriterOptions::new(ExportFormat::NDJSON), None, &hits).unwrap();
+        for h in &hits {
+            writer.write_hit(h).unwrap();
+        }
+        writer.finish().unwrap();
+
+        // 每行只有 _source，不带 _index、_id 等元字段
+        let content = std::fs::read_to_string(&ndjson_path).unwrap();
+        let lines: Vec<Value> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines, [hits[0]["_source"].clone(), hits[1]["_source"].clone()]);
+
+        std::fs::remove_file(ndjson_path).ok();
+    }
+
+    #[test]
+    fn test_hit_writer_bulk_output() {
+        let mut routed = hit("1", serde_json::json!({ "host": "web01" }));
+        routed["_routing"] = Value::from("tenant-a");
+        let hits = [routed, hit("2", serde_json::json!({ "host": "web02" }))];
+
+        let options = BulkExportOptions {
+            target_index: Some("logs-restored".to_string()),
+            include_routing: true,
+            action: Some("create".to_string()),
+        };
+        let bulk_path = temp_file("hits.bulk");
+        let mut writer = HitWriter::create(
+            &bulk_path,
+            &HitWriterOptions { bulk_options: Some(options), ..HitWriterOptions::new(ExportFormat::Bulk) },
+            None,
+            &hits,
+        )
+        .unwrap();
+        for h in &hits {
+            writer.write_hit(h).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let content = std::fs::read_to_string(&bulk_path).unwrap();
+        let lines: Vec<Value> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], serde_json::json!({ "create": { "_index": "logs-restored", "_id": "1", "routing": "tenant-a" } }));
+        assert_eq!(lines[1], serde_json::json!({ "host": "web01" }));
+        assert!(lines[2]["create"].get("routing").is_none());
+
+        std::fs::remove_file(bulk_path).ok();
+    }
+
+    #[test]
+    fn test_table_writer_json_and_ndjson() {
+        let columns = vec!["host".to_string(), "count".to_string()];
+        let rows = [
+            vec![Value::from("web01"), Value::from(3)],
+            vec![Value::from("web02"), Value::Null],
+        ];
+
+        let json_path = temp_file("table.json");
+        let mut writer = TableWriter::create(&json_path, &ExportFormat::JSON, columns.clone()).unwrap();
+        for row in &rows {
+            writer.write_row(row).unwrap();
+        }
+        assert_eq!(writer.rows_written(), 2);
+        writer.finish().unwrap();
+
+        let parsed: Value = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(parsed[1]["host"], "web02");
+        assert!(parsed[1]["count"].is_null());
+
+        let ndjson_path = temp_file("table.ndjson");
+        let mut writer = TableWriter::create(&ndjson_path, &ExportFormat::NDJSON, columns).unwrap();
+        for row in &rows {
+            writer.write_row(row).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let content = std::fs::read_to_string(&ndjson_path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(serde_json::from_str::<Value>(lines[0]).unwrap()["count"], 3);
+
+        let _ = std::fs::remove_file(json_path);
+        let _ = std::fs::remove_file(ndjson_path);
     }
 }
\ No newline at end of file
@@ -0,0 +1,342 @@
+// CSV/Excel 导出时把嵌套对象和数组展开成列
+use crate::export::value_to_string;
+use crate::types::{ArrayFlattenMode, ExportFormat, ExportSplitOptions, FlattenOptions, HeaderDiscovery};
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+pub const DEFAULT_JOIN_DELIMITER: &str = "; ";
+
+// 单个命中最多展开的行数，超出时报错而不是占满内存
+pub const MAX_EXPLODED_ROWS: usize = 10_000;
+
+// 与未展开时的默认表头一致，元数据列排在最前
+const META_COLUMNS: &[&str] = &["_id", "_score"];
+
+// 每行都会带上的命中元数据，选中字段时可以引用
+const HIT_METADATA: &[&str] = &["_index", "_id", "_score", "_routing"];
+
+pub fn validate_flatten_options(
+    format: &ExportFormat,
+    options: &FlattenOptions,
+    split: Option<&ExportSplitOptions>,
+) -> Result<()> {
+    if !matches!(format, ExportFormat::CSV | ExportFormat::Excel) {
+        return Err(anyhow!("展开选项仅适用于 CSV 和 Excel 格式"));
+    }
+    if options.arrays == ArrayFlattenMode::Explode && options.explode_path.as_deref().is_none_or(|p| p.trim().is_empty()) {
+        return Err(anyhow!("逐行展开数组时需要指定要展开的数组路径 explode_path"));
+    }
+    // 映射不记录数组长度，无法确定下标列
+    if options.headers == HeaderDiscovery::Mapping && options.arrays == ArrayFlattenMode::Index {
+        return Err(anyhow!("按下标展开数组时无法从映射确定表头，请使用完整扫描"));
+    }
+    // 完整扫描在分片结束时才生成表格，写入过程中无法得知文件大小
+    if options.headers == HeaderDiscovery::FullScan && split.is_some_and(|s| s.max_bytes.is_some()) {
+        return Err(anyhow!("完整扫描表头时不支持按字节数拆分"));
+    }
+    Ok(())
+}
+
+pub struct Flattener {
+    arrays: ArrayFlattenMode,
+    delimiter: String,
+    explode_path: Option<String>,
+}
+
+impl Flattener {
+    pub fn new(options: &FlattenOptions) -> Self {
+        Self {
+            arrays: options.arrays,
+            delimiter: options.delimiter.clone().unwrap_or_else(|| DEFAULT_JOIN_DELIMITER.to_string()),
+            explode_path: options.explode_path.as_ref().map(|p| p.trim().to_string()),
+        }
+    }
+
+    /// 把一个命中展开成 路径 -> 值 的行；Explode 模式下只有 explode_path 处的数组
+    /// 每个元素一行，空数组不产生额外的行，行数超过 MAX_EXPLODED_ROWS 时报错
+    pub fn flatten_hit(&self, hit: &Value) -> Result<Vec<Map<String, Value>>> {
+        let empty = Value::Object(Map::new());
+        let mut rows = self.flatten_value(hit.get("_source").unwrap_or(&empty), "")?;
+
+        for row in &mut rows {
+            for meta in HIT_METADATA {
+                if let Some(value) = hit.get(*meta) {
+                    row.insert(meta.to_string(), value.clone());
+                }
+            }
+        }
+
+        Ok(rows)
+    }
+
+    fn flatten_value(&self, value: &Value, path: &str) -> Result<Vec<Map<String, Value>>> {
+        let rows = match value {
+            Value::Object(object) => {
+                let mut rows = vec![Map::new()];
+                for (key, child) in object {
+                    rows = cross_join(rows, self.flatten_value(child, &join_path(path, key))?);
+                }
+                rows
+            }
+            Value::Array(items) => match self.arrays {
+                // 对象数组按叶子路径拆开，与由映射生成的表头一致
+                ArrayFlattenMode::Json if items.iter().any(Value::is_object) => vec![self.merge_items(items, path)?],
+                ArrayFlattenMode::Json => vec![single(path, value.clone())],
+                ArrayFlattenMode::Index => {
+                    let mut rows = vec![Map::new()];
+                    for (i, item) in items.iter().enumerate() {
+                        rows = cross_join(rows, self.flatten_value(item, &join_path(path, &i.to_string()))?);
+                    }
+                    rows
+                }
+                ArrayFlattenMode::Explode if self.explode_path.as_deref() == Some(path) => {
+                    let mut rows = Vec::new();
+                    for item in items {
+                        rows.extend(self.flatten_value(item, path)?);
+                    }
+                    if rows.is_empty() {
+                        rows.push(Map::new());
+                    }
+                    rows
+                }
+                ArrayFlattenMode::Explode | ArrayFlattenMode::Join => vec![self.merge_items(items, path)?],
+            },
+            _ => vec![single(path, value.clone())],
+        };
+
+        if rows.len() > MAX_EXPLODED_ROWS {
+            return Err(anyhow!("字段 {} 展开后超过 {} 行，请改用其他数组展开方式", path, MAX_EXPLODED_ROWS));
+        }
+        Ok(rows)
+    }
+
+    // 对象数组按叶子路径分别合并，如 [{code: a}, {code: b}] -> events.code = "a; b"（Json 模式为 ["a", "b"]）
+    fn merge_items(&self, items: &[Value], path: &str) -> Result<Map<String, Value>> {
+        let mut parts: Vec<(String, Vec<Value>)> = Vec::new();
+
+        for item in items {
+            for (key, value) in self.flatten_value(item, path)?.into_iter().flatten() {
+                if value.is_null() {
+                    continue;
+                }
+                match parts.iter_mut().find(|(existing, _)| *existing == key) {
+                    Some((_, values)) => values.push(value),
+                    None => parts.push((key, vec![value])),
+                }
+            }
+        }
+
+        Ok(parts.into_iter().map(|(key, values)| (key, self.combine(values))).collect())
+    }
+
+    fn combine(&self, values: Vec<Value>) -> Value {
+        match self.arrays {
+            ArrayFlattenMode::Json => Value::Array(values),
+            _ => Value::String(values.iter().map(value_to_string).collect::<Vec<_>>().join(&self.delimiter)),
+        }
+    }
+}
+
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn single(path: &str, value: Value) -> Map<String, Value> {
+    let mut row = Map::new();
+    row.insert(path.to_string(), value);
+    row
+}
+
+fn cross_join(left: Vec<Map<String, Value>>, right: Vec<Map<String, Value>>) -> Vec<Map<String, Value>> {
+    let mut rows = Vec::with_capacity(left.len() * right.len());
+    for l in &left {
+        for r in &right {
+            let mut row = l.clone();
+            row.extend(r.iter().map(|(k, v)| (k.clone(), v.clone())));
+            rows.push(row);
+        }
+    }
+    rows
+}
+
+/// 命中元数据列不计入未知字段
+pub fn is_hit_metadata(column: &str) -> bool {
+    HIT_METADATA.contains(&column)
+}
+
+/// 收集展开后出现过的列，最终按 `_id`、`_score` 在前，其余按路径排序
+#[derive(Default)]
+pub struct ColumnCollector {
+    columns: HashSet<String>,
+}
+
+impl ColumnCollector {
+    pub fn add_row(&mut self, row: &Map<String, Value>) {
+        for key in row.keys() {
+            if !self.columns.contains(key) {
+                self.columns.insert(key.clone());
+            }
+        }
+    }
+
+    pub fn into_headers(self) -> Vec<String> {
+        ordered_headers(self.columns)
+    }
+}
+
+/// 由映射生成表头：对象和 nested 字段都展开到叶子，与 Flattener 写出的列一致
+pub fn mapping_columns(properties: &Map<String, Value>) -> Vec<String> {
+    let mut columns = HashSet::new();
+    collect_mapping_columns(properties, "", &mut columns);
+    ordered_headers(columns)
+}
+
+fn collect_mapping_columns(properties: &Map<String, Value>, prefix: &str, columns: &mut HashSet<String>) {
+    for (name, def) in properties {
+        let path = join_path(prefix, name);
+
+        match def.get("properties") {
+            Some(Value::Object(children)) => {
+                collect_mapping_columns(children, &path, columns);
+            }
+            _ => {
+                columns.insert(path);
+            }
+        }
+    }
+}
+
+fn ordered_headers(columns: HashSet<String>) -> Vec<String> {
+    let mut fields: Vec<String> = columns
+        .into_iter()
+        .filter(|c| !HIT_METADATA.contains(&c.as_str()))
+        .collect();
+    fields.sort_by(|a, b| compare_paths(a, b));
+
+    let mut headers: Vec<String> = META_COLUMNS.iter().map(|c| c.to_string()).collect();
+    headers.extend(fields);
+    headers
+}
+
+// 逐段比较路径，数字段按数值比较，使 tags.2 排在 tags.10 之前
+fn compare_paths(a: &str, b: &str) -> Ordering {
+    let mut left = a.split('.');
+    let mut right = b.split('.');
+    loop {
+        match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) => {
+                let ordering = match (l.parse::<u64>(), r.parse::<u64>()) {
+                    (Ok(l), Ok(r)) => l.cmp(&r),
+                    _ => l.cmp(r),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hit() -> Value {
+        json!({
+            "_index": "logs",
+            "_id": "1",
+            "_score": 1.0,
+            "_source": {
+                "host": "web01",
+                "user": { "name": "alice", "roles": ["admin", "dev"] },
+                "events": [{ "code": "a" }, { "code": "b" }]
+            }
+        })
+    }
+
+    fn flattener(arrays: ArrayFlattenMode) -> Flattener {
+        Flattener::new(&FlattenOptions { arrays, ..Default::default() })
+    }
+
+    fn exploder(path: &str) -> Flattener {
+        Flattener::new(&FlattenOptions {
+            arrays: ArrayFlattenMode::Explode,
+            explode_path: Some(path.to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_flatten_array_modes() {
+        let rows = flattener(ArrayFlattenMode::Json).flatten_hit(&hit()).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["user.name"], "alice");
+        assert_eq!(rows[0]["user.roles"], json!(["admin", "dev"]));
+        assert_eq!(rows[0]["events.code"], json!(["a", "b"]));
+        assert_eq!(rows[0]["_id"], "1");
+
+        let rows = flattener(ArrayFlattenMode::Join).flatten_hit(&hit()).unwrap();
+        assert_eq!(rows[0]["user.roles"], "admin; dev");
+        assert_eq!(rows[0]["events.code"], "a; b");
+
+        let rows = flattener(ArrayFlattenMode::Index).flatten_hit(&hit()).unwrap();
+        assert_eq!(rows[0]["user.roles.1"], "dev");
+        assert_eq!(rows[0]["events.0.code"], "a");
+
+        // 只展开 events，user.roles 按 Join 合并
+        let rows = exploder("events").flatten_hit(&hit()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row["host"] == "web01" && row["_id"] == "1" && row["user.roles"] == "admin; dev"));
+        assert_eq!(rows[1]["events.code"], "b");
+
+        let mut huge = json!({ "_source": { "tags": [] } });
+        huge["_source"]["tags"] = Value::Array((0..=MAX_EXPLODED_ROWS).map(Value::from).collect());
+        assert!(exploder("tags").flatten_hit(&huge).is_err());
+    }
+
+    #[test]
+    fn test_header_discovery() {
+        let mut collector = ColumnCollector::default();
+        for row in flattener(ArrayFlattenMode::Index).flatten_hit(&hit()).unwrap() {
+            collector.add_row(&row);
+        }
+        let mut tags = json!({ "_source": { "tags": {} } });
+        tags["_source"]["tags"] = Value::Array((0..11).map(Value::from).collect());
+        for row in flattener(ArrayFlattenMode::Index).flatten_hit(&tags).unwrap() {
+            collector.add_row(&row);
+        }
+        let headers = collector.into_headers();
+        assert_eq!(&headers[..5], ["_id", "_score", "events.0.code", "events.1.code", "host"]);
+        let tag_2 = headers.iter().position(|h| h == "tags.2").unwrap();
+        let tag_10 = headers.iter().position(|h| h == "tags.10").unwrap();
+        assert!(tag_2 < tag_10);
+
+        let properties = json!({
+            "host": { "type": "keyword" },
+            "user": { "properties": { "name": { "type": "keyword" } } },
+            "events": { "type": "nested", "properties": { "code": { "type": "keyword" } } }
+        });
+        let properties = properties.as_object().unwrap();
+        let headers = mapping_columns(properties);
+        assert_eq!(headers, ["_id", "_score", "events.code", "host", "user.name"]);
+        // 映射表头覆盖 Json 模式下写出的所有列
+        let row = &flattener(ArrayFlattenMode::Json).flatten_hit(&hit()).unwrap()[0];
+        assert!(row.keys().all(|k| is_hit_metadata(k) || k == "user.roles" || headers.contains(k)));
+
+        let mapping_index = FlattenOptions { arrays: ArrayFlattenMode::Index, headers: HeaderDiscovery::Mapping, ..Default::default() };
+        assert!(validate_flatten_options(&ExportFormat::CSV, &mapping_index, None).is_err());
+        let explode = FlattenOptions { arrays: ArrayFlattenMode::Explode, ..Default::default() };
+        assert!(validate_flatten_options(&ExportFormat::CSV, &explode, None).is_err());
+        assert!(validate_flatten_options(&ExportFormat::JSON, &FlattenOptions::default(), None).is_err());
+    }
+}
@@ -31,9 +31,10 @@ impl ImportService {
             }
         }
 
-        let documents = match request.format {
-            ImportFormat::JSON => self.parse_json_file(&request.file_path)?,
-            ImportFormat::CSV => self.parse_csv_file(&request.file_path)?,
+        let documents: Vec<ImportDocument> = match request.format {
+            ImportFormat::JSON => self.parse_json_file(&request.file_path)?.into_iter().map(ImportDocument::from).collect(),
+            ImportFormat::CSV => self.parse_csv_file(&request.file_path)?.into_iter().map(ImportDocument::from).collect(),
+            ImportFormat::Bulk => self.parse_bulk_file(&request.file_path)?,
         };
 
         debug!("Parsed {} documents from file", documents.len());
@@ -48,12 +49,14 @@ impl ImportService {
         for (chunk_idx, chunk) in documents.chunks(batch_size).enumerate() {
             let mut operations = Vec::new();
 
-            for (doc_idx, document) in chunk.iter().enumerate() {
-                let _line_number = (chunk_idx * batch_size + doc_idx + 1) as u64;
+            for entry in chunk {
                 total_processed += 1;
 
-                let doc_id = if let Some(id_field) = &request.id_field {
-                    document.get(id_field).and_then(|v| v.as_str()).map(|s| s.to_string())
+                // bulk 文件自带的 _id 优先
+                let doc_id = if entry.id.is_some() {
+                    entry.id.clone()
+                } else if let Some(id_field) = &request.id_field {
+                    entry.document.get(id_field).and_then(|v| v.as_str()).map(|s| s.to_string())
                 } else {
                     None
                 };
@@ -64,7 +67,8 @@ impl ImportService {
                     action: action.to_string(),
                     index: request.index.clone(),
                     id: doc_id,
-                    document: Some(document.clone()),
+                    document: Some(entry.document.clone()),
+                    routing: entry.routing.clone(),
                 };
 
                 operations.push(operation);
@@ -76,7 +80,9 @@ impl ImportService {
                 Ok(response) => {
                     // 处理批量响应
                     for (idx, item) in response.items.iter().enumerate() {
-                        let line_number = (chunk_idx * batch_size + idx + 1) as u64;
+                        let line_number = chunk.get(idx)
+                            .and_then(|entry| entry.line_number)
+                            .unwrap_or((chunk_idx * batch_size + idx + 1) as u64);
 
                         if let Some(item_obj) = item.as_object() {
                             let mut has_error = false;
@@ -94,7 +100,7 @@ impl ImportService {
                                         errors.push(ImportError {
                                             line_number,
                                             error_message: error_msg,
-                                            document: chunk.get(idx).cloned(),
+                                            document: chunk.get(idx).map(|entry| entry.document.clone()),
                                         });
                                         failed_imports += 1;
                                     }
@@ -110,12 +116,12 @@ impl ImportService {
                 Err(e) => {
                     error!("Bulk operation failed: {}", e);
                     // 将整个批次标记为失败
-                    for (idx, document) in chunk.iter().enumerate() {
-                        let line_number = (chunk_idx * batch_size + idx + 1) as u64;
+                    for (idx, entry) in chunk.iter().enumerate() {
+                        let line_number = entry.line_number.unwrap_or((chunk_idx * batch_size + idx + 1) as u64);
                         errors.push(ImportError {
                             line_number,
                             error_message: format!("Bulk operation failed: {}", e),
-                            document: Some(document.clone()),
+                            document: Some(entry.document.clone()),
                         });
                         failed_imports += 1;
                     }
@@ -163,6 +169,61 @@ impl ImportService {
         Ok(documents)
     }
 
+    // 解析 _bulk 格式：动作行后跟 _source 行；delete 动作没有文档行，直接跳过。
+    // 只保留 _id 和 routing：动作行中的 _index 和 index/create 动作都不生效，
+    // 统一写入请求指定的索引，动作由 overwrite_existing 决定（index 或 create）
+    fn parse_bulk_file(&self, file_path: &str) -> Result<Vec<ImportDocument>> {
+        let file = File::open(file_path)
+            .with_context(|| format!("Failed to open file: {}", file_path))?;
+        let reader = BufReader::new(file);
+        let mut documents = Vec::new();
+        let mut pending_meta: Option<(usize, Value)> = None;
+
+        for (line_num, line) in reader.lines().enumerate() {
+            let line = line.with_context(|| format!("Failed to read line {}", line_num + 1))?;
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let value: Value = serde_json::from_str(line)
+                .with_context(|| format!("Failed to parse JSON on line {}", line_num + 1))?;
+
+            match pending_meta.take() {
+                Some((_, meta)) => {
+                    documents.push(ImportDocument {
+                        id: meta.get("_id").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                        routing: meta.get("routing").or_else(|| meta.get("_routing"))
+                            .and_then(|v| v.as_str()).map(|s| s.to_string()),
+                        document: value,
+                        line_number: Some((line_num + 1) as u64),
+                    });
+                }
+                None => {
+                    let (action, meta) = value.as_object()
+                        .filter(|obj| obj.len() == 1)
+                        .and_then(|obj| obj.iter().next())
+                        .ok_or_else(|| anyhow::anyhow!("Invalid bulk action on line {}", line_num + 1))?;
+
+                    match action.as_str() {
+                        "index" | "create" => pending_meta = Some((line_num + 1, meta.clone())),
+                        "delete" => {}
+                        other => {
+                            return Err(anyhow::anyhow!("Unsupported bulk action '{}' on line {}", other, line_num + 1));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some((line_number, _)) = pending_meta {
+            return Err(anyhow::anyhow!("Bulk action on line {} has no document line", line_number));
+        }
+
+        Ok(documents)
+    }
+
     fn parse_csv_file(&self, file_path: &str) -> Result<Vec<Value>> {
         let file = File::open(file_path)
             .with_context(|| format!("Failed to open file: {}", file_path))?;
@@ -205,4 +266,61 @@ impl ImportService {
 
         Ok(documents)
     }
-}
\ No newline at end of file
+}
+
+// 待导入的文档，bulk 文件会带上原始的 _id、routing 和文档所在行号；
+// 没有行号时错误按文档序号报告
+struct ImportDocument {
+    document: Value,
+    id: Option<String>,
+    routing: Option<String>,
+    line_number: Option<u64>,
+}
+
+impl From<Value> for ImportDocument {
+    fn from(document: Value) -> Self {
+        Self { document, id: None, routing: None, line_number: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bulk_file() {
+        let path = std::env::temp_dir().join(format!("es_client_{}_import.bulk", std::process::id()));
+        std::fs::write(
+            &path,
+            concat!(
+                "{\"index\":{\"_index\":\"logs\",\"_id\":\"1\",\"routing\":\"a\"}}\n",
+                "{\"host\":\"web01\"}\n",
+                "\n",
+                "{\"delete\":{\"_index\":\"logs\",\"_id\":\"2\"}}\n",
+                "{\"create\":{\"_id\":\"3\"}}\n",
+                "{\"host\":\"web03\"}\n",
+            ),
+        )
+        .unwrap();
+
+        let documents = ImportService::new().parse_bulk_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].id.as_deref(), Some("1"));
+        assert_eq!(documents[0].routing.as_deref(), Some("a"));
+        assert_eq!(documents[0].document, serde_json::json!({ "host": "web01" }));
+        assert_eq!(documents[1].id.as_deref(), Some("3"));
+        assert_eq!(documents[1].document["host"], "web03");
+        // 行号指向文档行，跨过空行和 delete 动作
+        assert_eq!(documents[0].line_number, Some(2));
+        assert_eq!(documents[1].line_number, Some(6));
+
+        // 最后一个动作行缺少文档行
+        std::fs::write(&path, "{\"index\":{\"_id\":\"1\"}}\n{\"host\":\"web01\"}\n{\"index\":{\"_id\":\"2\"}}\n").unwrap();
+        let Err(error) = ImportService::new().parse_bulk_file(path.to_str().unwrap()) else {
+            panic!("dangling bulk action should fail");
+        };
+        assert!(error.to_string().contains("line 3"));
+
+        std::fs::remove_file(path).ok();
+    }
+}
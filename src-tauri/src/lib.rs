@@ -5,6 +5,13 @@ pub mod crypto;
 pub mod export;
 pub mod error;
 pub mod import;
+pub mod query_parser;
+pub mod aggregation_table;
+pub mod columnar;
+pub mod output;
+pub mod flatten;
+pub mod redaction;
+pub mod schedule;
 
 pub use commands::*;
 pub use es_client::*;
@@ -12,4 +19,11 @@ pub use types::*;
 pub use crypto::*;
 pub use export::*;
 pub use error::*;
-pub use import::*;
\ No newline at end of file
+pub use import::*;
+pub use query_parser::*;
+pub use aggregation_table::*;
+pub use columnar::*;
+pub use output::*;
+pub use flatten::*;
+pub use redaction::*;
+pub use schedule::*;
\ No newline at end of file
@@ -8,6 +8,13 @@ mod crypto;
 mod export;
 mod import;
 mod error;
+mod query_parser;
+mod aggregation_table;
+mod columnar;
+mod output;
+mod flatten;
+mod redaction;
+mod schedule;
 
 use commands::*;
 use tauri::Wry;
@@ -24,8 +31,21 @@ async fn main() {
     let connection_manager = ConnectionManager::new(config)
         .expect("Failed to initialize connection manager");
 
+    // 应用设置（默认导出目录等）同样保存在应用数据目录
+    let settings_manager = SettingsManager::new(context.config().clone());
+
+    // 导出预设和运行历史，定时任务在应用运行期间执行
+    let preset_manager = PresetManager::new(context.config().clone());
+
     tauri::Builder::<Wry>::new()
         .manage(connection_manager)
+        .manage(settings_manager)
+        .manage(JobManager::new())
+        .manage(preset_manager)
+        .setup(|app| {
+            tauri::async_runtime::spawn(run_preset_scheduler(app.handle()));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             add_connection,
             list_connections,
@@ -35,13 +55,29 @@ async fn main() {
             get_cluster_health,
             list_indices,
             search_documents,
+            multi_search,
+            count_documents,
+            estimate_query_cost,
             search_documents_stream,
             get_index_mapping,
             get_field_names,
+            get_field_details,
+            get_geo_fields,
+            build_geo_query_dsl,
+            geo_grid_search,
+            translate_query_string,
             create_index,
             delete_index,
             export_search_results,
+            resume_search_export,
             get_export_directory,
+            get_app_settings,
+            update_app_settings,
+            list_export_presets,
+            save_export_preset,
+            delete_export_preset,
+            run_export_preset,
+            get_export_run_history,
             create_document,
             update_document,
             get_document,
@@ -58,10 +94,34 @@ async fn main() {
             get_template,
             put_template,
             delete_template,
+            list_search_templates,
+            get_search_template,
+            put_search_template,
+            delete_search_template,
+            render_search_template,
+            render_search_template_file,
+            search_template,
             execute_aggregation,
             execute_sql,
             execute_sql_cursor,
             close_sql_cursor,
+            translate_sql,
+            download_sql_results,
+            export_sql_results,
+            cancel_export_job,
+            execute_aggregation_table,
+            export_aggregation_result,
+            composite_aggregation_page,
+            stream_composite_aggregation,
+            export_composite_aggregation,
+            execute_eql,
+            get_eql_result,
+            get_eql_status,
+            delete_eql_search,
+            execute_esql,
+            get_esql_async_result,
+            delete_esql_async_query,
+            export_esql_results,
             get_nodes_info,
             get_nodes_stats,
             get_node_info,
@@ -5,6 +5,13 @@ mod crypto;
 mod export;
 mod import;
 mod error;
+mod query_parser;
+mod aggregation_table;
+mod columnar;
+mod output;
+mod flatten;
+mod redaction;
+mod schedule;
 
 // Test main without Tauri dependencies
 fn main() {
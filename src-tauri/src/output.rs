@@ -0,0 +1,502 @@
+// 导出文件的路径、压缩、分片与清单
+use crate::types::{ExportCheckpoint, ExportCompression, ExportFormat, ExportManifest, ExportPart, ExportSplitOptions};
+use anyhow::{anyhow, Result};
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+// 会被替换成目标格式扩展名的已知导出扩展名，其余扩展名保留并在后面追加
+const KNOWN_EXTENSIONS: &[&str] = &["json", "csv", "xlsx", "ndjson", "bulk", "parquet", "arrow", "tsv", "txt"];
+
+// Windows 下不能用作文件名的设备名
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 把用户输入的文件名清理成单个路径组件：路径分隔符、控制字符和 Windows 保留字符替换为 `_`，
+/// 去掉首尾空白以及结尾的点，设备名前加 `_`。清理后为空或只剩 `.`/`..` 时报错。
+pub fn sanitize_filename(filename: &str) -> Result<String> {
+    let replaced: String = filename
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = replaced.trim().trim_end_matches(['.', ' ']);
+
+    if trimmed.is_empty() || trimmed.chars().all(|c| c == '.' || c == '_') {
+        return Err(anyhow!("无效的文件名：{}", filename));
+    }
+
+    let stem = trimmed.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        return Ok(format!("_{}", trimmed));
+    }
+
+    Ok(trimmed.to_string())
+}
+
+/// 让文件名的扩展名与导出格式一致：末尾的 .gz/.zst 由压缩选项决定，先去掉；
+/// 已知的其他导出扩展名替换掉，其余情况直接追加
+pub fn with_format_extension(filename: &str, extension: &str) -> String {
+    let mut name = filename;
+    for compressed in [".gz", ".zst"] {
+        if name.len() > compressed.len() && name.to_ascii_lowercase().ends_with(compressed) {
+            name = &name[..name.len() - compressed.len()];
+        }
+    }
+
+    match name.rsplit_once('.') {
+        Some((_, current)) if current.eq_ignore_ascii_case(extension) => name.to_string(),
+        Some((stem, current)) if !stem.is_empty() && KNOWN_EXTENSIONS.contains(&current.to_ascii_lowercase().as_str()) => {
+            format!("{}.{}", stem, extension)
+        }
+        _ => format!("{}.{}", name, extension),
+    }
+}
+
+/// 校验用户选择的导出目录：必须是绝对路径且不含 `..`，已存在时必须是目录；不存在则创建
+pub fn prepare_directory(directory: &Path) -> Result<PathBuf> {
+    if !directory.is_absolute() {
+        return Err(anyhow!("导出目录必须是绝对路径：{}", directory.display()));
+    }
+    if directory.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(anyhow!("导出目录不能包含 ..：{}", directory.display()));
+    }
+    if directory.exists() && !directory.is_dir() {
+        return Err(anyhow!("导出目录不是文件夹：{}", directory.display()));
+    }
+
+    std::fs::create_dir_all(directory)?;
+    Ok(directory.to_path_buf())
+}
+
+/// 不覆盖时为已被占用的路径追加 " (1)"、" (2)" 等序号，直到 `occupied` 返回 false
+pub fn available_path(path: PathBuf, overwrite: bool, occupied: impl Fn(&Path) -> bool) -> PathBuf {
+    if overwrite || !occupied(&path) {
+        return path;
+    }
+
+    let stem = file_stem(&path);
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !occupied(candidate))
+        .unwrap_or(path)
+}
+
+/// 检查压缩和拆分选项是否适用于导出格式。
+/// Excel、Parquet、Arrow IPC 自带压缩，且只有在收尾时才确定文件大小，因此不支持外层压缩和按字节拆分。
+pub fn validate_output_options(
+    format: &ExportFormat,
+    compression: Option<ExportCompression>,
+    split: Option<&ExportSplitOptions>,
+) -> Result<()> {
+    let text_format = matches!(format, ExportFormat::JSON | ExportFormat::NDJSON | ExportFormat::CSV | ExportFormat::Bulk);
+
+    if compression.is_some() && !text_format {
+        return Err(anyhow!("压缩仅支持 JSON、NDJSON、CSV 和 Bulk 格式"));
+    }
+
+    if let Some(split) = split {
+        if split.max_records.unwrap_or(0) == 0 && split.max_bytes.unwrap_or(0) == 0 {
+            return Err(anyhow!("拆分导出需要指定大于 0 的 max_records 或 max_bytes"));
+        }
+        if split.max_bytes.is_some() && !text_format {
+            return Err(anyhow!("按字节数拆分仅支持 JSON、NDJSON、CSV 和 Bulk 格式"));
+        }
+    }
+
+    Ok(())
+}
+
+// 统计实际写入磁盘的字节数，压缩时即压缩后的大小
+struct CountingFile {
+    file: File,
+    bytes: u64,
+}
+
+impl Write for CountingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+enum Encoder {
+    Plain(CountingFile),
+    Gzip(GzEncoder<CountingFile>),
+    Zstd(zstd::Encoder<'static, CountingFile>),
+}
+
+/// 文本导出的输出文件，按需套上 gzip 或 zstd 压缩；写完必须调用 finish 写出压缩尾部
+pub struct OutputFile {
+    encoder: Encoder,
+}
+
+impl OutputFile {
+    pub fn create(path: &Path, compression: Option<ExportCompression>) -> Result<Self> {
+        let file = CountingFile { file: File::create(path)?, bytes: 0 };
+        let encoder = match compression {
+            None => Encoder::Plain(file),
+            Some(ExportCompression::Gzip) => Encoder::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+            Some(ExportCompression::Zstd) => Encoder::Zstd(zstd::Encoder::new(file, 0)?),
+        };
+        Ok(Self { encoder })
+    }
+
+    /// 续传时打开未压缩的部分文件：截断到断点记录的字节数，之后的写入追加在末尾
+    pub fn append(path: &Path, offset: u64) -> Result<Self> {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        let length = file.metadata()?.len();
+        if length < offset {
+            return Err(anyhow!("导出文件 {} 只有 {} 字节，短于断点记录的 {} 字节", path.display(), length, offset));
+        }
+        file.set_len(offset)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(Self { encoder: Encoder::Plain(CountingFile { file, bytes: offset }) })
+    }
+
+    /// 已落盘的字节数，不含压缩器内部尚未输出的部分
+    pub fn bytes_written(&self) -> u64 {
+        match &self.encoder {
+            Encoder::Plain(file) => file.bytes,
+            Encoder::Gzip(encoder) => encoder.get_ref().bytes,
+            Encoder::Zstd(encoder) => encoder.get_ref().bytes,
+        }
+    }
+
+    pub fn finish(self) -> Result<()> {
+        let mut file = match self.encoder {
+            Encoder::Plain(file) => file,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        file.flush()?;
+        Ok(())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            Encoder::Plain(file) => file.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            Encoder::Plain(file) => file.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// 先写出 BufWriter 中的缓冲，再收尾压缩流
+pub fn finish_buffered(writer: BufWriter<OutputFile>) -> Result<()> {
+    writer.into_inner().map_err(|e| e.into_error())?.finish()
+}
+
+fn compression_extension(compression: Option<ExportCompression>) -> &'static str {
+    match compression {
+        None => "",
+        Some(ExportCompression::Gzip) => ".gz",
+        Some(ExportCompression::Zstd) => ".zst",
+    }
+}
+
+/// 管理一次导出的输出文件：不拆分时只有一个文件，拆分时依次生成
+/// `name.part-0001.ext` 这样的分片，并在结束时写出 `name.manifest.json`
+pub struct ExportParts {
+    base_path: PathBuf,
+    compression: Option<ExportCompression>,
+    split: Option<ExportSplitOptions>,
+    current: Option<PathBuf>,
+    parts: Vec<ExportPart>,
+}
+
+impl ExportParts {
+    pub fn new(base_path: PathBuf, compression: Option<ExportCompression>, split: Option<ExportSplitOptions>) -> Self {
+        Self { base_path, compression, split, current: None, parts: Vec::new() }
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.split.is_some()
+    }
+
+    /// 代表这次导出的文件：拆分时为清单，否则为唯一的输出文件
+    pub fn primary_path(&self) -> PathBuf {
+        if self.is_split() {
+            self.manifest_path()
+        } else {
+            self.single_path()
+        }
+    }
+
+    /// 当前分片是否已达到拆分上限
+    pub fn is_full(&self, records: u64, bytes: u64) -> bool {
+        match &self.split {
+            Some(split) => {
+                split.max_records.is_some_and(|max| max > 0 && records >= max)
+                    || split.max_bytes.is_some_and(|max| max > 0 && bytes >= max)
+            }
+            None => false,
+        }
+    }
+
+    /// 分配下一个输出文件的路径
+    pub fn open_next(&mut self) -> PathBuf {
+        let path = if self.is_split() {
+            self.part_path(self.parts.len() + 1)
+        } else {
+            self.single_path()
+        };
+        self.current = Some(path.clone());
+        path
+    }
+
+    /// 当前分片写完后记录其记录数、大小和校验和
+    pub fn close_current(&mut self, records: u64) -> Result<()> {
+        let path = self.current.take().ok_or_else(|| anyhow!("没有正在写入的导出文件"))?;
+        let bytes = std::fs::metadata(&path)?.len();
+        self.parts.push(ExportPart {
+            file: file_name(&path),
+            records,
+            bytes,
+            sha256: sha256_file(&path)?,
+        });
+        Ok(())
+    }
+
+    /// 已写完的分片的总字节数
+    pub fn bytes_written(&self) -> u64 {
+        self.parts.iter().map(|p| p.bytes).sum()
+    }
+
+    pub fn parts(&self) -> &[ExportPart] {
+        &self.parts
+    }
+
+    /// 拆分导出时写出清单并返回其路径，不拆分时返回 None
+    pub fn write_manifest(&self, format: &ExportFormat) -> Result<Option<PathBuf>> {
+        if !self.is_split() {
+            return Ok(None);
+        }
+
+        let manifest = ExportManifest {
+            format: format.clone(),
+            compression: self.compression,
+            total_records: self.parts.iter().map(|p| p.records).sum(),
+            parts: self.parts.clone(),
+        };
+        let path = self.manifest_path();
+        let mut writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(&mut writer, &manifest)?;
+        writer.flush()?;
+        Ok(Some(path))
+    }
+
+    fn single_path(&self) -> PathBuf {
+        with_suffix(&self.base_path, compression_extension(self.compression))
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.base_path.with_file_name(format!("{}.manifest.json", file_stem(&self.base_path)))
+    }
+
+    fn part_path(&self, number: usize) -> PathBuf {
+        let extension = self
+            .base_path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        self.base_path.with_file_name(format!(
+            "{}.part-{:04}{}{}",
+            file_stem(&self.base_path),
+            number,
+            extension,
+            compression_extension(self.compression)
+        ))
+    }
+}
+
+/// 导出失败或取消时删除写了一半的文件，文件尚未创建时忽略
+pub fn discard_partial_output(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            tracing::warn!("Failed to remove partial export {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// 导出文件对应的断点文件，如 `logs.ndjson.checkpoint.json`
+pub fn checkpoint_path(file_path: &Path) -> PathBuf {
+    with_suffix(file_path, ".checkpoint.json")
+}
+
+/// 先写临时文件再重命名，中途退出时不会留下半个断点
+// 断点中的请求带有明文的脱敏盐，文件权限设为仅当前用户可读写
+pub fn write_checkpoint(path: &Path, checkpoint: &ExportCheckpoint) -> Result<()> {
+    let temp_path = with_suffix(path, ".tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    serde_json::to_writer_pretty(&mut writer, checkpoint)?;
+    writer.flush()?;
+    drop(writer);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+pub fn read_checkpoint(path: &Path) -> Result<ExportCheckpoint> {
+    let file = File::open(path).map_err(|e| anyhow!("无法读取断点文件 {}: {}", path.display(), e))?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("es_client_{}_{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_sanitize_filename_and_extension() {
+        assert_eq!(sanitize_filename("../../etc/passwd").unwrap(), ".._.._etc_passwd");
+        assert_eq!(sanitize_filename("C:\\temp\\out.csv").unwrap(), "C__temp_out.csv");
+        assert_eq!(sanitize_filename(" report?.csv. ").unwrap(), "report_.csv");
+        assert_eq!(sanitize_filename("con.json").unwrap(), "_con.json");
+        assert!(sanitize_filename("..").is_err());
+        assert!(sanitize_filename("/").is_err());
+
+        assert_eq!(with_format_extension("logs", "csv"), "logs.csv");
+        assert_eq!(with_format_extension("logs.CSV", "csv"), "logs.CSV");
+        assert_eq!(with_format_extension("logs.json", "xlsx"), "logs.xlsx");
+        assert_eq!(with_format_extension("logs.csv.gz", "csv"), "logs.csv");
+        assert_eq!(with_format_extension("logs.2024-01", "ndjson"), "logs.2024-01.ndjson");
+        assert_eq!(with_format_extension(".json", "csv"), ".json.csv");
+    }
+
+    #[test]
+    fn test_available_path_and_directory_validation() {
+        let dir = temp_dir("collision");
+        std::fs::write(dir.join("logs.csv"), "").unwrap();
+        std::fs::write(dir.join("logs (1).csv"), "").unwrap();
+
+        let exists = |p: &Path| p.exists();
+        assert_eq!(available_path(dir.join("logs.csv"), false, exists), dir.join("logs (2).csv"));
+        assert_eq!(available_path(dir.join("logs.csv"), true, exists), dir.join("logs.csv"));
+        assert_eq!(available_path(dir.join("other.csv"), false, exists), dir.join("other.csv"));
+
+        assert!(prepare_directory(Path::new("relative/dir")).is_err());
+        assert!(prepare_directory(&dir.join("..").join("escape")).is_err());
+        assert!(prepare_directory(&dir.join("logs.csv")).is_err());
+        assert!(prepare_directory(&dir.join("nested")).unwrap().is_dir());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_output_file_compression_round_trip() {
+        let dir = temp_dir("compression");
+        let content = "{\"host\":\"web01\"}\n".repeat(100);
+
+        for compression in [ExportCompression::Gzip, ExportCompression::Zstd] {
+            let path = dir.join(format!("out{}", compression_extension(Some(compression))));
+            let mut output = OutputFile::create(&path, Some(compression)).unwrap();
+            output.write_all(content.as_bytes()).unwrap();
+            output.finish().unwrap();
+
+            let file = File::open(&path).unwrap();
+            let mut decoded = String::new();
+            match compression {
+                ExportCompression::Gzip => flate2::read::GzDecoder::new(file).read_to_string(&mut decoded).unwrap(),
+                ExportCompression::Zstd => zstd::Decoder::new(file).unwrap().read_to_string(&mut decoded).unwrap(),
+            };
+            assert_eq!(decoded, content);
+            assert!(std::fs::metadata(&path).unwrap().len() < content.len() as u64);
+        }
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_export_parts_naming_and_manifest() {
+        let dir = temp_dir("parts");
+        let split = ExportSplitOptions { max_records: Some(2), max_bytes: None };
+        let mut parts = ExportParts::new(dir.join("logs.ndjson"), Some(ExportCompression::Gzip), Some(split));
+
+        assert!(!parts.is_full(1, 0));
+        assert!(parts.is_full(2, 0));
+
+        for records in [2u64, 1] {
+            let path = parts.open_next();
+            let mut output = OutputFile::create(&path, Some(ExportCompression::Gzip)).unwrap();
+            output.write_all(b"{}\n").unwrap();
+            output.finish().unwrap();
+            parts.close_current(records).unwrap();
+        }
+
+        assert_eq!(parts.parts()[0].file, "logs.part-0001.ndjson.gz");
+        assert_eq!(parts.parts()[1].file, "logs.part-0002.ndjson.gz");
+        assert_eq!(parts.parts()[0].sha256.len(), 64);
+
+        let manifest_path = parts.write_manifest(&ExportFormat::NDJSON).unwrap().unwrap();
+        assert_eq!(file_name(&manifest_path), "logs.manifest.json");
+        let manifest: ExportManifest = serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest.total_records, 3);
+        assert_eq!(manifest.parts[1].records, 1);
+        assert_eq!(manifest.parts[0].sha256, sha256_file(&dir.join("logs.part-0001.ndjson.gz")).unwrap());
+
+        // 不拆分时沿用原文件名并追加压缩扩展名
+        let mut single = ExportParts::new(dir.join("logs.csv"), Some(ExportCompression::Zstd), None);
+        assert_eq!(file_name(&single.open_next()), "logs.csv.zst");
+        assert!(single.write_manifest(&ExportFormat::CSV).unwrap().is_none());
+
+        assert!(validate_output_options(&ExportFormat::Excel, Some(ExportCompression::Gzip), None).is_err());
+        assert!(validate_output_options(&ExportFormat::Parquet, None, Some(&ExportSplitOptions { max_records: Some(10), max_bytes: None })).is_ok());
+        assert!(validate_output_options(&ExportFormat::CSV, None, Some(&ExportSplitOptions::default())).is_err());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
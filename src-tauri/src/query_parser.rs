@@ -0,0 +1,987 @@
+// KQL / Lucene 查询字符串到 Elasticsearch DSL 的转换
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum QuerySyntax {
+    KQL,
+    Lucene,
+}
+
+// 解析错误，column 从 1 开始按字符计数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryParseError {
+    pub message: String,
+    pub column: usize,
+}
+
+impl QueryParseError {
+    fn new(message: impl Into<String>, column: usize) -> Self {
+        Self {
+            message: message.into(),
+            column,
+        }
+    }
+}
+
+impl fmt::Display for QueryParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第 {} 列: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for QueryParseError {}
+
+/// 将 KQL 或 Lucene 查询字符串转换为 DSL。
+/// `field_types` 为字段路径到映射类型的表（见 `EsClient::get_field_types`），
+/// 用来决定生成 term、match 还是 range 查询。
+pub fn query_string_to_dsl(
+    input: &str,
+    syntax: QuerySyntax,
+    field_types: &HashMap<String, String>,
+) -> Result<Value, QueryParseError> {
+    let tokens = tokenize(input, syntax)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end_column: input.chars().count() + 1,
+    };
+
+    let node = match syntax {
+        QuerySyntax::KQL => parser.parse_kql()?,
+        QuerySyntax::Lucene => parser.parse_lucene()?,
+    };
+
+    compile(&node, field_types)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Colon,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    And,
+    Or,
+    Not,
+    Plus,
+    Minus,
+    To,
+    Tilde(Option<String>),
+    Caret(f64),
+    Quoted(String),
+    Word { text: String, wildcard: bool },
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn is_word_boundary(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | ':' | '<' | '>' | '"' | '[' | ']' | '{' | '}' | '~' | '^')
+}
+
+fn tokenize(input: &str, syntax: QuerySyntax) -> Result<Vec<Token>, QueryParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let next = chars.get(i + 1).copied();
+        let simple = match c {
+            '(' => Some(TokenKind::LParen),
+            ')' => Some(TokenKind::RParen),
+            '[' => Some(TokenKind::LBracket),
+            ']' => Some(TokenKind::RBracket),
+            '{' => Some(TokenKind::LBrace),
+            '}' => Some(TokenKind::RBrace),
+            ':' => Some(TokenKind::Colon),
+            _ => None,
+        };
+        if let Some(kind) = simple {
+            tokens.push(Token { kind, column });
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '<' | '>' => {
+                let kind = match (c, next == Some('=')) {
+                    ('<', true) => TokenKind::Lte,
+                    ('<', false) => TokenKind::Lt,
+                    (_, true) => TokenKind::Gte,
+                    (_, false) => TokenKind::Gt,
+                };
+                i += if next == Some('=') { 2 } else { 1 };
+                tokens.push(Token { kind, column });
+            }
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(QueryParseError::new("引号未闭合", column)),
+                        Some('\\') if i + 1 < chars.len() => {
+                            text.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some(ch) => {
+                            text.push(*ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token { kind: TokenKind::Quoted(text), column });
+            }
+            '~' => {
+                i += 1;
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let amount = if i > start { Some(chars[start..i].iter().collect()) } else { None };
+                tokens.push(Token { kind: TokenKind::Tilde(amount), column });
+            }
+            '^' => {
+                i += 1;
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let boost: String = chars[start..i].iter().collect();
+                let boost = boost
+                    .parse::<f64>()
+                    .map_err(|_| QueryParseError::new("^ 之后需要数字权重", column))?;
+                tokens.push(Token { kind: TokenKind::Caret(boost), column });
+            }
+            '&' if syntax == QuerySyntax::Lucene && next == Some('&') => {
+                i += 2;
+                tokens.push(Token { kind: TokenKind::And, column });
+            }
+            '|' if syntax == QuerySyntax::Lucene && next == Some('|') => {
+                i += 2;
+                tokens.push(Token { kind: TokenKind::Or, column });
+            }
+            '!' if syntax == QuerySyntax::Lucene => {
+                i += 1;
+                tokens.push(Token { kind: TokenKind::Not, column });
+            }
+            '+' | '-'
+                if syntax == QuerySyntax::Lucene
+                    && next.is_some_and(|n| !n.is_whitespace())
+                    && !matches!(
+                        tokens.last().map(|t| &t.kind),
+                        Some(TokenKind::Colon | TokenKind::Lt | TokenKind::Lte | TokenKind::Gt | TokenKind::Gte | TokenKind::LBracket | TokenKind::LBrace | TokenKind::To)
+                    ) =>
+            {
+                i += 1;
+                let kind = if c == '+' { TokenKind::Plus } else { TokenKind::Minus };
+                tokens.push(Token { kind, column });
+            }
+            _ => {
+                let mut text = String::new();
+                let mut wildcard = false;
+                let mut escaped = false;
+                while i < chars.len() && !is_word_boundary(chars[i]) {
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        text.push(chars[i + 1]);
+                        escaped = true;
+                        i += 2;
+                        continue;
+                    }
+                    if chars[i] == '*' || chars[i] == '?' {
+                        wildcard = true;
+                    }
+                    text.push(chars[i]);
+                    i += 1;
+                }
+
+                let kind = match (syntax, escaped, text.as_str()) {
+                    (QuerySyntax::KQL, false, t) if t.eq_ignore_ascii_case("and") => TokenKind::And,
+                    (QuerySyntax::KQL, false, t) if t.eq_ignore_ascii_case("or") => TokenKind::Or,
+                    (QuerySyntax::KQL, false, t) if t.eq_ignore_ascii_case("not") => TokenKind::Not,
+                    (QuerySyntax::Lucene, false, "AND") => TokenKind::And,
+                    (QuerySyntax::Lucene, false, "OR") => TokenKind::Or,
+                    (QuerySyntax::Lucene, false, "NOT") => TokenKind::Not,
+                    (QuerySyntax::Lucene, false, "TO") => TokenKind::To,
+                    _ => TokenKind::Word { text, wildcard },
+                };
+                tokens.push(Token { kind, column });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TermKind {
+    Word,
+    Phrase,
+    Wildcard,
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    text: String,
+    kind: TermKind,
+    fuzziness: Option<String>, // 单词为模糊度，短语为 slop
+}
+
+#[derive(Debug, Clone)]
+struct Bound {
+    value: String,
+    inclusive: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    MatchAll,
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    Bool {
+        must: Vec<Node>,
+        should: Vec<Node>,
+        must_not: Vec<Node>,
+    },
+    Boost(Box<Node>, f64),
+    Match {
+        field: Option<String>,
+        term: Term,
+        column: usize,
+    },
+    Range {
+        field: String,
+        lower: Option<Bound>,
+        upper: Option<Bound>,
+        column: usize,
+    },
+    Exists {
+        field: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Occur {
+    Must,
+    Should,
+    MustNot,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end_column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.pos + offset).map(|t| &t.kind)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens.get(self.pos).map(|t| t.column).unwrap_or(self.end_column)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, kind: TokenKind, description: &str) -> Result<(), QueryParseError> {
+        if self.peek() == Some(&kind) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(description))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> QueryParseError {
+        match self.peek() {
+            None => QueryParseError::new(format!("查询意外结束，期望{}", expected), self.end_column),
+            Some(kind) => QueryParseError::new(
+                format!("意外的符号 {}，期望{}", describe(kind), expected),
+                self.column(),
+            ),
+        }
+    }
+
+    fn is_field_prefix(&self) -> bool {
+        matches!(self.peek(), Some(TokenKind::Word { .. }))
+            && matches!(self.peek_at(1), Some(TokenKind::Colon))
+    }
+
+    fn take_field(&mut self) -> String {
+        match self.advance().map(|t| t.kind) {
+            Some(TokenKind::Word { text, .. }) => text,
+            _ => unreachable!("take_field called without a field token"),
+        }
+    }
+
+    // ---------- KQL ----------
+
+    fn parse_kql(&mut self) -> Result<Node, QueryParseError> {
+        if self.tokens.is_empty() {
+            return Ok(Node::MatchAll);
+        }
+        let node = self.kql_or()?;
+        if self.peek().is_some() {
+            return Err(self.unexpected("and、or 或查询结束"));
+        }
+        Ok(node)
+    }
+
+    fn kql_or(&mut self) -> Result<Node, QueryParseError> {
+        let mut nodes = vec![self.kql_and()?];
+        while self.peek() == Some(&TokenKind::Or) {
+            self.pos += 1;
+            nodes.push(self.kql_and()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::Or(nodes) })
+    }
+
+    fn kql_and(&mut self) -> Result<Node, QueryParseError> {
+        let mut nodes = vec![self.kql_not()?];
+        while self.peek() == Some(&TokenKind::And) {
+            self.pos += 1;
+            nodes.push(self.kql_not()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::And(nodes) })
+    }
+
+    fn kql_not(&mut self) -> Result<Node, QueryParseError> {
+        if self.peek() == Some(&TokenKind::Not) {
+            self.pos += 1;
+            return Ok(Node::Not(Box::new(self.kql_not()?)));
+        }
+        self.kql_primary()
+    }
+
+    fn kql_primary(&mut self) -> Result<Node, QueryParseError> {
+        match self.peek() {
+            Some(TokenKind::LParen) => {
+                self.pos += 1;
+                let node = self.kql_or()?;
+                self.expect(TokenKind::RParen, " )")?;
+                Ok(node)
+            }
+            Some(TokenKind::LBrace) => Err(QueryParseError::new("暂不支持嵌套查询 {}", self.column())),
+            Some(TokenKind::Word { .. }) if self.is_field_prefix() => {
+                let field = self.take_field();
+                self.pos += 1; // ':'
+                match self.peek() {
+                    Some(TokenKind::LParen) => {
+                        self.pos += 1;
+                        let node = self.kql_value_or(&field)?;
+                        self.expect(TokenKind::RParen, " )")?;
+                        Ok(node)
+                    }
+                    Some(TokenKind::LBrace) => Err(QueryParseError::new("暂不支持嵌套查询 {}", self.column())),
+                    _ => self.kql_value(Some(&field)),
+                }
+            }
+            Some(TokenKind::Word { .. })
+                if matches!(self.peek_at(1), Some(TokenKind::Lt | TokenKind::Lte | TokenKind::Gt | TokenKind::Gte)) =>
+            {
+                let column = self.column();
+                let field = self.take_field();
+                self.comparison(field, column)
+            }
+            Some(TokenKind::Word { .. }) | Some(TokenKind::Quoted(_)) => self.kql_value(None),
+            _ => Err(self.unexpected("字段、值或 (")),
+        }
+    }
+
+    fn kql_value_or(&mut self, field: &str) -> Result<Node, QueryParseError> {
+        let mut nodes = vec![self.kql_value_and(field)?];
+        while self.peek() == Some(&TokenKind::Or) {
+            self.pos += 1;
+            nodes.push(self.kql_value_and(field)?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::Or(nodes) })
+    }
+
+    fn kql_value_and(&mut self, field: &str) -> Result<Node, QueryParseError> {
+        let mut nodes = vec![self.kql_value_not(field)?];
+        while self.peek() == Some(&TokenKind::And) {
+            self.pos += 1;
+            nodes.push(self.kql_value_not(field)?);
+        }
+        Ok(if nodes.len() == 1 { nodes.remove(0) } else { Node::And(nodes) })
+    }
+
+    fn kql_value_not(&mut self, field: &str) -> Result<Node, QueryParseError> {
+        match self.peek() {
+            Some(TokenKind::Not) => {
+                self.pos += 1;
+                Ok(Node::Not(Box::new(self.kql_value_not(field)?)))
+            }
+            Some(TokenKind::LParen) => {
+                self.pos += 1;
+                let node = self.kql_value_or(field)?;
+                self.expect(TokenKind::RParen, " )")?;
+                Ok(node)
+            }
+            _ => self.kql_value(Some(field)),
+        }
+    }
+
+    // KQL 中未加引号的连续单词视为同一个值
+    fn kql_value(&mut self, field: Option<&str>) -> Result<Node, QueryParseError> {
+        let column = self.column();
+        let term = match self.peek().cloned() {
+            Some(TokenKind::Quoted(text)) => {
+                self.pos += 1;
+                Term { text, kind: TermKind::Phrase, fuzziness: None }
+            }
+            Some(TokenKind::Word { .. }) => {
+                let mut words = Vec::new();
+                let mut wildcard = false;
+                while let Some(TokenKind::Word { text, wildcard: w }) = self.peek().cloned() {
+                    if self.is_field_prefix() {
+                        break;
+                    }
+                    words.push(text);
+                    wildcard |= w;
+                    self.pos += 1;
+                }
+                let kind = if wildcard { TermKind::Wildcard } else { TermKind::Word };
+                Term { text: words.join(" "), kind, fuzziness: None }
+            }
+            _ => return Err(self.unexpected("值")),
+        };
+
+        Ok(match field {
+            Some(field) if term.kind == TermKind::Wildcard && term.text == "*" => Node::Exists { field: field.to_string() },
+            Some(field) => Node::Match { field: Some(field.to_string()), term, column },
+            None => Node::Match { field: None, term, column },
+        })
+    }
+
+    // field < value 这类比较（KQL 与 Lucene 的 field:>value 共用）
+    fn comparison(&mut self, field: String, column: usize) -> Result<Node, QueryParseError> {
+        let op = self.advance().map(|t| t.kind);
+        let value = match self.advance().map(|t| t.kind) {
+            Some(TokenKind::Word { text, .. }) | Some(TokenKind::Quoted(text)) => text,
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected("比较值"));
+            }
+        };
+
+        let (lower, upper) = match op {
+            Some(TokenKind::Gt) => (Some(Bound { value, inclusive: false }), None),
+            Some(TokenKind::Gte) => (Some(Bound { value, inclusive: true }), None),
+            Some(TokenKind::Lt) => (None, Some(Bound { value, inclusive: false })),
+            _ => (None, Some(Bound { value, inclusive: true })),
+        };
+
+        Ok(Node::Range { field, lower, upper, column })
+    }
+
+    // ---------- Lucene ----------
+
+    fn parse_lucene(&mut self) -> Result<Node, QueryParseError> {
+        if self.tokens.is_empty() {
+            return Ok(Node::MatchAll);
+        }
+        let node = self.lucene_query(None)?;
+        if self.peek().is_some() {
+            return Err(self.unexpected("查询结束"));
+        }
+        Ok(node)
+    }
+
+    // 与 Lucene QueryParser 相同的子句组合规则：默认 OR，AND 会把前后两个子句都变为必须
+    fn lucene_query(&mut self, default_field: Option<&str>) -> Result<Node, QueryParseError> {
+        let mut clauses: Vec<(Occur, Node)> = Vec::new();
+
+        while let Some(kind) = self.peek() {
+            if *kind == TokenKind::RParen {
+                break;
+            }
+
+            let conjunction = match kind {
+                TokenKind::And | TokenKind::Or if clauses.is_empty() => {
+                    return Err(QueryParseError::new("运算符前缺少查询条件", self.column()));
+                }
+                TokenKind::And => {
+                    self.pos += 1;
+                    Some(Occur::Must)
+                }
+                TokenKind::Or => {
+                    self.pos += 1;
+                    Some(Occur::Should)
+                }
+                _ => None,
+            };
+
+            let modifier = match self.peek() {
+                Some(TokenKind::Plus) => Some(Occur::Must),
+                Some(TokenKind::Minus) | Some(TokenKind::Not) => Some(Occur::MustNot),
+                _ => None,
+            };
+            if modifier.is_some() {
+                self.pos += 1;
+            }
+
+            let clause = self.lucene_clause(default_field)?;
+
+            if conjunction == Some(Occur::Must) {
+                if let Some(last) = clauses.last_mut() {
+                    if last.0 == Occur::Should {
+                        last.0 = Occur::Must;
+                    }
+                }
+            }
+
+            let occur = match (modifier, conjunction) {
+                (Some(occur), _) => occur,
+                (None, Some(Occur::Must)) => Occur::Must,
+                _ => Occur::Should,
+            };
+            clauses.push((occur, clause));
+        }
+
+        if clauses.is_empty() {
+            return Err(self.unexpected("查询条件"));
+        }
+
+        if clauses.len() == 1 && clauses[0].0 != Occur::MustNot {
+            return Ok(clauses.remove(0).1);
+        }
+
+        let mut must = Vec::new();
+        let mut should = Vec::new();
+        let mut must_not = Vec::new();
+        for (occur, node) in clauses {
+            match occur {
+                Occur::Must => must.push(node),
+                Occur::Should => should.push(node),
+                Occur::MustNot => must_not.push(node),
+            }
+        }
+        Ok(Node::Bool { must, should, must_not })
+    }
+
+    fn lucene_clause(&mut self, default_field: Option<&str>) -> Result<Node, QueryParseError> {
+        let node = match self.peek() {
+            Some(TokenKind::LParen) => {
+                self.pos += 1;
+                let node = self.lucene_query(default_field)?;
+                self.expect(TokenKind::RParen, " )")?;
+                node
+            }
+            Some(TokenKind::Word { .. }) if self.is_field_prefix() => {
+                let column = self.column();
+                let field = self.take_field();
+                self.pos += 1; // ':'
+                match self.peek() {
+                    Some(TokenKind::LParen) => {
+                        self.pos += 1;
+                        let node = self.lucene_query(Some(&field))?;
+                        self.expect(TokenKind::RParen, " )")?;
+                        node
+                    }
+                    Some(TokenKind::Lt | TokenKind::Lte | TokenKind::Gt | TokenKind::Gte) => self.comparison(field, column)?,
+                    _ if field == "_exists_" => match self.advance().map(|t| t.kind) {
+                        Some(TokenKind::Word { text, .. }) => Node::Exists { field: text },
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.unexpected("字段名"));
+                        }
+                    },
+                    _ => self.lucene_term(Some(&field))?,
+                }
+            }
+            _ => self.lucene_term(default_field)?,
+        };
+
+        if let Some(TokenKind::Caret(boost)) = self.peek().cloned() {
+            self.pos += 1;
+            return Ok(Node::Boost(Box::new(node), boost));
+        }
+        Ok(node)
+    }
+
+    fn lucene_term(&mut self, field: Option<&str>) -> Result<Node, QueryParseError> {
+        let column = self.column();
+        let mut term = match self.peek().cloned() {
+            Some(TokenKind::Quoted(text)) => {
+                self.pos += 1;
+                Term { text, kind: TermKind::Phrase, fuzziness: None }
+            }
+            Some(TokenKind::Word { text, wildcard }) => {
+                self.pos += 1;
+                let kind = if wildcard { TermKind::Wildcard } else { TermKind::Word };
+                Term { text, kind, fuzziness: None }
+            }
+            // 范围外的 TO 只是普通单词
+            Some(TokenKind::To) => {
+                self.pos += 1;
+                Term { text: "TO".to_string(), kind: TermKind::Word, fuzziness: None }
+            }
+            Some(TokenKind::LBracket) | Some(TokenKind::LBrace) => return self.lucene_range(field, column),
+            _ => return Err(self.unexpected("查询词")),
+        };
+
+        if let Some(TokenKind::Tilde(amount)) = self.peek().cloned() {
+            if term.kind == TermKind::Wildcard {
+                return Err(QueryParseError::new("通配符不能与 ~ 同时使用", self.column()));
+            }
+            self.pos += 1;
+            term.fuzziness = Some(amount.unwrap_or_else(|| {
+                if term.kind == TermKind::Phrase { "0".to_string() } else { "AUTO".to_string() }
+            }));
+        }
+
+        if term.kind == TermKind::Wildcard && term.text == "*" {
+            return Ok(match field {
+                Some(field) => Node::Exists { field: field.to_string() },
+                None => Node::MatchAll,
+            });
+        }
+
+        Ok(Node::Match { field: field.map(|f| f.to_string()), term, column })
+    }
+
+    fn lucene_range(&mut self, field: Option<&str>, column: usize) -> Result<Node, QueryParseError> {
+        let field = field
+            .ok_or_else(|| QueryParseError::new("范围查询必须指定字段", column))?
+            .to_string();
+
+        let lower_inclusive = self.advance().map(|t| t.kind) == Some(TokenKind::LBracket);
+        let lower = self.range_bound(lower_inclusive)?;
+        self.expect(TokenKind::To, " TO")?;
+        let upper_value = self.range_bound(true)?;
+        let upper_inclusive = match self.advance().map(|t| t.kind) {
+            Some(TokenKind::RBracket) => true,
+            Some(TokenKind::RBrace) => false,
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected(" ] 或 }"));
+            }
+        };
+        let upper = upper_value.map(|b| Bound { inclusive: upper_inclusive, ..b });
+
+        if lower.is_none() && upper.is_none() {
+            return Ok(Node::Exists { field });
+        }
+
+        Ok(Node::Range { field, lower, upper, column })
+    }
+
+    fn range_bound(&mut self, inclusive: bool) -> Result<Option<Bound>, QueryParseError> {
+        match self.advance().map(|t| t.kind) {
+            Some(TokenKind::Word { text, .. }) if text == "*" => Ok(None),
+            Some(TokenKind::Word { text, .. }) | Some(TokenKind::Quoted(text)) => Ok(Some(Bound { value: text, inclusive })),
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("范围边界"))
+            }
+        }
+    }
+}
+
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::LParen => "(".to_string(),
+        TokenKind::RParen => ")".to_string(),
+        TokenKind::LBracket => "[".to_string(),
+        TokenKind::RBracket => "]".to_string(),
+        TokenKind::LBrace => "{".to_string(),
+        TokenKind::RBrace => "}".to_string(),
+        TokenKind::Colon => ":".to_string(),
+        TokenKind::Lt => "<".to_string(),
+        TokenKind::Lte => "<=".to_string(),
+        TokenKind::Gt => ">".to_string(),
+        TokenKind::Gte => ">=".to_string(),
+        TokenKind::And => "AND".to_string(),
+        TokenKind::Or => "OR".to_string(),
+        TokenKind::Not => "NOT".to_string(),
+        TokenKind::Plus => "+".to_string(),
+        TokenKind::Minus => "-".to_string(),
+        TokenKind::To => "TO".to_string(),
+        TokenKind::Tilde(_) => "~".to_string(),
+        TokenKind::Caret(_) => "^".to_string(),
+        TokenKind::Quoted(text) => format!("\"{}\"", text),
+        TokenKind::Word { text, .. } => format!("'{}'", text),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldKind {
+    Text,
+    Keyword,
+    Numeric,
+    Date,
+    Boolean,
+}
+
+fn field_kind(field_types: &HashMap<String, String>, field: &str) -> FieldKind {
+    match field_types.get(field).map(|t| t.as_str()) {
+        Some("keyword" | "constant_keyword" | "wildcard" | "ip" | "version" | "flattened") => FieldKind::Keyword,
+        Some(
+            "long" | "integer" | "short" | "byte" | "double" | "float" | "half_float" | "scaled_float"
+            | "unsigned_long",
+        ) => FieldKind::Numeric,
+        Some("date" | "date_nanos") => FieldKind::Date,
+        Some("boolean") => FieldKind::Boolean,
+        // text、match_only_text 以及映射中不存在的字段都按全文处理
+        _ => FieldKind::Text,
+    }
+}
+
+fn validate_value(field: &str, kind: FieldKind, value: &str, column: usize) -> Result<(), QueryParseError> {
+    match kind {
+        FieldKind::Numeric if value.parse::<f64>().is_err() => Err(QueryParseError::new(
+            format!("字段 '{}' 为数值类型，'{}' 不是有效数字", field, value),
+            column,
+        )),
+        FieldKind::Boolean if !matches!(value, "true" | "false") => Err(QueryParseError::new(
+            format!("字段 '{}' 为布尔类型，值只能是 true 或 false", field),
+            column,
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn compile(node: &Node, field_types: &HashMap<String, String>) -> Result<Value, QueryParseError> {
+    let compile_all = |nodes: &[Node]| -> Result<Vec<Value>, QueryParseError> {
+        nodes.iter().map(|n| compile(n, field_types)).collect()
+    };
+
+    Ok(match node {
+        Node::MatchAll => serde_json::json!({ "match_all": {} }),
+        Node::And(nodes) => serde_json::json!({ "bool": { "must": compile_all(nodes)? } }),
+        Node::Or(nodes) => serde_json::json!({
+            "bool": { "should": compile_all(nodes)?, "minimum_should_match": 1 }
+        }),
+        Node::Not(inner) => serde_json::json!({ "bool": { "must_not": [compile(inner, field_types)?] } }),
+        Node::Bool { must, should, must_not } => {
+            let mut bool_query = serde_json::Map::new();
+            if !must.is_empty() {
+                bool_query.insert("must".to_string(), Value::Array(compile_all(must)?));
+            }
+            if !should.is_empty() {
+                bool_query.insert("should".to_string(), Value::Array(compile_all(should)?));
+                if must.is_empty() {
+                    bool_query.insert("minimum_should_match".to_string(), Value::from(1));
+                }
+            }
+            if !must_not.is_empty() {
+                bool_query.insert("must_not".to_string(), Value::Array(compile_all(must_not)?));
+            }
+            serde_json::json!({ "bool": bool_query })
+        }
+        Node::Boost(inner, boost) => serde_json::json!({
+            "bool": { "must": [compile(inner, field_types)?], "boost": boost }
+        }),
+        Node::Exists { field } => serde_json::json!({ "exists": { "field": field } }),
+        Node::Range { field, lower, upper, column } => {
+            let kind = field_kind(field_types, field);
+            let mut range = serde_json::Map::new();
+            if let Some(bound) = lower {
+                validate_value(field, kind, &bound.value, *column)?;
+                let key = if bound.inclusive { "gte" } else { "gt" };
+                range.insert(key.to_string(), Value::from(bound.value.as_str()));
+            }
+            if let Some(bound) = upper {
+                validate_value(field, kind, &bound.value, *column)?;
+                let key = if bound.inclusive { "lte" } else { "lt" };
+                range.insert(key.to_string(), Value::from(bound.value.as_str()));
+            }
+            serde_json::json!({ "range": { field.as_str(): range } })
+        }
+        Node::Match { field: None, term, .. } => compile_free_text(term),
+        Node::Match { field: Some(field), term, column } => compile_field_term(field, term, *column, field_types)?,
+    })
+}
+
+fn compile_free_text(term: &Term) -> Value {
+    match term.kind {
+        TermKind::Wildcard => serde_json::json!({
+            "query_string": { "query": term.text, "analyze_wildcard": true }
+        }),
+        TermKind::Phrase => {
+            let mut multi_match = serde_json::json!({ "query": term.text, "type": "phrase", "lenient": true });
+            if let Some(slop) = &term.fuzziness {
+                multi_match["slop"] = Value::from(slop.parse::<u64>().unwrap_or(0));
+            }
+            serde_json::json!({ "multi_match": multi_match })
+        }
+        TermKind::Word => {
+            let mut multi_match = serde_json::json!({ "query": term.text, "lenient": true });
+            if let Some(fuzziness) = &term.fuzziness {
+                multi_match["fuzziness"] = Value::from(fuzziness.as_str());
+            }
+            serde_json::json!({ "multi_match": multi_match })
+        }
+    }
+}
+
+fn compile_field_term(
+    field: &str,
+    term: &Term,
+    column: usize,
+    field_types: &HashMap<String, String>,
+) -> Result<Value, QueryParseError> {
+    let kind = field_kind(field_types, field);
+
+    if term.kind == TermKind::Wildcard {
+        if matches!(kind, FieldKind::Numeric | FieldKind::Date | FieldKind::Boolean) {
+            return Err(QueryParseError::new(
+                format!("字段 '{}' 的类型不支持通配符查询", field),
+                column,
+            ));
+        }
+        return Ok(serde_json::json!({ "wildcard": { field: { "value": term.text } } }));
+    }
+
+    validate_value(field, kind, &term.text, column)?;
+
+    Ok(match kind {
+        FieldKind::Date => serde_json::json!({
+            "range": { field: { "gte": term.text, "lte": term.text } }
+        }),
+        FieldKind::Keyword | FieldKind::Numeric | FieldKind::Boolean => match &term.fuzziness {
+            Some(fuzziness) if term.kind == TermKind::Word && kind == FieldKind::Keyword => serde_json::json!({
+                "fuzzy": { field: { "value": term.text, "fuzziness": fuzziness } }
+            }),
+            _ => serde_json::json!({ "term": { field: term.text } }),
+        },
+        FieldKind::Text if term.kind == TermKind::Phrase => {
+            let mut phrase = serde_json::json!({ "query": term.text });
+            if let Some(slop) = &term.fuzziness {
+                phrase["slop"] = Value::from(slop.parse::<u64>().unwrap_or(0));
+            }
+            serde_json::json!({ "match_phrase": { field: phrase } })
+        }
+        FieldKind::Text => {
+            let mut match_query = serde_json::json!({ "query": term.text });
+            if let Some(fuzziness) = &term.fuzziness {
+                match_query["fuzziness"] = Value::from(fuzziness.as_str());
+            }
+            serde_json::json!({ "match": { field: match_query } })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field_types() -> HashMap<String, String> {
+        [
+            ("status", "long"),
+            ("host", "keyword"),
+            ("message", "text"),
+            ("@timestamp", "date"),
+            ("active", "boolean"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    fn kql(input: &str) -> Result<Value, QueryParseError> {
+        query_string_to_dsl(input, QuerySyntax::KQL, &field_types())
+    }
+
+    fn lucene(input: &str) -> Result<Value, QueryParseError> {
+        query_string_to_dsl(input, QuerySyntax::Lucene, &field_types())
+    }
+
+    #[test]
+    fn test_kql_and_not_with_wildcard() {
+        let dsl = kql("status:500 and not host:web-*").unwrap();
+        assert_eq!(
+            dsl,
+            json!({ "bool": { "must": [
+                { "term": { "status": "500" } },
+                { "bool": { "must_not": [{ "wildcard": { "host": { "value": "web-*" } } }] } }
+            ] } })
+        );
+    }
+
+    #[test]
+    fn test_kql_field_types_choose_query() {
+        assert_eq!(kql("message:disk full").unwrap(), json!({ "match": { "message": { "query": "disk full" } } }));
+        assert_eq!(kql("message:\"disk full\"").unwrap(), json!({ "match_phrase": { "message": { "query": "disk full" } } }));
+        assert_eq!(kql("status >= 400").unwrap(), json!({ "range": { "status": { "gte": "400" } } }));
+        assert_eq!(kql("host:*").unwrap(), json!({ "exists": { "field": "host" } }));
+        assert_eq!(kql("").unwrap(), json!({ "match_all": {} }));
+    }
+
+    #[test]
+    fn test_kql_value_group() {
+        let dsl = kql("status:(500 or 503)").unwrap();
+        assert_eq!(dsl["bool"]["should"].as_array().unwrap().len(), 2);
+        assert_eq!(dsl["bool"]["minimum_should_match"], 1);
+    }
+
+    #[test]
+    fn test_lucene_operators_and_ranges() {
+        let dsl = lucene("+host:web01 -status:[500 TO 599} message:timeout").unwrap();
+        assert_eq!(dsl["bool"]["must"][0], json!({ "term": { "host": "web01" } }));
+        assert_eq!(dsl["bool"]["must_not"][0], json!({ "range": { "status": { "gte": "500", "lt": "599" } } }));
+        assert_eq!(dsl["bool"]["should"][0], json!({ "match": { "message": { "query": "timeout" } } }));
+        assert!(dsl["bool"].get("minimum_should_match").is_none());
+
+        let dsl = lucene("host:a AND host:b OR host:c").unwrap();
+        assert_eq!(dsl["bool"]["must"].as_array().unwrap().len(), 2);
+        assert_eq!(dsl["bool"]["should"].as_array().unwrap().len(), 1);
+
+        assert_eq!(lucene("_exists_:host").unwrap(), json!({ "exists": { "field": "host" } }));
+        assert_eq!(
+            lucene("@timestamp:[2024-01-01 TO *]").unwrap(),
+            json!({ "range": { "@timestamp": { "gte": "2024-01-01" } } })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_report_columns() {
+        let error = kql("status:500 and (host:web").unwrap_err();
+        assert_eq!(error.column, 25);
+
+        let error = kql("status:abc").unwrap_err();
+        assert_eq!(error.column, 8);
+
+        let error = lucene("message:\"unterminated").unwrap_err();
+        assert_eq!(error.column, 9);
+
+        let error = lucene("AND host:a").unwrap_err();
+        assert_eq!(error.column, 1);
+    }
+}
@@ -0,0 +1,368 @@
+// 导出前按规则对字段脱敏：删除、加盐哈希、保留末尾字符的掩码或正则替换
+use crate::export::value_to_string;
+use crate::types::{RedactionAction, RedactionConfig, RedactionRule, SearchQuery};
+use anyhow::{anyhow, Result};
+use rand::RngCore;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+const DEFAULT_MASK_CHAR: char = '*';
+
+// 命中中 _source 以外可能带出原始值的部分：高亮片段、fields（docvalue/stored/script 字段）、
+// inner_hits 和排序值。有脱敏规则时整体去掉，所有格式的输出都只包含脱敏后的 _source
+const UNREDACTED_HIT_PARTS: &[&str] = &["highlight", "fields", "inner_hits", "sort"];
+
+// 请求体中产生上述部分的参数
+const UNREDACTED_QUERY_PARAMS: &[&str] = &["script_fields", "fields"];
+
+enum CompiledAction {
+    Drop,
+    Hash,
+    Mask { keep_last: usize, mask_char: char },
+    Replace { regex: Regex, replacement: String },
+}
+
+struct CompiledRule {
+    field: String,
+    path: Vec<String>,
+    action: CompiledAction,
+}
+
+/// 编译后的脱敏规则，并统计被处理的值的个数。
+/// 同一次导出的所有格式、所有分片共用一个 Redactor，哈希结果在整次导出内保持一致。
+pub struct Redactor {
+    rules: Vec<CompiledRule>,
+    salt: String,
+    redacted: u64,
+}
+
+impl Redactor {
+    pub fn new(config: Option<&RedactionConfig>) -> Result<Self> {
+        let mut rules = Vec::new();
+        for rule in config.map(|c| c.rules.as_slice()).unwrap_or_default() {
+            rules.push(compile_rule(rule)?);
+        }
+
+        let salt = match config.and_then(|c| c.salt.clone()) {
+            Some(salt) => salt,
+            None => {
+                let mut bytes = [0u8; 16];
+                rand::thread_rng().fill_bytes(&mut bytes);
+                bytes.iter().map(|b| format!("{:02x}", b)).collect()
+            }
+        };
+
+        Ok(Self { rules, salt, redacted: 0 })
+    }
+
+    pub fn redacted_values(&self) -> u64 {
+        self.redacted
+    }
+
+    // 续传时沿用同一个盐，哈希结果才能与已导出的部分一致
+    pub fn salt(&self) -> &str {
+        &self.salt
+    }
+
+    /// 有脱敏规则时导出请求不再取高亮、docvalue/stored/script 字段
+    pub fn export_query(&self, query: &SearchQuery) -> SearchQuery {
+        let mut query = query.clone();
+        if !self.rules.is_empty() {
+            query.highlight = None;
+            query.docvalue_fields = None;
+            query.stored_fields = None;
+            for param in UNREDACTED_QUERY_PARAMS {
+                query.extra.remove(*param);
+            }
+        }
+        query
+    }
+
+    /// 对命中的 _source 应用全部规则，路径经过对象数组时作用于每个元素；
+    /// 同时去掉 _source 以外可能含有原始值的部分
+    pub fn redact_hit(&mut self, hit: &mut Value) {
+        if self.rules.is_empty() {
+            return;
+        }
+        if let Some(hit) = hit.as_object_mut() {
+            for part in UNREDACTED_HIT_PARTS {
+                hit.remove(*part);
+            }
+        }
+
+        let Some(source) = hit.get_mut("_source") else {
+            return;
+        };
+
+        for rule in &self.rules {
+            self.redacted += apply_path(source, &rule.path, &rule.action, &self.salt);
+        }
+    }
+
+    /// 表格导出时的列名，Drop 规则命中的列被去掉
+    pub fn visible_columns(&self, columns: &[String]) -> Vec<String> {
+        columns.iter().filter(|c| !self.drops(c)).cloned().collect()
+    }
+
+    /// 按列名对一行应用规则，返回与 visible_columns 对应的值
+    pub fn redact_row(&mut self, columns: &[String], row: &[Value]) -> Vec<Value> {
+        let mut redacted_row = Vec::with_capacity(row.len());
+
+        for (column, value) in columns.iter().zip(row) {
+            if self.drops(column) {
+                if !value.is_null() {
+                    self.redacted += 1;
+                }
+                continue;
+            }
+
+            let mut value = value.clone();
+            for rule in self.rules.iter().filter(|r| r.field == *column) {
+                self.redacted += transform(&mut value, &rule.action, &self.salt);
+            }
+            redacted_row.push(value);
+        }
+
+        redacted_row
+    }
+
+    fn drops(&self, column: &str) -> bool {
+        self.rules.iter().any(|r| r.field == column && matches!(r.action, CompiledAction::Drop))
+    }
+}
+
+fn compile_rule(rule: &RedactionRule) -> Result<CompiledRule> {
+    let field = rule.field.trim();
+    if field.is_empty() || field.split('.').any(|part| part.is_empty()) {
+        return Err(anyhow!("无效的脱敏字段路径：{}", rule.field));
+    }
+
+    let action = match &rule.action {
+        RedactionAction::Drop => CompiledAction::Drop,
+        RedactionAction::Hash => CompiledAction::Hash,
+        RedactionAction::Mask { keep_last, mask_char } => CompiledAction::Mask {
+            keep_last: *keep_last,
+            mask_char: mask_char.unwrap_or(DEFAULT_MASK_CHAR),
+        },
+        RedactionAction::Replace { pattern, replacement } => CompiledAction::Replace {
+            regex: Regex::new(pattern).map_err(|e| anyhow!("字段 {} 的正则表达式无效: {}", rule.field, e))?,
+            replacement: replacement.clone(),
+        },
+    };
+
+    Ok(CompiledRule {
+        field: field.to_string(),
+        path: field.split('.').map(str::to_string).collect(),
+        action,
+    })
+}
+
+/// Drop 规则删除的字段路径，表头中不再保留这些列
+pub fn dropped_fields(rules: &[RedactionRule]) -> HashSet<String> {
+    rules
+        .iter()
+        .filter(|r| matches!(r.action, RedactionAction::Drop))
+        .map(|r| r.field.trim().to_string())
+        .collect()
+}
+
+/// 让映射与脱敏后的文档保持一致：删除的字段从映射中去掉，
+/// 其余规则会把值改写成字符串，对应字段按 keyword 处理（影响列式 schema 和 Excel 日期列）
+pub fn redact_mapping_properties(properties: &mut Map<String, Value>, rules: &[RedactionRule]) {
+    'rules: for rule in rules {
+        let path: Vec<&str> = rule.field.trim().split('.').collect();
+        let Some((last, parents)) = path.split_last() else {
+            continue;
+        };
+
+        let mut current = &mut *properties;
+        for parent in parents {
+            let Some(children) = current
+                .get_mut(*parent)
+                .and_then(|def| def.get_mut("properties"))
+                .and_then(Value::as_object_mut)
+            else {
+                continue 'rules;
+            };
+            current = children;
+        }
+
+        match rule.action {
+            RedactionAction::Drop => {
+                current.remove(*last);
+            }
+            _ => {
+                if let Some(def) = current.get_mut(*last) {
+                    *def = json!({ "type": "keyword" });
+                }
+            }
+        }
+    }
+}
+
+fn apply_path(value: &mut Value, path: &[String], action: &CompiledAction, salt: &str) -> u64 {
+    match value {
+        Value::Array(items) => items.iter_mut().map(|item| apply_path(item, path, action, salt)).sum(),
+        Value::Object(object) => {
+            // 文档中也可能直接存有带点的键，如 {"user.email": ...}
+            let dotted = path.join(".");
+            let (key, rest) = if path.len() > 1 && object.contains_key(&dotted) {
+                (&dotted, &[][..])
+            } else {
+                match path.split_first() {
+                    Some((head, rest)) => (head, rest),
+                    None => return 0,
+                }
+            };
+
+            if !rest.is_empty() {
+                return object.get_mut(key).map_or(0, |child| apply_path(child, rest, action, salt));
+            }
+            match action {
+                CompiledAction::Drop => object.remove(key).map_or(0, |_| 1),
+                _ => object.get_mut(key).map_or(0, |target| transform(target, action, salt)),
+            }
+        }
+        _ => 0,
+    }
+}
+
+// 改写单个值（数组逐个元素），返回被处理的值的个数
+fn transform(value: &mut Value, action: &CompiledAction, salt: &str) -> u64 {
+    match value {
+        Value::Null => 0,
+        Value::Array(items) => items.iter_mut().map(|item| transform(item, action, salt)).sum(),
+        _ => {
+            let text = value_to_string(value);
+            let redacted = match action {
+                CompiledAction::Drop => return 0,
+                CompiledAction::Hash => {
+                    let mut hasher = Sha256::new();
+                    hasher.update(salt.as_bytes());
+                    hasher.update(text.as_bytes());
+                    format!("{:x}", hasher.finalize())
+                }
+                CompiledAction::Mask { keep_last, mask_char } => mask(&text, *keep_last, *mask_char),
+                CompiledAction::Replace { regex, replacement } => {
+                    if !regex.is_match(&text) {
+                        return 0;
+                    }
+                    regex.replace_all(&text, replacement.as_str()).to_string()
+                }
+            };
+            *value = Value::String(redacted);
+            1
+        }
+    }
+}
+
+fn mask(text: &str, keep_last: usize, mask_char: char) -> String {
+    let length = text.chars().count();
+    let masked = length.saturating_sub(keep_last);
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| if i < masked { mask_char } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(field: &str, action: RedactionAction) -> RedactionRule {
+        RedactionRule { field: field.to_string(), action }
+    }
+
+    fn redactor(rules: Vec<RedactionRule>) -> Redactor {
+        Redactor::new(Some(&RedactionConfig { rules, profile: None, salt: Some("pepper".to_string()) })).unwrap()
+    }
+
+    #[test]
+    fn test_redact_hit() {
+        let mut redactor = redactor(vec![
+            rule("user.email", RedactionAction::Hash),
+            rule("user.phone", RedactionAction::Mask { keep_last: 4, mask_char: None }),
+            rule("contacts.email", RedactionAction::Drop),
+            rule("message", RedactionAction::Replace { pattern: r"\d{3}-\d{4}".to_string(), replacement: "###-####".to_string() }),
+            rule("missing.field", RedactionAction::Drop),
+        ]);
+
+        let mut hit = json!({
+            "_id": "1",
+            "sort": ["alice@example.com"],
+            "highlight": { "user.email": ["<em>alice@example.com</em>"] },
+            "fields": { "user.email": ["alice@example.com"] },
+            "_source": {
+                "user": { "email": "alice@example.com", "phone": "13800138000" },
+                "contacts": [{ "email": "a@x.com", "name": "a" }, { "email": "b@x.com", "name": "b" }],
+                "message": "call 555-1234 or 555-9876"
+            }
+        });
+        redactor.redact_hit(&mut hit);
+
+        let source = &hit["_source"];
+        let expected_hash = format!("{:x}", Sha256::digest(b"pepperalice@example.com"));
+        assert_eq!(source["user"]["email"], expected_hash);
+        assert_eq!(source["user"]["phone"], "*******8000");
+        assert_eq!(source["contacts"], json!([{ "name": "a" }, { "name": "b" }]));
+        assert_eq!(source["message"], "call ###-#### or ###-####");
+        assert_eq!(redactor.redacted_values(), 5);
+        // 高亮、fields 和排序值中的原始值不会被导出
+        assert!(!hit.to_string().contains("alice@example.com"));
+        assert!(hit.get("highlight").is_none() && hit.get("sort").is_none());
+
+        let query = SearchQuery {
+            highlight: Some(json!({ "fields": { "user.email": {} } })),
+            stored_fields: Some(vec!["user.email".to_string()]),
+            extra: [("script_fields".to_string(), json!({}))].into(),
+            ..Default::default()
+        };
+        let export_query = redactor.export_query(&query);
+        assert!(export_query.highlight.is_none() && export_query.stored_fields.is_none() && export_query.extra.is_empty());
+        assert!(Redactor::new(None).unwrap().export_query(&query).highlight.is_some());
+    }
+
+    #[test]
+    fn test_redact_row_and_validation() {
+        let mut redactor = redactor(vec![
+            rule("email", RedactionAction::Drop),
+            rule("phone", RedactionAction::Mask { keep_last: 2, mask_char: Some('#') }),
+        ]);
+        let columns = vec!["host".to_string(), "email".to_string(), "phone".to_string()];
+
+        assert_eq!(redactor.visible_columns(&columns), ["host", "phone"]);
+        let row = redactor.redact_row(&columns, &[json!("web01"), json!("a@x.com"), json!(12345)]);
+        assert_eq!(row, vec![json!("web01"), json!("###45")]);
+        assert_eq!(redactor.redacted_values(), 2);
+
+        let invalid = RedactionConfig {
+            rules: vec![rule("message", RedactionAction::Replace { pattern: "(".to_string(), replacement: String::new() })],
+            ..Default::default()
+        };
+        assert!(Redactor::new(Some(&invalid)).is_err());
+        assert!(Redactor::new(Some(&RedactionConfig { rules: vec![rule("user..email", RedactionAction::Hash)], ..Default::default() })).is_err());
+    }
+
+    #[test]
+    fn test_redact_mapping_properties() {
+        let mut properties = json!({
+            "@timestamp": { "type": "date" },
+            "user": { "properties": { "email": { "type": "keyword" }, "age": { "type": "long" } } }
+        });
+        let rules = vec![
+            rule("user.email", RedactionAction::Drop),
+            rule("user.age", RedactionAction::Hash),
+            rule("@timestamp", RedactionAction::Mask { keep_last: 4, mask_char: None }),
+            rule("missing.path", RedactionAction::Drop),
+        ];
+        redact_mapping_properties(properties.as_object_mut().unwrap(), &rules);
+
+        assert_eq!(properties, json!({
+            "@timestamp": { "type": "keyword" },
+            "user": { "properties": { "age": { "type": "keyword" } } }
+        }));
+        assert_eq!(dropped_fields(&rules), HashSet::from(["user.email".to_string(), "missing.path".to_string()]));
+    }
+}
@@ -0,0 +1,184 @@
+// 导出预设的定时表达式和文件名模板
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+/// 5 段 cron 表达式：分 时 日 月 周（0 和 7 都表示周日），按本地时间匹配。
+/// 每段支持 `*`、数字、`a-b` 范围、`/n` 步长和逗号分隔的列表，另有 @hourly、@daily、@weekly、@monthly 简写。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // 日和周都被限制时按标准 cron 取并集
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(anyhow!("定时表达式需要 5 段（分 时 日 月 周）：{}", expression));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        // 7 和 0 都表示周日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+
+    /// 给定的本地时间所在的这一分钟是否应当执行
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        let bit = |set: u64, value: u32| set & (1 << value) != 0;
+
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+
+        bit(self.minutes, time.minute()) && bit(self.hours, time.hour()) && bit(self.months, time.month()) && day_matches
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut set = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| anyhow!("无效的步长：{}", part))?;
+                if step == 0 {
+                    return Err(anyhow!("步长必须大于 0：{}", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start, part)?, parse_value(end, part)?),
+                // a/n 表示从 a 开始到最大值
+                None if step > 1 => (parse_value(range, part)?, max),
+                None => {
+                    let value = parse_value(range, part)?;
+                    (value, value)
+                }
+            },
+        };
+
+        if start < min || end > max || start > end {
+            return Err(anyhow!("{} 超出范围 {}-{}", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok(set)
+}
+
+fn parse_value(value: &str, part: &str) -> Result<u32> {
+    value.parse().map_err(|_| anyhow!("无效的定时表达式字段：{}", part))
+}
+
+/// 展开文件名模板中的占位符：{index}、{preset}、{date}（2024-01-31）、
+/// {time}（083000）和 {datetime}（20240131-083000）。未知的占位符报错
+pub fn render_filename_template(template: &str, index: &str, preset: &str, now: &NaiveDateTime) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        rendered.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .map(|i| open + i)
+            .ok_or_else(|| anyhow!("文件名模板缺少 }}：{}", template))?;
+
+        match &rest[open + 1..close] {
+            "index" => rendered.push_str(index),
+            "preset" => rendered.push_str(preset),
+            "date" => rendered.push_str(&now.format("%Y-%m-%d").to_string()),
+            "time" => rendered.push_str(&now.format("%H%M%S").to_string()),
+            "datetime" => rendered.push_str(&now.format("%Y%m%d-%H%M%S").to_string()),
+            other => return Err(anyhow!("文件名模板中有未知的占位符：{{{}}}", other)),
+        }
+        rest = &rest[close + 1..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        // 工作日早上 8:30
+        let schedule = CronSchedule::parse("30 8 * * 1-5").unwrap();
+        assert!(schedule.matches(&at(2024, 1, 31, 8, 30))); // 周三
+        assert!(!schedule.matches(&at(2024, 2, 3, 8, 30))); // 周六
+        assert!(!schedule.matches(&at(2024, 1, 31, 8, 31)));
+
+        let every_15 = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert!(every_15.matches(&at(2024, 1, 1, 3, 45)));
+        assert!(!every_15.matches(&at(2024, 1, 1, 3, 50)));
+
+        // 日和周同时限制时取并集；7 表示周日
+        let either = CronSchedule::parse("0 0 1 * 7").unwrap();
+        assert!(either.matches(&at(2024, 2, 1, 0, 0))); // 1 号，周四
+        assert!(either.matches(&at(2024, 2, 4, 0, 0))); // 周日
+        assert!(!either.matches(&at(2024, 2, 5, 0, 0)));
+
+        assert_eq!(CronSchedule::parse("@daily").unwrap(), CronSchedule::parse("0 0 * * *").unwrap());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_render_filename_template() {
+        let now = at(2024, 1, 31, 8, 30);
+        assert_eq!(
+            render_filename_template("{index}-{date}.csv", "logs-app", "daily", &now).unwrap(),
+            "logs-app-2024-01-31.csv"
+        );
+        assert_eq!(
+            render_filename_template("{preset}_{datetime}", "logs", "早报", &now).unwrap(),
+            "早报_20240131-083000"
+        );
+        assert!(render_filename_template("{host}.csv", "logs", "daily", &now).is_err());
+        assert!(render_filename_template("{index.csv", "logs", "daily", &now).is_err());
+    }
+}
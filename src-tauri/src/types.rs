@@ -1,3 +1,4 @@
+use crate::error::ErrorDetails;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -24,21 +25,69 @@ pub struct IndexInfo {
     pub store_size: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchQuery {
     pub index: String,
     pub query: serde_json::Value,
     pub from: Option<i32>,
     pub size: Option<i32>,
     pub sort: Option<Vec<serde_json::Value>>,
+    #[serde(alias = "_source")]
+    pub source: Option<serde_json::Value>, // bool、字段数组或 {includes, excludes}
+    pub highlight: Option<serde_json::Value>,
+    pub track_total_hits: Option<serde_json::Value>, // bool 或精确计数上限
+    pub aggs: Option<serde_json::Value>,
+    pub collapse: Option<serde_json::Value>,
+    pub post_filter: Option<serde_json::Value>,
+    pub docvalue_fields: Option<Vec<serde_json::Value>>,
+    pub stored_fields: Option<Vec<String>>,
+    pub runtime_mappings: Option<serde_json::Value>,
+    pub timeout: Option<String>,
+    pub knn: Option<Vec<KnnQuery>>,
+    // 设置后使用 rrf retriever 融合 query 与 knn 的排序（混合检索）
+    pub rrf: Option<RrfConfig>,
+    // 未建模的其他请求体参数（如 rank、retriever），原样透传给 Elasticsearch
+    #[serde(flatten, default)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnnQuery {
+    pub field: String,
+    pub query_vector: Vec<f32>,
+    pub k: u32,
+    pub num_candidates: u32,
+    pub filter: Option<serde_json::Value>,
+    pub similarity: Option<f32>,
+    pub boost: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RrfConfig {
+    pub rank_constant: Option<u32>,
+    pub rank_window_size: Option<u32>,
 }
 
+// 带类型信息的字段，dense_vector 字段附带维度与相似度
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldInfo {
+    pub name: String,
+    pub field_type: String,
+    pub dims: Option<u32>,
+    pub similarity: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SqlQuery {
     pub query: String,
     pub fetch_size: Option<u32>,
     pub request_timeout: Option<String>,
     pub page_timeout: Option<String>,
+    pub params: Option<Vec<serde_json::Value>>, // 按顺序绑定 query 中的 ? 占位符
+    pub filter: Option<serde_json::Value>, // DSL 预过滤条件
+    pub time_zone: Option<String>,
+    pub field_multi_value_leniency: Option<bool>,
+    pub columnar: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +95,69 @@ pub struct SqlResult {
     pub columns: Vec<SqlColumn>,
     pub rows: Vec<Vec<serde_json::Value>>,
     pub cursor: Option<String>,
+    // columnar 模式下按列返回的数据，此时 rows 为空
+    #[serde(default)]
+    pub values: Option<Vec<Vec<serde_json::Value>>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EsqlQuery {
+    pub query: String,
+    pub params: Option<Vec<serde_json::Value>>, // 按顺序绑定 query 中的 ? 占位符
+    pub locale: Option<String>,
+    pub columnar: Option<bool>,
+    pub filter: Option<serde_json::Value>,
+    // 设置后使用异步查询接口，超时未完成时返回 id
+    pub wait_for_completion_timeout: Option<String>,
+    pub keep_alive: Option<String>,
+    pub keep_on_completion: Option<bool>,
+}
+
+// 与 SqlResult 相同的列加行结构，异步查询额外返回 id 与运行状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EsqlResult {
+    pub columns: Vec<SqlColumn>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    pub values: Option<Vec<Vec<serde_json::Value>>>, // columnar 模式下按列返回
+    pub id: Option<String>,
+    pub is_running: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EsqlExportRequest {
+    pub connection_id: String,
+    pub query: EsqlQuery,
+    pub format: ExportFormat,
+    pub filename: String,
+    #[serde(default)]
+    pub target: ExportTarget,
+    #[serde(default)]
+    pub redaction: Option<RedactionConfig>,
+}
+
+// SQL 结果直接下载时的文本格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum SqlFormat {
+    CSV,
+    TSV,
+    TXT,
+}
+
+impl SqlFormat {
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            SqlFormat::CSV => "csv",
+            SqlFormat::TSV => "tsv",
+            SqlFormat::TXT => "txt",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqlDownloadResult {
+    pub file_path: String,
+    pub bytes_written: u64,
+    pub pages: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +166,56 @@ pub struct SqlColumn {
     pub r#type: String,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EqlQuery {
+    pub index: String,
+    pub query: String,
+    pub event_category_field: Option<String>,
+    pub timestamp_field: Option<String>,
+    pub tiebreaker_field: Option<String>,
+    pub size: Option<u32>,
+    pub filter: Option<serde_json::Value>,
+    // 异步执行参数：超过该时间仍未完成时返回 id，之后用 get_eql_result 获取结果
+    pub wait_for_completion_timeout: Option<String>,
+    pub keep_alive: Option<String>,
+    pub keep_on_completion: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EqlEvent {
+    pub index: String,
+    pub id: String,
+    pub source: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EqlSequence {
+    pub join_keys: Vec<serde_json::Value>,
+    pub events: Vec<EqlEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EqlResult {
+    pub id: Option<String>,
+    pub is_running: bool,
+    pub is_partial: bool,
+    pub took: u64,
+    pub timed_out: bool,
+    pub total: u64,
+    pub events: Vec<EqlEvent>,
+    pub sequences: Vec<EqlSequence>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EqlStatus {
+    pub id: String,
+    pub is_running: bool,
+    pub is_partial: bool,
+    pub start_time_in_millis: Option<u64>,
+    pub expiration_time_in_millis: Option<u64>,
+    pub completion_status: Option<u16>, // 完成后的 HTTP 状态码
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub total: u64,
@@ -61,6 +223,60 @@ pub struct SearchResult {
     pub took: u64,
     pub timed_out: bool,
     pub aggregations: Option<serde_json::Value>,
+    #[serde(default)]
+    pub total_relation: Option<String>, // "eq" 或 "gte"
+    #[serde(default)]
+    pub max_score: Option<f64>,
+    #[serde(default)]
+    pub shards: Option<ShardStats>,
+    // 与 hits 一一对应的高亮片段（字段名 -> 片段列表），未请求高亮时为空
+    #[serde(default)]
+    pub highlights: Vec<HashMap<String, Vec<String>>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardStats {
+    pub total: u32,
+    pub successful: u32,
+    pub skipped: u32,
+    pub failed: u32,
+}
+
+// _msearch 中单个查询的结果，result 与 error 二者只会有一个
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiSearchItem {
+    pub result: Option<SearchResult>,
+    pub error: Option<ErrorDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CountResult {
+    pub count: u64,
+    pub shards: Option<ShardStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardDocCount {
+    pub index: String,
+    pub shard: u32,
+    pub primary: bool,
+    pub state: String,
+    pub docs: u64,
+    pub store_bytes: u64,
+}
+
+// 导出或 delete-by-query 之前的开销预估
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryCostEstimate {
+    pub index: String,
+    pub matching_documents: u64,
+    pub total_documents: u64,
+    pub index_count: u32,
+    pub primary_store_bytes: u64,
+    pub estimated_bytes: u64, // 按命中比例估算的数据量
+    pub shards: Vec<ShardDocCount>,
+    pub risk_level: String, // low, medium, high
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -332,6 +548,151 @@ pub enum ExportFormat {
     JSON,
     CSV,
     Excel,
+    NDJSON,
+    Bulk, // _bulk 请求体：动作行 + _source 行
+    Parquet,
+    ArrowIpc,
+}
+
+impl ExportFormat {
+    // 导出文件应使用的扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::JSON => "json",
+            ExportFormat::CSV => "csv",
+            ExportFormat::Excel => "xlsx",
+            ExportFormat::NDJSON => "ndjson",
+            ExportFormat::Bulk => "bulk",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::ArrowIpc => "arrow",
+        }
+    }
+}
+
+// CSV/Excel 导出时数组值的展开方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ArrayFlattenMode {
+    #[default]
+    Json,    // 标量数组整体写成 JSON；对象数组按叶子路径分别写成 JSON 数组
+    Join,    // 元素按 delimiter 连接在同一单元格
+    Explode, // explode_path 指定的数组每个元素展开成一行，其余列重复，其余数组按 Join 处理
+    Index,   // 按下标展开成 tags.0、tags.1 这样的列
+}
+
+// CSV/Excel 表头的来源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum HeaderDiscovery {
+    #[default]
+    Sample,   // 第一页命中
+    Mapping,  // 索引映射中的全部叶子字段
+    FullScan, // 全部导出数据，先写入临时文件再生成表格
+}
+
+// 嵌套对象按 "user.name" 这样的路径展开成列，数组按 arrays 处理
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlattenOptions {
+    #[serde(default)]
+    pub arrays: ArrayFlattenMode,
+    pub delimiter: Option<String>, // Join 模式使用，默认为 "; "
+    #[serde(default)]
+    pub headers: HeaderDiscovery,
+    #[serde(default)]
+    pub explode_path: Option<String>, // Explode 模式必填，如 "events"
+}
+
+// 导出文件的位置：file_path 为完整路径，优先于 directory；两者都为空时写入默认导出目录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportTarget {
+    pub directory: Option<String>,
+    pub file_path: Option<String>,
+    #[serde(default)]
+    pub overwrite: bool, // 为 false 时遇到同名文件自动追加 " (1)" 这样的序号
+}
+
+// 对单个字段的脱敏处理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RedactionAction {
+    Drop,
+    Hash, // 加盐 SHA-256，输出十六进制
+    Mask {
+        keep_last: usize,
+        mask_char: Option<char>, // 默认为 *
+    },
+    Replace {
+        pattern: String, // 正则表达式
+        replacement: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRule {
+    pub field: String, // _source 中的字段路径，如 "user.email"；表格导出时为列名
+    pub action: RedactionAction,
+}
+
+// 保存在应用设置中的一组脱敏规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionProfile {
+    pub name: String,
+    pub rules: Vec<RedactionRule>,
+}
+
+// 导出时使用的脱敏规则：profile 引用已保存的规则集，rules 追加在其后
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+    pub profile: Option<String>,
+    pub salt: Option<String>, // 哈希用的盐，为空时每次导出随机生成
+}
+
+// 保存在应用数据目录 settings.json 中的应用设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppSettings {
+    #[serde(default)]
+    pub default_export_directory: Option<String>, // 为空时使用 ~/Documents/ES_Client_Exports
+    #[serde(default)]
+    pub redaction_profiles: Vec<RedactionProfile>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkExportOptions {
+    pub target_index: Option<String>, // 改写动作行中的 _index，为空时保留原索引
+    #[serde(default)]
+    pub include_routing: bool,
+    pub action: Option<String>, // "index"（默认）或 "create"
+}
+
+// 文本格式（JSON、NDJSON、CSV、Bulk）导出文件的压缩方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExportCompression {
+    Gzip,
+    Zstd,
+}
+
+// 按记录数或字节数把导出拆分成多个文件，任一条件达到即切换到下一个分片
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportSplitOptions {
+    pub max_records: Option<u64>,
+    pub max_bytes: Option<u64>, // 以写入磁盘（压缩后）的字节数计算
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPart {
+    pub file: String, // 相对清单文件所在目录的文件名
+    pub records: u64,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+// 拆分导出时与分片写在同一目录下的清单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub format: ExportFormat,
+    pub compression: Option<ExportCompression>,
+    pub total_records: u64,
+    pub parts: Vec<ExportPart>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -341,7 +702,106 @@ pub struct ExportRequest {
     pub format: ExportFormat,
     pub filename: String,
     pub selected_fields: Option<Vec<String>>,
-    pub max_records: Option<u64>,
+    pub max_records: Option<u64>, // 为空时导出全部命中
+    #[serde(default)]
+    pub job_id: Option<String>,
+    #[serde(default)]
+    pub bulk_options: Option<BulkExportOptions>,
+    #[serde(default)]
+    pub compression: Option<ExportCompression>,
+    #[serde(default)]
+    pub split: Option<ExportSplitOptions>,
+    #[serde(default)]
+    pub target: ExportTarget,
+    #[serde(default)]
+    pub flatten: Option<FlattenOptions>, // 仅用于 CSV/Excel，为空时只展开顶层字段
+    #[serde(default)]
+    pub redaction: Option<RedactionConfig>,
+}
+
+// 将 SQL 查询的全部结果（跟随游标）导出到文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqlExportRequest {
+    pub connection_id: String,
+    pub job_id: Option<String>, // 前端可指定，用于取消任务和匹配进度事件
+    pub query: SqlQuery,
+    pub format: ExportFormat,
+    pub filename: String,
+    #[serde(default)]
+    pub target: ExportTarget,
+    #[serde(default)]
+    pub redaction: Option<RedactionConfig>,
+}
+
+// 通过 "export-progress" 事件推送给前端的导出进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportProgress {
+    pub job_id: String,
+    pub records_written: u64,
+    pub pages: u32,
+    pub finished: bool,
+    #[serde(default)]
+    pub bytes_written: u64, // 列式格式和 Excel 在收尾前为 0
+    #[serde(default)]
+    pub total_records: Option<u64>, // 预计导出的总条数，无法得知时为空
+    #[serde(default)]
+    pub records_per_second: f64,
+    #[serde(default)]
+    pub eta_seconds: Option<u64>,
+    #[serde(default)]
+    pub checkpoint_path: Option<String>, // 最近一次写出的断点文件，可用于续传
+}
+
+// 可续传导出的断点：记录已完整写入的字节数和最后一条命中的排序值，
+// 续传时把文件截断到该位置，再用 search_after 从下一条命中继续追加
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportCheckpoint {
+    // 脱敏的盐以明文固定在这里，续传部分的哈希与之前一致；
+    // 拿到断点文件就能对哈希值做字典比对，导出完成后断点会被删除
+    pub request: ExportRequest,
+    pub file_path: String,
+    pub records_written: u64,
+    pub bytes_written: u64,
+    pub search_after: serde_json::Value,
+    #[serde(default)]
+    pub headers: Option<Vec<String>>, // CSV 的表头，续传时不再重新推断
+    pub updated_at: String,
+}
+
+// 保存在应用数据目录中的导出预设；request.filename 为文件名模板，
+// 可使用 {index}、{preset}、{date}、{time}、{datetime} 占位符
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPreset {
+    #[serde(default)]
+    pub id: String, // 新建时为空，保存时生成
+    pub name: String,
+    pub request: ExportRequest,
+    #[serde(default)]
+    pub schedule: Option<String>, // 5 段 cron 表达式（本地时间），为空时只能手动运行
+    #[serde(default)]
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportRunTrigger {
+    Manual,
+    Schedule,
+}
+
+// 预设的一次运行记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRunRecord {
+    pub id: String,
+    pub preset_id: String,
+    pub preset_name: String,
+    pub trigger: ExportRunTrigger,
+    pub started_at: String,
+    pub finished_at: String,
+    pub success: bool,
+    pub total_records: u64,
+    pub file_path: Option<String>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -350,6 +810,12 @@ pub struct ExportResult {
     pub file_path: String,
     pub total_records: u64,
     pub message: String,
+    #[serde(default)]
+    pub manifest_path: Option<String>, // 拆分导出时的清单文件
+    #[serde(default)]
+    pub redacted_values: u64, // 被脱敏规则处理的值的个数
+    #[serde(default)]
+    pub warnings: Vec<String>, // 如表头确定之后才出现、未写入文件的字段
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -382,6 +848,8 @@ pub struct BulkOperation {
     pub index: String,
     pub id: Option<String>,
     pub document: Option<serde_json::Value>,
+    #[serde(default)]
+    pub routing: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -448,13 +916,34 @@ pub struct TemplateRequest {
     pub template: IndexTemplate,
 }
 
+// 存储在 _scripts 中的 mustache 搜索模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchTemplate {
+    pub id: String,
+    pub source: serde_json::Value, // 可以是 JSON 对象，也可以是 mustache 字符串
+}
+
+// 搜索模板的渲染/执行请求，id 与 source 二选一
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchTemplateQuery {
+    pub index: String,
+    pub id: Option<String>,
+    pub source: Option<serde_json::Value>,
+    pub params: Option<HashMap<String, serde_json::Value>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AggregationConfig {
     pub id: String,
     pub name: String,
     pub r#type: String, // "type" is a keyword in Rust, so we use raw identifier
-    pub field: String,
+    // filters、top_hits 以及管道聚合等不需要字段
+    #[serde(default)]
+    pub field: Option<String>,
     pub params: Option<serde_json::Value>,
+    // 管道聚合引用的路径：字符串，或 bucket_script/bucket_selector 的 {变量名: 路径}
+    #[serde(default)]
+    pub buckets_path: Option<serde_json::Value>,
     pub sub_aggregations: Option<Vec<AggregationConfig>>,
 }
 
@@ -474,10 +963,173 @@ pub struct AggregationResult {
     pub aggregations: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoBoundingBox {
+    pub top_left: GeoPoint,
+    pub bottom_right: GeoPoint,
+}
+
+// 地理查询，按 type 区分：distance / bounding_box / shape
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GeoQuery {
+    Distance {
+        field: String,
+        center: GeoPoint,
+        distance: String, // 如 "5km"
+        distance_type: Option<String>,
+    },
+    BoundingBox {
+        field: String,
+        bounds: GeoBoundingBox,
+    },
+    Shape {
+        field: String,
+        shape: serde_json::Value, // GeoJSON
+        relation: Option<String>, // intersects / disjoint / within / contains
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoGridRequest {
+    pub index: String,
+    pub field: String,
+    pub query: Option<serde_json::Value>,
+    pub grid_type: String, // "geohash_grid" 或 "geotile_grid"
+    pub precision: Option<u32>,
+    pub size: Option<u32>,
+}
+
+// 解码后的网格单元，可直接在地图上绘制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoCell {
+    pub key: String,
+    pub doc_count: u64,
+    pub center: GeoPoint,
+    pub bounds: GeoBoundingBox,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeoGridResult {
+    pub took: u64,
+    pub total: u64,
+    pub cells: Vec<GeoCell>,
+    pub bounds: Option<GeoBoundingBox>,
+    pub centroid: Option<GeoPoint>,
+}
+
+// composite 聚合的一个来源，type 为 terms / histogram / date_histogram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeSource {
+    pub name: String,
+    pub r#type: String,
+    pub field: String,
+    pub params: Option<serde_json::Value>, // interval、calendar_interval、order 等
+    pub missing_bucket: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeAggregationRequest {
+    pub index: String,
+    pub query: Option<serde_json::Value>,
+    pub sources: Vec<CompositeSource>,
+    pub page_size: Option<u32>,
+    pub sub_aggregations: Option<Vec<AggregationConfig>>,
+    pub after: Option<serde_json::Value>, // 上一页返回的 after_key
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeBucket {
+    pub key: serde_json::Map<String, serde_json::Value>,
+    pub doc_count: u64,
+    pub metrics: serde_json::Map<String, serde_json::Value>, // 子聚合结果
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositePage {
+    pub buckets: Vec<CompositeBucket>,
+    pub after_key: Option<serde_json::Value>, // 为空表示已取完
+}
+
+// 通过 "composite-batch" 事件分批推送给前端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeBatch {
+    pub job_id: String,
+    pub buckets: Vec<CompositeBucket>,
+    pub total_buckets: u64,
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeExportRequest {
+    pub connection_id: String,
+    pub job_id: Option<String>,
+    pub request: CompositeAggregationRequest,
+    pub format: ExportFormat,
+    pub filename: String,
+    #[serde(default)]
+    pub target: ExportTarget,
+    #[serde(default)]
+    pub redaction: Option<RedactionConfig>,
+}
+
+// 聚合结果展开后的表格
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregationTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChartSeries {
+    pub name: String,
+    pub data: Vec<Option<f64>>, // 与 categories 一一对应，缺失为 null
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregationChart {
+    pub categories: Vec<serde_json::Value>,
+    pub series: Vec<ChartSeries>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlattenedAggregation {
+    pub table: AggregationTable,
+    pub chart: AggregationChart,
+    #[serde(default)]
+    pub warnings: Vec<String>, // 如同一层未展开的其他桶聚合
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AggregationView {
+    Table,
+    Chart,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregationExportRequest {
+    pub connection_id: String,
+    pub request: AggregationRequest,
+    pub view: AggregationView,
+    pub format: ExportFormat,
+    pub filename: String,
+    #[serde(default)]
+    pub target: ExportTarget,
+    #[serde(default)]
+    pub redaction: Option<RedactionConfig>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ImportFormat {
     JSON,
     CSV,
+    Bulk, // 导出的 _bulk 文件，保留其中的 _id 和 routing
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/tauri'
-import type { EsConnection, IndexInfo, SearchQuery, SearchResult, ClusterHealth, ExportRequest, ExportResult, DocumentRequest, DocumentResponse, GetDocumentResponse, BulkRequest, BulkResponse, IndexSettings, AliasRequest, TemplateRequest, AggregationRequest, AggregationResult, SqlQuery, SqlResult, NodeInfo, NodeStats, ImportRequest, ImportResult } from '../types'
+import type { EsConnection, IndexInfo, SearchQuery, SearchResult, ClusterHealth, ExportRequest, ExportResult, DocumentRequest, DocumentResponse, GetDocumentResponse, BulkRequest, BulkResponse, IndexSettings, AliasRequest, TemplateRequest, AggregationRequest, AggregationResult, SqlQuery, SqlResult, NodeInfo, NodeStats, ImportRequest, ImportResult, AppSettings, ExportPreset, ExportRunRecord, IndexFieldInfo } from '../types'
 
 export class TauriApi {
   // 连接管理
@@ -37,7 +37,7 @@ export class TauriApi {
     return await invoke('get_index_mapping', { connectionId, index })
   }
 
-  static async getFieldNames(connectionId: string, index: string): Promise<string[]> {
+  static async getFieldNames(connectionId: string, index: string): Promise<IndexFieldInfo[]> {
     return await invoke('get_field_names', { connectionId, index })
   }
 
@@ -74,10 +74,49 @@ export class TauriApi {
     return await invoke('export_search_results', { request })
   }
 
+  // 从断点文件续传，进度同样通过 export-progress 事件推送
+  static async resumeSearchExport(checkpointPath: string, jobId?: string): Promise<ExportResult> {
+    return await invoke('resume_search_export', { checkpointPath, jobId })
+  }
+
+  static async cancelExportJob(jobId: string): Promise<boolean> {
+    return await invoke('cancel_export_job', { jobId })
+  }
+
   static async getExportDirectory(): Promise<string> {
     return await invoke('get_export_directory')
   }
 
+  // 应用设置
+  static async getAppSettings(): Promise<AppSettings> {
+    return await invoke('get_app_settings')
+  }
+
+  static async updateAppSettings(settings: AppSettings): Promise<AppSettings> {
+    return await invoke('update_app_settings', { newSettings: settings })
+  }
+
+  // 导出预设与定时导出
+  static async listExportPresets(): Promise<ExportPreset[]> {
+    return await invoke('list_export_presets')
+  }
+
+  static async saveExportPreset(preset: ExportPreset): Promise<ExportPreset> {
+    return await invoke('save_export_preset', { preset })
+  }
+
+  static async deleteExportPreset(id: string): Promise<boolean> {
+    return await invoke('delete_export_preset', { id })
+  }
+
+  static async runExportPreset(id: string): Promise<ExportRunRecord> {
+    return await invoke('run_export_preset', { id })
+  }
+
+  static async getExportRunHistory(presetId?: string): Promise<ExportRunRecord[]> {
+    return await invoke('get_export_run_history', { presetId })
+  }
+
   // 文档操作
   static async createDocument(connectionId: string, request: DocumentRequest): Promise<DocumentResponse> {
     return await invoke('create_document', { connectionId, request })
@@ -1,4 +1,4 @@
-import type { EsConnection, IndexInfo, SearchQuery, SearchResult, ClusterHealth, ExportRequest, ExportResult, DocumentRequest, DocumentResponse, GetDocumentResponse, BulkRequest, BulkResponse, IndexSettings, AliasRequest, TemplateRequest, AggregationRequest, AggregationResult, SqlQuery, SqlResult, NodeInfo, NodeStats, ImportRequest, ImportResult } from '../types'
+import type { EsConnection, IndexInfo, SearchQuery, SearchResult, ClusterHealth, ExportRequest, ExportResult, DocumentRequest, DocumentResponse, GetDocumentResponse, BulkRequest, BulkResponse, IndexSettings, AliasRequest, TemplateRequest, AggregationRequest, AggregationResult, SqlQuery, SqlResult, NodeInfo, NodeStats, ImportRequest, ImportResult, IndexFieldInfo } from '../types'
 
 // Web版本的API实现 - 使用axios直接调用ES API
 export class WebApi {
@@ -139,24 +139,33 @@ export class WebApi {
     return await response.json()
   }
 
-  static async getFieldNames(connectionId: string, index: string): Promise<string[]> {
+  static async getFieldNames(connectionId: string, index: string): Promise<IndexFieldInfo[]> {
     // For web version, we extract field names from mapping on the frontend
     const mapping = await this.getIndexMapping(connectionId, index)
-    const fieldNames: string[] = []
+    const fields: IndexFieldInfo[] = []
+    
+    const toFieldInfo = (name: string, def: any): IndexFieldInfo => {
+      const fieldType = def?.type ?? (def?.properties ? 'object' : 'unknown')
+      if (fieldType !== 'dense_vector') {
+        return { name, field_type: fieldType }
+      }
+      // Indexed vectors default to cosine similarity in 8.x
+      return { name, field_type: fieldType, dims: def.dims, similarity: def.similarity ?? 'cosine' }
+    }
     
     const extractFields = (properties: any, prefix = '') => {
       if (!properties || typeof properties !== 'object') return
       
       for (const [fieldName, fieldDef] of Object.entries(properties)) {
         const fullName = prefix ? `${prefix}.${fieldName}` : fieldName
-        fieldNames.push(fullName)
+        fields.push(toFieldInfo(fullName, fieldDef))
         
         if (typeof fieldDef === 'object' && fieldDef !== null) {
           const def = fieldDef as any
           
           // Add keyword subfield for text fields
           if (def.type === 'text') {
-            fieldNames.push(`${fullName}.keyword`)
+            fields.push({ name: `${fullName}.keyword`, field_type: 'keyword' })
           }
           
           // Recurse into nested properties
@@ -180,10 +189,17 @@ export class WebApi {
     })
     
     // Add common meta fields
-    fieldNames.push('_id', '_index', '_type', '_score', '_source', '@timestamp')
+    for (const name of ['_id', '_index', '_type', '_score', '_source']) {
+      fields.push({ name, field_type: 'metadata' })
+    }
+    fields.push({ name: '@timestamp', field_type: 'date' })
     
-    // Sort and dedupe
-    return [...new Set(fieldNames)].sort()
+    // Sort and dedupe, keeping the mapped definition of a field
+    const unique = new Map<string, IndexFieldInfo>()
+    for (const field of fields) {
+      if (!unique.has(field.name)) unique.set(field.name, field)
+    }
+    return [...unique.values()].sort((a, b) => a.name.localeCompare(b.name))
   }
 
   static async createIndex(connectionId: string, index: string, mapping?: any): Promise<any> {
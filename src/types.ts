@@ -25,6 +25,81 @@ export interface SearchQuery {
   from?: number
   size?: number
   sort?: any[]
+  source?: boolean | string[] | { includes?: string[]; excludes?: string[] }
+  highlight?: any
+  track_total_hits?: boolean | number
+  aggs?: any
+  collapse?: any
+  post_filter?: any
+  docvalue_fields?: any[]
+  stored_fields?: string[]
+  runtime_mappings?: any
+  timeout?: string
+  knn?: KnnQuery[]
+  rrf?: RrfConfig
+  [key: string]: any
+}
+
+export interface KnnQuery {
+  field: string
+  query_vector: number[]
+  k: number
+  num_candidates: number
+  filter?: any
+  similarity?: number
+  boost?: number
+}
+
+export interface RrfConfig {
+  rank_constant?: number
+  rank_window_size?: number
+}
+
+export interface GeoPoint {
+  lat: number
+  lon: number
+}
+
+export interface GeoBoundingBox {
+  top_left: GeoPoint
+  bottom_right: GeoPoint
+}
+
+export type GeoQuery =
+  | { type: 'distance'; field: string; center: GeoPoint; distance: string; distance_type?: string }
+  | { type: 'bounding_box'; field: string; bounds: GeoBoundingBox }
+  | { type: 'shape'; field: string; shape: any; relation?: string }
+
+export interface GeoGridRequest {
+  index: string
+  field: string
+  query?: any
+  grid_type: 'geohash_grid' | 'geotile_grid'
+  precision?: number
+  size?: number
+}
+
+export interface GeoCell {
+  key: string
+  doc_count: number
+  center: GeoPoint
+  bounds: GeoBoundingBox
+}
+
+export interface GeoGridResult {
+  took: number
+  total: number
+  cells: GeoCell[]
+  bounds?: GeoBoundingBox
+  centroid?: GeoPoint
+}
+
+// 对应后端的 FieldInfo，dense_vector 字段附带维度与相似度（查询构建器另有自己的 FieldInfo）
+export interface IndexFieldInfo {
+  name: string
+  field_type: string
+  dims?: number
+  similarity?: string
 }
 
 export interface SqlQuery {
@@ -32,6 +107,11 @@ export interface SqlQuery {
   fetch_size?: number
   request_timeout?: string
   page_timeout?: string
+  params?: any[]
+  filter?: any
+  time_zone?: string
+  field_multi_value_leniency?: boolean
+  columnar?: boolean
 }
 
 export interface SqlColumn {
@@ -43,6 +123,48 @@ export interface SqlResult {
   columns: SqlColumn[]
   rows: any[][]
   cursor?: string
+  values?: any[][]
+}
+
+export type SqlFormat = 'CSV' | 'TSV' | 'TXT'
+
+export interface SqlExportRequest {
+  connection_id: string
+  job_id?: string
+  query: SqlQuery
+  format: ExportFormat
+  filename: string
+  target?: ExportTarget
+  redaction?: RedactionConfig
+}
+
+export interface ExportProgress {
+  job_id: string
+  records_written: number
+  pages: number
+  finished: boolean
+  bytes_written: number // 列式格式和 Excel 在收尾前为 0
+  total_records?: number
+  records_per_second: number
+  eta_seconds?: number
+  checkpoint_path?: string // 最近一次写出的断点文件，可用于续传
+}
+
+// 可续传导出的断点
+export interface ExportCheckpoint {
+  request: ExportRequest
+  file_path: string
+  records_written: number
+  bytes_written: number
+  search_after: any[]
+  headers?: string[]
+  updated_at: string
+}
+
+export interface SqlDownloadResult {
+  file_path: string
+  bytes_written: number
+  pages: number
 }
 
 export interface SearchResult {
@@ -51,6 +173,17 @@ export interface SearchResult {
   took: number
   timed_out: boolean
   aggregations?: any
+  total_relation?: 'eq' | 'gte'
+  max_score?: number
+  shards?: ShardStats
+  highlights?: Record<string, string[]>[]
+}
+
+export interface ShardStats {
+  total: number
+  successful: number
+  skipped: number
+  failed: number
 }
 
 export interface ClusterHealth {
@@ -233,7 +366,72 @@ export interface NodeHttpStats {
 export enum ExportFormat {
   JSON = 'JSON',
   CSV = 'CSV',
-  Excel = 'Excel'
+  Excel = 'Excel',
+  NDJSON = 'NDJSON',
+  Bulk = 'Bulk',
+  Parquet = 'Parquet',
+  ArrowIpc = 'ArrowIpc'
+}
+
+export enum ArrayFlattenMode {
+  Json = 'Json',
+  Join = 'Join',
+  Explode = 'Explode',
+  Index = 'Index'
+}
+
+export enum HeaderDiscovery {
+  Sample = 'Sample',
+  Mapping = 'Mapping',
+  FullScan = 'FullScan'
+}
+
+export interface FlattenOptions {
+  arrays?: ArrayFlattenMode
+  delimiter?: string // Join 模式使用，默认 "; "
+  headers?: HeaderDiscovery
+  explode_path?: string // Explode 模式必填，只展开这一个数组，其余数组按 Join 处理
+}
+
+// file_path 为完整路径，优先于 directory；都不填时写入默认导出目录
+export interface ExportTarget {
+  directory?: string
+  file_path?: string
+  overwrite?: boolean // 不覆盖时同名文件自动追加序号
+}
+
+export type RedactionAction =
+  | { type: 'drop' }
+  | { type: 'hash' } // 加盐 SHA-256
+  | { type: 'mask'; keep_last: number; mask_char?: string }
+  | { type: 'replace'; pattern: string; replacement: string }
+
+export interface RedactionRule {
+  field: string // 如 user.email；表格导出时为列名
+  action: RedactionAction
+}
+
+export interface RedactionProfile {
+  name: string
+  rules: RedactionRule[]
+}
+
+// profile 引用已保存的规则集，rules 追加在其后
+export interface RedactionConfig {
+  rules?: RedactionRule[]
+  profile?: string
+  salt?: string // 为空时每次导出随机生成
+}
+
+export interface AppSettings {
+  default_export_directory?: string
+  redaction_profiles?: RedactionProfile[]
+}
+
+export interface BulkExportOptions {
+  target_index?: string
+  include_routing?: boolean
+  action?: 'index' | 'create'
 }
 
 export interface ExportRequest {
@@ -242,7 +440,38 @@ export interface ExportRequest {
   format: ExportFormat
   filename: string
   selected_fields?: string[]
-  max_records?: number
+  max_records?: number // 不填则导出全部
+  job_id?: string
+  bulk_options?: BulkExportOptions
+  compression?: ExportCompression
+  split?: ExportSplitOptions
+  target?: ExportTarget
+  flatten?: FlattenOptions // 仅用于 CSV/Excel
+  redaction?: RedactionConfig
+}
+
+// 导出预设；request.filename 为文件名模板，支持 {index}、{preset}、{date}、{time}、{datetime}
+export interface ExportPreset {
+  id?: string // 新建时为空
+  name: string
+  request: ExportRequest
+  schedule?: string // 5 段 cron：分 时 日 月 周（本地时间）
+  paused?: boolean
+}
+
+export type ExportRunTrigger = 'manual' | 'schedule'
+
+export interface ExportRunRecord {
+  id: string
+  preset_id: string
+  preset_name: string
+  trigger: ExportRunTrigger
+  started_at: string
+  finished_at: string
+  success: boolean
+  total_records: number
+  file_path?: string
+  error?: string
 }
 
 export interface ExportResult {
@@ -250,6 +479,33 @@ export interface ExportResult {
   file_path: string
   total_records: number
   message: string
+  manifest_path?: string // 拆分导出时的清单文件
+  redacted_values: number
+  warnings?: string[] // 如表头确定之后才出现、未写入文件的字段
+}
+
+export enum ExportCompression {
+  Gzip = 'Gzip',
+  Zstd = 'Zstd'
+}
+
+export interface ExportSplitOptions {
+  max_records?: number
+  max_bytes?: number // 按压缩后的文件大小计算
+}
+
+export interface ExportPart {
+  file: string
+  records: number
+  bytes: number
+  sha256: string
+}
+
+export interface ExportManifest {
+  format: ExportFormat
+  compression?: ExportCompression
+  total_records: number
+  parts: ExportPart[]
 }
 
 export interface DocumentRequest {
@@ -384,11 +640,68 @@ export interface AggregationConfig {
   id: string
   name: string
   type: string // terms, date_histogram, histogram, range, avg, sum, count, max, min
-  field: string
+  field?: string // filters、top_hits 及管道聚合不需要字段
   params?: any // 聚合参数
+  buckets_path?: string | Record<string, string> // 管道聚合引用的路径
   subAggregations?: AggregationConfig[]
 }
 
+export interface AggregationTable {
+  columns: string[]
+  rows: any[][]
+}
+
+export interface ChartSeries {
+  name: string
+  data: (number | null)[]
+}
+
+export interface AggregationChart {
+  categories: any[]
+  series: ChartSeries[]
+}
+
+export interface FlattenedAggregation {
+  table: AggregationTable
+  chart: AggregationChart
+  warnings?: string[] // 如同一层未展开的其他桶聚合
+}
+
+export interface CompositeSource {
+  name: string
+  type: 'terms' | 'histogram' | 'date_histogram'
+  field: string
+  params?: any
+  missing_bucket?: boolean
+}
+
+export interface CompositeAggregationRequest {
+  index: string
+  query?: any
+  sources: CompositeSource[]
+  page_size?: number
+  sub_aggregations?: AggregationConfig[]
+  after?: Record<string, any>
+}
+
+export interface CompositeBucket {
+  key: Record<string, any>
+  doc_count: number
+  metrics: Record<string, any>
+}
+
+export interface CompositePage {
+  buckets: CompositeBucket[]
+  after_key?: Record<string, any>
+}
+
+export interface CompositeBatch {
+  job_id: string
+  buckets: CompositeBucket[]
+  total_buckets: number
+  finished: boolean
+}
+
 export interface AggregationRequest {
   index: string
   query?: any
@@ -441,7 +754,8 @@ export interface QueryHistoryFilter {
 
 export enum ImportFormat {
   JSON = 'JSON',
-  CSV = 'CSV'
+  CSV = 'CSV',
+  Bulk = 'Bulk'
 }
 
 export interface ImportRequest {